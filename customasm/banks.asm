@@ -0,0 +1,20 @@
+; Generated from platform.ron by `platform banks`, edit that and regenerate instead of editing this.
+
+#bankdef code {
+    #outp 0*8
+    #addr 0x0000
+    #size 0x0400
+    #bits 8
+    #labelalign 8
+    #fill
+}
+
+#bankdef ram {
+    #addr 0x0400
+    #size 0xfbd0
+    #bits 8
+    #labelalign 8
+}
+
+MMIO_MMU = 0xffd0
+MMIO_SEMIHOSTING = 0xfff0
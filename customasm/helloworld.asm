@@ -15,9 +15,8 @@ char_loop:
     jmpz %3
 
     ; Increment the data and variable pointers to point to the next character
-    loadi %3, $1
-    add %0, %3
-    add %1, %3
+    addi %0, $1
+    addi %1, $1
 
     ; Jump back to the beginning of the loop
     loadi %3, $char_loop
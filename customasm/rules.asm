@@ -1,19 +1,7 @@
 #once
 
-#bankdef code {
-    #outp 0*8
-    #addr 0
-    #size 1024
-    #bits 8
-    #labelalign 8 ; Only 8-bit alignment should be required, this is just a workaround for endianness problems in the emulator.
-    #fill
-}
-
-#bankdef ram {
-    #addr       1024
-    #addr_end   65536
-    #bits       8
-}
+; Banks of the memory regions in platform.ron.
+#include "banks.asm"
 
 #fn instr       (opcode)        => instr_rr(opcode, %0, %0)
 #fn instr_r     (opcode, ra)     => instr_rr(opcode, ra, %0)
@@ -39,11 +27,13 @@
     jmpc {addr: reg}                        => instr_r  (6, addr)               ; if carry { PC = %addr }
     jmpz {addr: reg}                        => instr_r  (7, addr)               ; if zero { PC = %addr }
 
+    ; Flags are set from the full 17-bit result, carry being its top bit for additions and the borrow for
+    ; subtractions, so addc and subc chain multi-word arithmetic from the least significant word up.
     add {a: reg}, {b: reg}                  => instr_rr (8, a, b)               ; %a = %a + %b
     sub {a: reg}, {b: reg}                  => instr_rr (9, a, b)               ; %a = %a - %b
     ; 10, 11 reserved for mul and div
-    addc {a: reg}, {b: reg}                 => instr_rr (12, a, b)              ; %a = %a + (%b + carry)
-    subc {a: reg}, {b: reg}                 => instr_rr (13, a, b)              ; %a = %a - (%b + carry)
+    addc {a: reg}, {b: reg}                 => instr_rr (12, a, b)              ; carry:%a = %a + %b + carry
+    subc {a: reg}, {b: reg}                 => instr_rr (13, a, b)              ; borrow:%a = %a - %b - carry
     ; 14 reserved for mulc
 
     and {a: reg}, {b: reg}                  => instr_rr (15, a, b)              ; %a = %a & %b
@@ -58,4 +48,53 @@
     storel {dest_addr: reg}, {src: reg}     => instr_rr (25, dest_addr, src)    ; high MEM[%dest_addr] = low %src
 
     halt                                    => instr    (26)                    ; Stops linear code execution
+
+    ; Immediate forms of the ALU instructions, taking the B operand from the immediate word.
+    addi {a: reg}, {value: imm}             => instr_r  (27, a) @ value         ; %a = %a + $value
+    subi {a: reg}, {value: imm}             => instr_r  (28, a) @ value         ; %a = %a - $value
+    andi {a: reg}, {value: imm}             => instr_r  (29, a) @ value         ; %a = %a & $value
+
+    ; Jumps to an immediate address, and jumps relative to the next instruction taking the absolute target address
+    ; in source but encoding it as a signed offset, for position independent code.
+    jmpi {addr: imm}                        => instr    (30) @ addr             ; PC = $addr
+    jmpci {addr: imm}                       => instr    (31) @ addr             ; if carry { PC = $addr }
+    jmpzi {addr: imm}                       => instr    (32) @ addr             ; if zero { PC = $addr }
+    jmpr {addr: imm}                        => instr    (33) @ (addr - $ - 4)`16  ; PC = $addr
+    jmpcr {addr: imm}                       => instr    (34) @ (addr - $ - 4)`16  ; if carry { PC = $addr }
+    jmpzr {addr: imm}                       => instr    (35) @ (addr - $ - 4)`16  ; if zero { PC = $addr }
+
+    ; Loads and stores addressing memory by the address register plus an immediate offset, e.g. for struct fields.
+    loado {dest: reg}, {src_addr: reg}, {offset: imm}   => instr_rr (36, dest, src_addr) @ offset   ; %dest = MEM[%src_addr + $offset]
+    storeo {dest_addr: reg}, {src: reg}, {offset: imm}  => instr_rr (37, dest_addr, src) @ offset   ; MEM[%dest_addr + $offset] = %src
+    loadho {dest: reg}, {src_addr: reg}, {offset: imm}  => instr_rr (38, dest, src_addr) @ offset   ; high %dest = high MEM[%src_addr + $offset]
+    loadlo {dest: reg}, {src_addr: reg}, {offset: imm}  => instr_rr (39, dest, src_addr) @ offset   ; low %dest = high MEM[%src_addr + $offset]
+    storeho {dest_addr: reg}, {src: reg}, {offset: imm} => instr_rr (40, dest_addr, src) @ offset   ; high MEM[%dest_addr + $offset] = high %src
+    storelo {dest_addr: reg}, {src: reg}, {offset: imm} => instr_rr (41, dest_addr, src) @ offset   ; high MEM[%dest_addr + $offset] = low %src
+
+    ; Jumps on the sign of the result and on signed overflow, and on signed less-than after a subtraction.
+    jmpn {addr: reg}                        => instr_r  (42, addr)              ; if negative { PC = %addr }
+    jmpo {addr: reg}                        => instr_r  (43, addr)              ; if overflow { PC = %addr }
+    jmpl {addr: reg}                        => instr_r  (44, addr)              ; if negative != overflow { PC = %addr }
+    jmpni {addr: imm}                       => instr    (45) @ addr             ; if negative { PC = $addr }
+    jmpoi {addr: imm}                       => instr    (46) @ addr             ; if overflow { PC = $addr }
+    jmpli {addr: imm}                       => instr    (47) @ addr             ; if negative != overflow { PC = $addr }
+    jmpnr {addr: imm}                       => instr    (48) @ (addr - $ - 4)`16  ; if negative { PC = $addr }
+    jmpor {addr: imm}                       => instr    (49) @ (addr - $ - 4)`16  ; if overflow { PC = $addr }
+    jmplr {addr: imm}                       => instr    (50) @ (addr - $ - 4)`16  ; if negative != overflow { PC = $addr }
+
+    ; Flags as data, bit 0 being carry, 1 zero, 2 negative and 3 overflow, e.g. for saving them around a handler.
+    getf {dest: reg}                        => instr_r  (51, dest)              ; %dest = FLAGS
+    setf {src: reg}                         => instr_r  (52, src)               ; FLAGS = %src
+    cmp {a: reg}, {b: reg}                  => instr_rr (53, a, b)              ; FLAGS of %a - %b
+    cmpi {a: reg}, {value: imm}             => instr_r  (54, a) @ value         ; FLAGS of %a - $value
+
+    ; Privileged instructions, including halt, trap to the handler at the trap vector in user mode, as do fetches,
+    ; loads and stores in the protected region or the MMIO device registers.
+    ;
+    ; Control registers: 0 status, 1 epc, 2 cause, 3 trap vector, 4 fault address, 5 and 6 start and end of the
+    ; protected region.
+    sys                                     => instr    (55)                    ; EPC = $ + 2, supervisor, PC = TRAP_VECTOR
+    sret                                    => instr    (56)                    ; PC = EPC, mode = previous mode
+    getcr {dest: reg}, {cr: imm}            => instr_r  (57, dest) @ cr         ; %dest = CR[$cr]
+    setcr {src: reg}, {cr: imm}             => instr_r  (58, src) @ cr          ; CR[$cr] = %src
 }
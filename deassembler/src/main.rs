@@ -1,15 +1,156 @@
-use std::{env, fs, path::PathBuf, process::exit};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::exit,
+};
 
-use libdeassembler::Deassembler;
+use clap::{Parser, Subcommand};
+use libdeassembler::{cfg::ControlFlowGraph, diff::ListingDiff, listing::Listing, text::Syntax};
+use libisa::{
+    executable::Executable,
+    memimage::{self, ImageFormat, ImageLayout},
+    mmu::BankMap,
+    Word,
+};
+use libplatform::Platform;
+
+#[derive(Parser, Debug)]
+#[command(
+    version,
+    about,
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true
+)]
+pub struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    #[arg(required = true)]
+    program_path: Option<PathBuf>,
+
+    /// Only deassemble code reachable from the entry point, showing everything else as data.
+    #[arg(short, long)]
+    traverse: bool,
+
+    /// Entry point address for traversal, by default the entry point of an executable, or 0 for anything else.
+    #[arg(short, long)]
+    entry: Option<Word>,
+
+    /// Output the control flow graph in Graphviz DOT format instead of a listing.
+    #[arg(long)]
+    dot: bool,
+
+    /// Output customasm source that reassembles into the same binary instead of a listing.
+    #[arg(long, requires = "platform")]
+    emit_asm: bool,
+
+    /// Platform description the customasm banks are generated from, like customasm/platform.ron, whose code region
+    /// the source is assembled into.
+    #[arg(long)]
+    platform: Option<PathBuf>,
+
+    /// Syntax flavour of the listing: customasm, verbose or ansi.
+    #[arg(short, long, default_value_t = Syntax::Customasm)]
+    syntax: Syntax,
+
+    /// Treat the program as an image of physical memory seen through the MMU banks of the windows, given as
+    /// comma separated bank numbers, showing physical addresses in the listing.
+    #[arg(long)]
+    banks: Option<BankMap>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Compare two programs instruction by instruction, exiting with status 1 if they differ.
+    Diff {
+        old_path: PathBuf,
+        new_path: PathBuf,
+
+        /// Syntax flavour of the instructions: customasm, verbose or ansi.
+        #[arg(short, long, default_value_t = Syntax::Customasm)]
+        syntax: Syntax,
+    },
+}
 
 fn main() {
-    let path: PathBuf = env::args().skip(1).collect();
+    let args = Args::parse();
+
+    if let Some(Command::Diff {
+        old_path,
+        new_path,
+        syntax,
+    }) = &args.command
+    {
+        let old = Listing::linear(&read_program(old_path).0);
+        let new = Listing::linear(&read_program(new_path).0);
+        let diff = ListingDiff::new(&old, &new);
+
+        print!("{}", diff.format(&old, &new, syntax.formatter()));
+        exit(if diff.has_changes() { 1 } else { 0 });
+    }
+
+    let program_path = args
+        .program_path
+        .as_ref()
+        .expect("Program path is required by clap without a subcommand");
+    let (program, executable_entry) = read_program(program_path);
+    let entry = args.entry.or(executable_entry).unwrap_or(0);
+    let program = match &args.banks {
+        Some(bank_map) => bank_map.logical_view(&program),
+        None => program,
+    };
 
-    if path.file_name().is_none() {
-        eprintln!("Specify the program file path as arguments.");
-        exit(1);
+    if args.dot {
+        let cfg = ControlFlowGraph::recover(&program, entry);
+        print!("{}", cfg.to_dot());
+        return;
     }
 
+    let mut listing = if args.traverse {
+        Listing::traversed(&program, entry)
+    } else {
+        Listing::linear(&program)
+    };
+
+    if let Some(bank_map) = args.banks {
+        listing = listing.with_bank_map(bank_map);
+    }
+
+    if args.emit_asm {
+        let platform_path = args
+            .platform
+            .as_ref()
+            .expect("Platform is required by clap with --emit-asm");
+
+        match listing.to_customasm(&read_platform(platform_path)) {
+            Ok(asm) => print!("{}", asm),
+            Err(e) => {
+                eprintln!("Error emitting customasm source: {}", e);
+                exit(1);
+            }
+        }
+    } else {
+        print!("{}", listing.format(args.syntax.formatter()));
+    }
+}
+
+fn read_platform(path: &Path) -> Platform {
+    let platform = fs::read_to_string(path)
+        .map_err(|e| e.to_string())
+        .and_then(|platform| platform.parse::<Platform>().map_err(|e| e.to_string()));
+
+    match platform {
+        Ok(platform) => platform,
+        Err(e) => {
+            eprintln!("Error reading platform: {}", e);
+            exit(1);
+        }
+    }
+}
+
+/// Read a raw binary or a memory image going by its extension, or the memory image an executable loads to along with
+/// its entry point.
+fn read_program(path: &Path) -> (Vec<u8>, Option<Word>) {
     let program = match fs::read(path) {
         Ok(program) => program,
         Err(e) => {
@@ -18,7 +159,25 @@ fn main() {
         }
     };
 
-    let deassembler = Deassembler::new(program.iter());
-    println!("{}", deassembler.deassemble_text());
-    println!();
+    let format = ImageFormat::from_path(path);
+
+    let program = match memimage::import(&program, format, ImageLayout::default()) {
+        Ok(program) => program,
+        Err(e) => {
+            eprintln!("Error loading memory image: {}", e);
+            exit(1);
+        }
+    };
+
+    if !Executable::is_executable(&program) {
+        return (program, None);
+    }
+
+    match Executable::parse(&program) {
+        Ok(executable) => (executable.image(), Some(executable.entry)),
+        Err(e) => {
+            eprintln!("Error parsing executable: {}", e);
+            exit(1);
+        }
+    }
 }
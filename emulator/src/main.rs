@@ -1,11 +1,30 @@
-use std::{fs, path::PathBuf, process::exit};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::exit,
+};
 
 use anyhow::anyhow;
 use clap::Parser;
 use command::{Command, CommandError};
-use libdeassembler::Deassembler;
-use libemulator::{Emulator, ExecuteOk};
-use libisa::Word;
+use libdeassembler::{
+    text::{Syntax, SyntaxFormatter},
+    Deassembler,
+};
+use libemulator::{
+    cache::{Cache, CacheConfig, CacheRegion},
+    pipeline::Pipeline,
+    mmu::{self, Mmu},
+    semihosting::{self, Semihosting},
+    Emulator, ExecuteOk,
+};
+use libisa::{
+    executable::Executable,
+    memimage::{self, ImageFormat, ImageLayout},
+    timing::{CycleProfile, ExecuteCycles},
+    Word,
+};
+use libplatform::Platform;
 use log::{error, info, LevelFilter};
 
 mod command;
@@ -13,14 +32,132 @@ mod command;
 #[derive(Parser, Debug)]
 #[command(version, about)]
 pub struct Args {
-    #[arg(short, long, default_value_t = Word::MAX)]
-    memory_size: Word,
+    /// Bytes of physical data memory, by default 1 MiB with the MMU, else enough for the platform's regions and MMIO
+    /// ranges, or the whole address space without a platform.
+    #[arg(short, long)]
+    memory_size: Option<usize>,
+
+    /// Platform description whose memory size and reset PC to use, like customasm/platform.ron.
+    #[arg(long)]
+    platform: Option<PathBuf>,
 
     #[arg(short, long)]
     program_path: PathBuf,
 
+    /// Fetch instructions from a separate instruction memory holding the program, loads and stores accessing a data
+    /// memory of their own.
+    #[arg(long)]
+    harvard: bool,
+
+    /// Initial contents of the data memory in Harvard mode, zeroed if not given.
+    #[arg(long, requires = "harvard")]
+    data_path: Option<PathBuf>,
+
+    /// Format of the program and data files: bin, ihex or mem. Taken from each file's extension by default, files
+    /// without a known one being raw binaries.
+    #[arg(long)]
+    image_format: Option<ImageFormat>,
+
+    /// Bytes per entry of Intel HEX and $readmemh images given as program or data, 1 for byte-addressed images.
+    #[arg(long, default_value_t = ImageLayout::default().width)]
+    image_width: usize,
+
+    /// Address of the entry of Intel HEX and $readmemh images holding byte 0 of memory, in entries.
+    #[arg(long, default_value_t = ImageLayout::default().base_addr)]
+    image_base: u32,
+
     #[arg(long, default_value_t = { "".to_owned() })]
     log: String,
+
+    /// Syntax flavour of deassembled instructions: customasm, verbose or ansi.
+    #[arg(short, long, default_value_t = Syntax::Customasm)]
+    syntax: Syntax,
+
+    /// Microarchitecture whose cycle costs are counted: ideal or multicycle.
+    #[arg(short, long, default_value_t = CycleProfile::default())]
+    cycle_profile: CycleProfile,
+
+    /// Cycles per fetched instruction word, overriding the cycle profile's.
+    #[arg(long)]
+    fetch_cycles: Option<u32>,
+
+    /// Cycles executing instructions without a cost of their own, overriding the cycle profile's.
+    #[arg(long)]
+    execute_cycles: Option<u32>,
+
+    /// Cycles executing one kind of instruction, given as KIND=CYCLES with its mnemonic, e.g. load=2. Can be given
+    /// multiple times.
+    #[arg(long = "instruction-cycles")]
+    instruction_cycles: Vec<ExecuteCycles>,
+
+    /// Cycles of accessing data memory, overriding the cycle profile's.
+    #[arg(long)]
+    memory_access_cycles: Option<u32>,
+
+    /// Cycles of taking a jump, overriding the cycle profile's.
+    #[arg(long)]
+    taken_branch_cycles: Option<u32>,
+
+    /// Run the program to halt without prompting for commands, then report what it took.
+    #[arg(short, long)]
+    batch: bool,
+
+    /// Step the pipeline model cycle by cycle, printing the state of its stages every cycle in batch mode.
+    #[arg(long)]
+    pipeline: bool,
+
+    /// Simulate an instruction cache, given as SIZE/LINE/WAYS[/POLICY] with the policy lru, fifo or random.
+    #[arg(long)]
+    icache: Option<CacheConfig>,
+
+    /// Simulate a data cache, given like the instruction cache.
+    #[arg(long)]
+    dcache: Option<CacheConfig>,
+
+    /// Report cache accesses to the region of physical addresses separately, given as NAME:START-END in hex. Can be
+    /// given multiple times.
+    #[arg(long = "cache-region")]
+    cache_regions: Vec<CacheRegion>,
+
+    /// Serve semihosting calls through the doorbell at the platform's semihosting MMIO range, or at 0xfff0 without
+    /// one. Always on if the platform has the range. In batch mode the emulator exits with the program's status,
+    /// clamped to 255.
+    #[arg(long)]
+    semihosting: bool,
+
+    /// Map data accesses and, outside of Harvard mode, instruction fetches through the bank registers of the MMU at
+    /// the platform's mmu MMIO range, or at 0xffd0 without one. Always on if the platform has the range.
+    #[arg(long)]
+    mmu: bool,
+}
+
+impl Args {
+    /// The cycle profile with the costs given on the command line overriding its own.
+    fn cycle_profile(&self) -> CycleProfile {
+        let mut profile = self.cycle_profile.clone();
+
+        if let Some(cycles) = self.fetch_cycles {
+            profile = profile.with_fetch_cycles(cycles);
+        }
+
+        if let Some(cycles) = self.execute_cycles {
+            profile = profile.with_default_execute_cycles(cycles);
+        }
+
+        for ExecuteCycles { kind, cycles } in &self.instruction_cycles {
+            profile = profile.with_execute_cycles(*kind, *cycles);
+        }
+
+        if let Some(cycles) = self.memory_access_cycles {
+            profile = profile.with_memory_access_cycles(cycles);
+        }
+
+        if let Some(cycles) = self.taken_branch_cycles {
+            profile = profile.with_taken_branch_cycles(cycles);
+        }
+
+        profile
+    }
 }
 
 fn main() {
@@ -32,26 +169,177 @@ fn main() {
         .parse_filters(&args.log)
         .init();
 
-    if let Err(e) = Cli::new(args).map(|mut cli| cli.run()) {
-        error!("Fatal: {}", e);
+    let batch = args.batch;
+
+    let result = Cli::new(args).and_then(|mut cli| {
+        if batch {
+            return cli.run_batch();
+        }
+
+        cli.run();
+        Ok(None)
+    });
+
+    match result {
+        // Only the low 8 bits of a process status survive, which would turn e.g. 256 into success.
+        Ok(Some(exit_status)) => exit(exit_status.min(u8::MAX as Word) as i32),
+        Ok(None) => {}
+        Err(e) => {
+            error!("Fatal: {}", e);
+            exit(1);
+        }
     }
 }
 
 struct Cli {
-    #[expect(dead_code)] // Not currently used
     args: Args,
 
     emulator: Emulator,
+    pipeline: Option<Pipeline>,
 }
 
 impl Cli {
     pub fn new(args: Args) -> anyhow::Result<Self> {
-        let program =
-            fs::read(&args.program_path).map_err(|e| anyhow!("Couldn't read program: {}", e))?;
+        let layout = ImageLayout::new(args.image_width, args.image_base)?;
+
+        let platform = match &args.platform {
+            Some(path) => Some(
+                fs::read_to_string(path)
+                    .map_err(|e| anyhow!("Couldn't read platform: {}", e))?
+                    .parse::<Platform>()?,
+            ),
+            None => None,
+        };
+
+        let mmu_range = platform
+            .as_ref()
+            .and_then(|platform| platform.mmio_range(mmu::MMIO_NAME));
+        let mmu = (args.mmu || mmu_range.is_some())
+            .then(|| Mmu::new(mmu_range.map_or(mmu::DEFAULT_BASE, |range| range.addr as Word)));
+
+        let memory_size = match (args.memory_size, &mmu, &platform) {
+            (Some(memory_size), ..) => memory_size,
+            (None, Some(_), _) => mmu::DEFAULT_PHYSICAL_SIZE,
+            (None, None, Some(platform)) => platform.memory_size() as usize,
+            (None, None, None) => libisa::ADDRESS_SPACE_SIZE,
+        };
+
+        // Images can't hold more than the memory they get loaded to.
+        let layout = layout.with_limit(memory_size);
+
+        let program = Self::read_image(&args.program_path, args.image_format, layout)?;
+        let is_executable = Executable::is_executable(&program);
+
+        let emulator = match (args.harvard, &args.data_path) {
+            (true, Some(data_path)) => {
+                let data = Self::read_image(data_path, args.image_format, layout)?;
+
+                Emulator::new_harvard(program, data)?
+            }
+            (true, None) => Emulator::new_harvard(program, vec![])?,
+            (false, _) => Emulator::new(program)?,
+        };
+
+        let mut emulator = emulator
+            .with_memory_size(memory_size)?
+            .with_cycle_profile(args.cycle_profile());
+
+        // Executables bring their own entry point, raw programs start where the platform resets to.
+        if let Some(platform) = &platform {
+            if !is_executable {
+                emulator.pc = platform.reset_pc;
+            }
+        }
 
-        let emulator = Emulator::new(program)?;
+        let new_cache = |config| {
+            args.cache_regions
+                .iter()
+                .cloned()
+                .fold(Cache::new(config), Cache::with_region)
+        };
 
-        Ok(Self { args, emulator })
+        emulator.instruction_cache = args.icache.map(new_cache);
+        emulator.data_cache = args.dcache.map(new_cache);
+
+        if let Some(mmu) = mmu {
+            emulator = emulator.with_mmu(mmu)?;
+        }
+
+        let semihosting_range = platform
+            .as_ref()
+            .and_then(|platform| platform.mmio_range(semihosting::MMIO_NAME));
+
+        if args.semihosting || semihosting_range.is_some() {
+            let base = semihosting_range
+                .map_or(semihosting::DEFAULT_BASE, |range| range.addr as Word);
+            emulator = emulator.with_semihosting(Semihosting::new(base));
+        }
+
+        let pipeline = args.pipeline.then(|| Pipeline::new(emulator.pc));
+
+        Ok(Self {
+            args,
+            emulator,
+            pipeline,
+        })
+    }
+
+    /// Contents of the memory image, in the given format or the one its extension suggests.
+    fn read_image(
+        path: &Path,
+        format: Option<ImageFormat>,
+        layout: ImageLayout,
+    ) -> anyhow::Result<Vec<u8>> {
+        let bytes = fs::read(path).map_err(|e| anyhow!("Couldn't read {}: {}", path.display(), e))?;
+        let format = format.unwrap_or_else(|| ImageFormat::from_path(path));
+
+        memimage::import(&bytes, format, layout)
+            .map_err(|e| anyhow!("Couldn't load {} as {}: {}", path.display(), format, e))
+    }
+
+    /// Run to halt, returning the status the program exited with through semihosting, if it did.
+    pub fn run_batch(&mut self) -> anyhow::Result<Option<Word>> {
+        if let Some(pipeline) = &mut self.pipeline {
+            while !pipeline.halted() {
+                println!("{}", pipeline.step(&mut self.emulator)?);
+            }
+
+            let stats = pipeline.stats;
+
+            println!(
+                "Halted after {} retired instructions, {} cycles with {} stalled and {} instructions flushed.",
+                stats.retired, stats.cycles, stats.stall_cycles, stats.flushed
+            );
+            print!("{}", self.cache_report());
+
+            return Ok(self.exit_status());
+        }
+
+        let mut instruction_count: u64 = 0;
+
+        loop {
+            let exec_ok = self.emulator.execute_instruction()?;
+            instruction_count += 1;
+
+            if exec_ok == ExecuteOk::Halted {
+                break;
+            }
+        }
+
+        println!(
+            "Halted after {} executed instructions, {} {} cycles.",
+            instruction_count, self.emulator.cycles, self.emulator.cycle_profile
+        );
+        print!("{}", self.cache_report());
+
+        Ok(self.exit_status())
+    }
+
+    fn exit_status(&self) -> Option<Word> {
+        let exit_status = self.emulator.semihosting.as_ref()?.exit_status?;
+        println!("Program exited with status {}.", exit_status);
+
+        Some(exit_status)
     }
 
     pub fn run(&mut self) {
@@ -75,14 +363,50 @@ impl Cli {
                 let instruction_count: usize = cmd_args.next_parsed().unwrap_or(Ok(1))?;
 
                 for instruction_index in 0..instruction_count {
-                    if self.emulator.execute_instruction()? == ExecuteOk::Halted {
-                        println!("Halted after {} executed instructions.", instruction_index + 1);
+                    let mode = self.emulator.mode;
+                    let exec_ok = self.emulator.execute_instruction()?;
+
+                    // Traps and returns from them are where a monitor's bugs tend to be.
+                    if self.emulator.mode != mode {
+                        info!("Entered {} mode at {:05}", self.emulator.mode, self.emulator.pc);
+                    }
+
+                    if exec_ok == ExecuteOk::Halted {
+                        println!(
+                            "Halted after {} executed instructions, {} cycles in total.",
+                            instruction_index + 1,
+                            self.emulator.cycles
+                        );
+                        break
+                    }
+                }
+            }
+
+            "c" | "cycle" => {
+                let pipeline = self
+                    .pipeline
+                    .as_mut()
+                    .ok_or_else(|| CommandError::Other("Pipeline model not enabled".to_string()))?;
+
+                let cycle_count: usize = cmd_args.next_parsed().unwrap_or(Ok(1))?;
+
+                for _ in 0..cycle_count {
+                    let cycle = pipeline.step(&mut self.emulator)?;
+                    info!("{}", cycle);
+
+                    if cycle.halted {
                         break
                     }
                 }
             }
 
             "p" | "print" => {
+                // Optionally override the syntax given on the command line.
+                let syntax = match cmd_args.next() {
+                    Ok(syntax) => syntax.parse().map_err(CommandError::ParseError)?,
+                    Err(_) => self.args.syntax,
+                };
+
                 let set_alu_flags = self
                     .emulator
                     .alu
@@ -92,13 +416,28 @@ impl Cli {
                     .map(|(name, _)| name.replace('"', ""))
                     .collect::<Vec<_>>();
 
-                info!("PC:          {:05}", self.emulator.pc);
-                info!("Deassembled: {}", self.deassemble_pc_instruction());
+                match self.emulator.mmu {
+                    Some(_) => info!(
+                        "PC:          {:05} (physical 0x{:05x})",
+                        self.emulator.pc,
+                        self.emulator.physical_pc()
+                    ),
+                    None => info!("PC:          {:05}", self.emulator.pc),
+                }
+                info!("Mode:        {}", self.emulator.mode);
+                info!(
+                    "Deassembled: {}",
+                    self.deassemble_pc_instruction(syntax.formatter())
+                );
                 info!(
                     "Registers:   {:05?}",
-                    self.emulator.reg_file
+                    self.emulator.reg_file.iter_words().collect::<Vec<_>>()
                 );
                 info!("ALU flags:   {:?}", set_alu_flags);
+                info!(
+                    "Cycles:      {} ({})",
+                    self.emulator.cycles, self.emulator.cycle_profile
+                );
             }
 
             "d" | "dump" => {
@@ -107,11 +446,13 @@ impl Cli {
 
                 let words = (addr..addr + len)
                     .step_by(libisa::BYTES_PER_WORD)
-                    .map(|addr| *self.emulator.memory.word(addr).as_deref().unwrap_or(&0))
+                    .map(|addr| self.emulator.physical_addr(addr))
+                    .map(|addr| *self.emulator.memory.get_multi::<Word>(addr).as_deref().unwrap_or(&0))
                     .collect::<Vec<_>>();
 
                 let bytes = (addr..addr + len)
-                    .map(|addr| *self.emulator.memory.byte(addr).as_deref().unwrap_or(&0))
+                    .map(|addr| self.emulator.physical_addr(addr))
+                    .map(|addr| *self.emulator.memory.get(addr).unwrap_or(&0))
                     .collect::<Vec<_>>();
 
                 let output = match cmd_args.next()? {
@@ -133,6 +474,8 @@ impl Cli {
                 info!("Dump {}..{}: {}", addr, addr + len, output);
             }
 
+            "cache" => info!("\n{}", self.cache_report()),
+
             "j" | "jmp" | "goto" => {
                 let addr = cmd_args.next_parsed()??;
                 self.emulator.pc = addr;
@@ -146,14 +489,27 @@ impl Cli {
         Ok(())
     }
 
-    fn deassemble_pc_instruction(&self) -> String {
+    fn cache_report(&self) -> String {
+        let caches = [
+            ("Instruction cache", &self.emulator.instruction_cache),
+            ("Data cache", &self.emulator.data_cache),
+        ];
+
+        caches
+            .into_iter()
+            .filter_map(|(name, cache)| Some(format!("{} {}", name, cache.as_ref()?)))
+            .collect()
+    }
+
+    fn deassemble_pc_instruction(&self, syntax: &dyn SyntaxFormatter) -> String {
         let mut deassembler = Deassembler::new(
             self.emulator
-                .memory
-                .iter_bytes()
-                .skip(self.emulator.pc as usize),
-        );
+                .code_memory()
+                .iter_words()
+                .skip(self.emulator.physical_pc()),
+        )
+        .with_offset(self.emulator.pc);
 
-        deassembler.deassemble_instruction_text()
+        deassembler.deassemble_instruction_text_with(syntax)
     }
 }
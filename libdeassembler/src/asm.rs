@@ -0,0 +1,117 @@
+use std::fmt::Write;
+
+use libisa::instruction::Instruction;
+use libplatform::Platform;
+use thiserror::Error;
+
+use crate::{
+    listing::{Listing, ListingContent, ListingRecord},
+    text::{source_form, write_string, CustomasmSyntax, SyntaxFormatter},
+};
+
+#[derive(Debug, Error, Clone, Copy, PartialEq, Eq)]
+pub enum AsmError {
+    #[error("Program is {len} bytes, which doesn't fit the {bank_size} byte code bank")]
+    DoesntFitCodeBank { len: usize, bank_size: usize },
+}
+
+impl Listing {
+    /// Emit customasm source which reassembles with `rules.asm` into the exact same binary, the banks it includes
+    /// being generated from the platform. Binaries shorter than the code bank come out padded to its size, as the
+    /// bank is filled.
+    pub fn to_customasm(&self, platform: &Platform) -> Result<String, AsmError> {
+        let code_len = self.records.last().map_or(0, |record| record.end_addr());
+        let bank_size = platform.code_region().size as usize;
+
+        if code_len > bank_size {
+            return Err(AsmError::DoesntFitCodeBank {
+                len: code_len,
+                bank_size,
+            });
+        }
+
+        // The code bank's #fill pads to its full size, which only reproduces input that was padded the same way.
+        let records = match code_len == bank_size {
+            true => self.records_without_fill(),
+            false => &self.records,
+        };
+
+        Ok(write_string(|asm| {
+            writeln!(asm, "#include \"rules.asm\"")?;
+            writeln!(asm)?;
+            writeln!(asm, "#bank code")?;
+
+            for record in records {
+                if let Some(label) = self.label(record.addr) {
+                    writeln!(asm, "{}", CustomasmSyntax.label_definition(&label))?;
+                }
+
+                let line = match &record.content {
+                    ListingContent::Instruction(instruction)
+                        if Self::is_reassemblable(instruction, &record.bytes) =>
+                    {
+                        self.instruction_customasm(record, *instruction)
+                    }
+
+                    // Data, or an instruction customasm wouldn't encode to the same bytes.
+                    _ => Self::data_customasm(&record.bytes),
+                };
+
+                writeln!(asm, "    {}", line)?;
+            }
+
+            if records.len() < self.records.len() {
+                writeln!(
+                    asm,
+                    "    ; Trailing zero bytes are restored by the code bank's #fill."
+                )?;
+            }
+
+            Ok(())
+        }))
+    }
+
+    /// The records excluding any trailing zero bytes that the code bank would fill in anyway.
+    fn records_without_fill(&self) -> &[ListingRecord] {
+        let fill_start = self
+            .records
+            .iter()
+            .rposition(|record| {
+                record.bytes.iter().any(|byte| *byte != 0) || self.label(record.addr).is_some()
+            })
+            .map_or(0, |index| index + 1);
+
+        &self.records[..fill_start]
+    }
+
+    fn instruction_customasm(&self, record: &ListingRecord, instruction: Instruction) -> String {
+        // Refer to code by label, so the immediate is correct even if the code is later modified.
+        let label = record
+            .immediate_target()
+            .and_then(|target| self.label(target));
+
+        let instruction = source_form(record.addr, instruction);
+
+        CustomasmSyntax.instruction_with_label(&instruction, label.as_deref())
+    }
+
+    fn data_customasm(bytes: &[u8]) -> String {
+        let data = bytes
+            .iter()
+            .map(|byte| format!("0x{:02x}", byte))
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        format!("#d {}", data)
+    }
+
+    /// Whether customasm would assemble the instruction's text into the original bytes.
+    /// Not the case when unused register fields or the padding bits are set, for example.
+    fn is_reassemblable(instruction: &Instruction, bytes: &[u8]) -> bool {
+        instruction.reg_a.is_some() == instruction.kind.has_reg_a()
+            && instruction.reg_b.is_some() == instruction.kind.has_reg_b()
+            && instruction
+                .assemble()
+                .is_ok_and(|assembled| assembled == bytes)
+    }
+}
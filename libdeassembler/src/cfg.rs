@@ -0,0 +1,206 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::Write,
+};
+
+use libisa::{
+    instruction::{kind::InstructionKind, Instruction},
+    Word,
+};
+
+use crate::{
+    listing::label_name,
+    text::{source_form, write_string},
+    traversal::Traversal,
+};
+
+/// Control flow graph of the code reachable from an entry point.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ControlFlowGraph {
+    pub entry: Word,
+
+    /// Basic blocks by their start address.
+    pub blocks: BTreeMap<Word, BasicBlock>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BasicBlock {
+    pub start: Word,
+    pub instructions: Vec<(Word, Instruction)>,
+    pub edges: Vec<Edge>,
+
+    /// Whether the block ends in a jump whose target couldn't be resolved, meaning some edges may be missing.
+    pub unresolved_jump: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Edge {
+    pub target: Word,
+    pub kind: EdgeKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EdgeKind {
+    /// Execution continues to the next instruction, either normally or by a conditional jump not being taken.
+    Fallthrough,
+
+    /// A jump, conditional or not, was taken.
+    Jump(InstructionKind),
+}
+
+impl ControlFlowGraph {
+    pub fn recover(code: &[u8], entry: Word) -> Self {
+        Self::from_traversal(&Traversal::from_entry(code, entry), entry)
+    }
+
+    pub fn from_traversal(traversal: &Traversal, entry: Word) -> Self {
+        let jump_targets: BTreeSet<_> = traversal.jump_targets.values().copied().collect();
+
+        let mut blocks = BTreeMap::new();
+        let mut current: Option<BasicBlock> = None;
+
+        for (&addr, &instruction) in &traversal.instructions {
+            let continues_block = current.as_ref().is_some_and(|block| {
+                block.end_addr() == Some(addr)
+                    && !block.is_terminated()
+                    && !jump_targets.contains(&addr)
+            });
+
+            if !continues_block {
+                if let Some(block) = current.take() {
+                    blocks.insert(block.start, block);
+                }
+            }
+
+            current
+                .get_or_insert_with(|| BasicBlock::new(addr))
+                .instructions
+                .push((addr, instruction));
+        }
+
+        if let Some(block) = current {
+            blocks.insert(block.start, block);
+        }
+
+        for block in blocks.values_mut() {
+            block.compute_edges(traversal);
+        }
+
+        Self { entry, blocks }
+    }
+
+    /// Render the graph in Graphviz DOT format.
+    pub fn to_dot(&self) -> String {
+        write_string(|dot| {
+            writeln!(dot, "digraph cfg {{")?;
+            writeln!(dot, "    node [shape=box, fontname=monospace];")?;
+
+            for block in self.blocks.values() {
+                let mut label = format!("{}:\\l", label_name(block.start));
+
+                for (addr, instruction) in &block.instructions {
+                    write!(
+                        label,
+                        "{:04x}  {}\\l",
+                        addr,
+                        source_form(*addr, *instruction)
+                    )?;
+                }
+
+                let mut attributes = String::new();
+
+                if block.unresolved_jump {
+                    label.push_str("(unresolved jump)\\l");
+                    attributes.push_str(", color=red");
+                }
+
+                if block.start == self.entry {
+                    attributes.push_str(", penwidth=2");
+                }
+
+                writeln!(
+                    dot,
+                    "    {} [label=\"{}\"{}];",
+                    label_name(block.start),
+                    label,
+                    attributes
+                )?;
+            }
+
+            for block in self.blocks.values() {
+                for edge in &block.edges {
+                    let attributes = match edge.kind {
+                        EdgeKind::Fallthrough => "style=dashed".to_string(),
+                        EdgeKind::Jump(kind) => format!("label=\"{}\"", kind),
+                    };
+
+                    writeln!(
+                        dot,
+                        "    {} -> {} [{}];",
+                        label_name(block.start),
+                        label_name(edge.target),
+                        attributes
+                    )?;
+                }
+            }
+
+            writeln!(dot, "}}")
+        })
+    }
+}
+
+impl BasicBlock {
+    fn new(start: Word) -> Self {
+        Self {
+            start,
+            instructions: Vec::new(),
+            edges: Vec::new(),
+            unresolved_jump: false,
+        }
+    }
+
+    pub fn last_instruction(&self) -> Option<&(Word, Instruction)> {
+        self.instructions.last()
+    }
+
+    /// Address right after the last instruction of the block.
+    pub fn end_addr(&self) -> Option<Word> {
+        self.last_instruction()
+            .map(|(addr, instruction)| addr.wrapping_add(instruction.kind.len_bytes() as Word))
+    }
+
+    /// Whether the block ends in an instruction that always leaves the block or stops execution.
+    fn is_terminated(&self) -> bool {
+        self.last_instruction().is_some_and(|(_, instruction)| {
+            instruction.kind.is_jump() || instruction.kind.stops_flow()
+        })
+    }
+
+    fn compute_edges(&mut self, traversal: &Traversal) {
+        let Some(&(last_addr, last_instruction)) = self.last_instruction() else {
+            return;
+        };
+
+        if last_instruction.kind.is_jump() {
+            match traversal.jump_targets.get(&last_addr) {
+                Some(target) => self.edges.push(Edge {
+                    target: *target,
+                    kind: EdgeKind::Jump(last_instruction.kind),
+                }),
+                None => self.unresolved_jump = true,
+            }
+        }
+
+        let falls_through = !last_instruction.kind.stops_flow()
+            && (!last_instruction.kind.is_jump() || last_instruction.kind.is_conditional_jump());
+
+        if let Some(next_addr) = self.end_addr().filter(|_| falls_through) {
+            if traversal.is_reachable(next_addr) {
+                self.edges.push(Edge {
+                    target: next_addr,
+                    kind: EdgeKind::Fallthrough,
+                });
+            }
+        }
+    }
+}
@@ -0,0 +1,432 @@
+use std::{cmp::Reverse, collections::HashMap, ops::Range};
+
+use libisa::{instruction::Instruction, Word};
+
+use crate::{
+    listing::{Listing, ListingContent, ListingRecord},
+    text::{source_form, SyntaxFormatter},
+};
+
+/// Amount of unchanged records shown around every change.
+const CONTEXT_RECORDS: usize = 2;
+
+/// Instruction-aware difference between two listings, e.g. of the same program before and after a compiler change.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ListingDiff {
+    pub entries: Vec<DiffEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DiffEntry {
+    /// Equivalent records, possibly at different addresses.
+    Same {
+        old: ListingRecord,
+        new: ListingRecord,
+    },
+
+    Removed(ListingRecord),
+    Inserted(ListingRecord),
+
+    /// A record replaced by a different one at the same position in the instruction stream.
+    Changed {
+        old: ListingRecord,
+        new: ListingRecord,
+    },
+}
+
+impl ListingDiff {
+    pub fn new(old: &Listing, new: &Listing) -> Self {
+        let entries = Self::align(old, new);
+        let entries = Self::pair_changes(entries);
+        let entries = Self::check_targets(entries, old, new);
+
+        Self { entries }
+    }
+
+    pub fn has_changes(&self) -> bool {
+        self.entries
+            .iter()
+            .any(|entry| !matches!(entry, DiffEntry::Same { .. }))
+    }
+
+    /// Longest common subsequence of the records, with immediates referring to labels considered equivalent
+    /// no matter their value. Whether those still refer to the same code is checked once the alignment is known.
+    fn align(old: &Listing, new: &Listing) -> Vec<DiffEntry> {
+        let mut alignment = Alignment {
+            old,
+            new,
+            entries: Vec::new(),
+        };
+
+        let (old_len, new_len) = (old.records.len(), new.records.len());
+        let common_len = old_len.min(new_len);
+
+        // Common ends are aligned as they are, only the differing middle of the listings needs searching.
+        let prefix = (0..common_len)
+            .take_while(|&index| alignment.equivalent(index, index))
+            .count();
+        let suffix = (0..common_len - prefix)
+            .take_while(|&index| alignment.equivalent(old_len - 1 - index, new_len - 1 - index))
+            .count();
+
+        alignment.same(0..prefix, 0..prefix);
+        alignment.align(prefix..old_len - suffix, prefix..new_len - suffix);
+        alignment.same(old_len - suffix..old_len, new_len - suffix..new_len);
+
+        alignment.entries
+    }
+
+    fn equivalent(
+        old_listing: &Listing,
+        old: &ListingRecord,
+        new_listing: &Listing,
+        new: &ListingRecord,
+    ) -> bool {
+        match (old.instruction_ref(), new.instruction_ref()) {
+            (Some(old_instruction), Some(new_instruction)) => {
+                let old_target = label_target(old_listing, old);
+                let new_target = label_target(new_listing, new);
+
+                match (old_target, new_target) {
+                    (Some(_), Some(_)) => {
+                        Instruction {
+                            immediate: None,
+                            ..*old_instruction
+                        } == Instruction {
+                            immediate: None,
+                            ..*new_instruction
+                        }
+                    }
+                    _ => old_instruction == new_instruction,
+                }
+            }
+
+            (None, None) => {
+                matches!(old.content, ListingContent::Data)
+                    == matches!(new.content, ListingContent::Data)
+                    && old.bytes == new.bytes
+            }
+
+            _ => false,
+        }
+    }
+
+    /// Turn runs of removed records directly followed by inserted ones into changes, pairwise.
+    fn pair_changes(entries: Vec<DiffEntry>) -> Vec<DiffEntry> {
+        let mut paired = Vec::with_capacity(entries.len());
+        let mut removed = Vec::new();
+        let mut inserted = Vec::new();
+
+        let flush = |paired: &mut Vec<DiffEntry>,
+                     removed: &mut Vec<ListingRecord>,
+                     inserted: &mut Vec<ListingRecord>| {
+            let changed = removed.len().min(inserted.len());
+            let mut removed = removed.drain(..);
+            let mut inserted = inserted.drain(..);
+
+            // Limited up front, as zip would otherwise drop a removed record once it runs out of inserted ones.
+            for (old, new) in removed.by_ref().zip(inserted.by_ref()).take(changed) {
+                paired.push(DiffEntry::Changed { old, new });
+            }
+
+            paired.extend(removed.map(DiffEntry::Removed));
+            paired.extend(inserted.map(DiffEntry::Inserted));
+        };
+
+        for entry in entries {
+            match entry {
+                DiffEntry::Removed(record) if inserted.is_empty() => removed.push(record),
+                DiffEntry::Inserted(record) => inserted.push(record),
+                entry => {
+                    flush(&mut paired, &mut removed, &mut inserted);
+
+                    match entry {
+                        DiffEntry::Removed(record) => removed.push(record),
+                        entry => paired.push(entry),
+                    }
+                }
+            }
+        }
+
+        flush(&mut paired, &mut removed, &mut inserted);
+        paired
+    }
+
+    /// Mark aligned instructions as changed if their label immediates refer to code that wasn't aligned together,
+    /// as opposed to the same code that merely shifted to another address.
+    fn check_targets(entries: Vec<DiffEntry>, old: &Listing, new: &Listing) -> Vec<DiffEntry> {
+        let aligned_addrs: HashMap<Word, Word> = entries
+            .iter()
+            .filter_map(|entry| match entry {
+                DiffEntry::Same { old, new } | DiffEntry::Changed { old, new } => {
+                    Some((old.addr, new.addr))
+                }
+                DiffEntry::Removed(..) | DiffEntry::Inserted(..) => None,
+            })
+            .collect();
+
+        entries
+            .into_iter()
+            .map(|entry| match entry {
+                DiffEntry::Same {
+                    old: old_record,
+                    new: new_record,
+                } => {
+                    let old_target = label_target(old, &old_record);
+                    let new_target = label_target(new, &new_record);
+
+                    let shifted = match (old_target, new_target) {
+                        (Some(old_target), Some(new_target)) => {
+                            aligned_addrs.get(&old_target) == Some(&new_target)
+                        }
+                        _ => true,
+                    };
+
+                    if shifted {
+                        DiffEntry::Same {
+                            old: old_record,
+                            new: new_record,
+                        }
+                    } else {
+                        DiffEntry::Changed {
+                            old: old_record,
+                            new: new_record,
+                        }
+                    }
+                }
+                entry => entry,
+            })
+            .collect()
+    }
+
+    /// Render the changes with some surrounding context, instructions in the given syntax.
+    pub fn format(&self, old: &Listing, new: &Listing, syntax: &dyn SyntaxFormatter) -> String {
+        let changed: Vec<_> = self
+            .entries
+            .iter()
+            .map(|entry| !matches!(entry, DiffEntry::Same { .. }))
+            .collect();
+
+        let mut text = String::new();
+        let mut skipped = false;
+
+        for (index, entry) in self.entries.iter().enumerate() {
+            let context_start = index.saturating_sub(CONTEXT_RECORDS);
+            let context_end = (index + CONTEXT_RECORDS + 1).min(changed.len());
+
+            if !changed[context_start..context_end].contains(&true) {
+                skipped = true;
+                continue;
+            }
+
+            if skipped {
+                text.push_str("  ...\n");
+                skipped = false;
+            }
+
+            let line = match entry {
+                DiffEntry::Same {
+                    old: record,
+                    new: new_record,
+                } => format!(
+                    "  {:04x} {:04x}  {}",
+                    record.addr,
+                    new_record.addr,
+                    record_text(old, record, syntax)
+                ),
+                DiffEntry::Removed(record) => format!(
+                    "- {:04x}       {}",
+                    record.addr,
+                    record_text(old, record, syntax)
+                ),
+                DiffEntry::Inserted(record) => format!(
+                    "+      {:04x}  {}",
+                    record.addr,
+                    record_text(new, record, syntax)
+                ),
+                DiffEntry::Changed {
+                    old: old_record,
+                    new: new_record,
+                } => format!(
+                    "! {:04x} {:04x}  {}  =>  {}",
+                    old_record.addr,
+                    new_record.addr,
+                    record_text(old, old_record, syntax),
+                    record_text(new, new_record, syntax)
+                ),
+            };
+
+            text.push_str(&line);
+            text.push('\n');
+        }
+
+        text
+    }
+}
+
+/// Alignment of two listings in progress, found with Hirschberg's algorithm so that memory stays linear in the
+/// length of the listings, whole address spaces of them included.
+struct Alignment<'a> {
+    old: &'a Listing,
+    new: &'a Listing,
+    entries: Vec<DiffEntry>,
+}
+
+impl Alignment<'_> {
+    fn equivalent(&self, old_index: usize, new_index: usize) -> bool {
+        ListingDiff::equivalent(
+            self.old,
+            &self.old.records[old_index],
+            self.new,
+            &self.new.records[new_index],
+        )
+    }
+
+    /// Align the ranges of records, appending the entries for them.
+    fn align(&mut self, old_range: Range<usize>, new_range: Range<usize>) {
+        if old_range.is_empty() || new_range.is_empty() {
+            self.removed(old_range);
+            self.inserted(new_range);
+            return;
+        }
+
+        if old_range.len() == 1 {
+            let old_index = old_range.start;
+
+            match new_range
+                .clone()
+                .find(|&new_index| self.equivalent(old_index, new_index))
+            {
+                Some(new_index) => {
+                    self.inserted(new_range.start..new_index);
+                    self.same(old_range, new_index..new_index + 1);
+                    self.inserted(new_index + 1..new_range.end);
+                }
+                None => {
+                    self.removed(old_range);
+                    self.inserted(new_range);
+                }
+            }
+
+            return;
+        }
+
+        // Split the old records in half, and the new ones where the common subsequences of both halves are the
+        // longest together. Of equally long ones the earliest split is taken, so that removals come before the
+        // insertions they could pair up with as changes.
+        let old_mid = old_range.start + old_range.len() / 2;
+        let before = self.lengths(old_range.start..old_mid, new_range.clone(), false);
+        let after = self.lengths(old_mid..old_range.end, new_range.clone(), true);
+
+        let split = (0..=new_range.len())
+            .max_by_key(|&split| (before[split] + after[split], Reverse(split)))
+            .unwrap();
+        let new_mid = new_range.start + split;
+
+        self.align(old_range.start..old_mid, new_range.start..new_mid);
+        self.align(old_mid..old_range.end, new_mid..new_range.end);
+    }
+
+    /// Lengths of the longest common subsequences of the old records with every prefix of the new ones, indexed
+    /// by prefix length. Reversed, they are of the suffixes instead, indexed by where the suffix starts.
+    fn lengths(
+        &self,
+        old_range: Range<usize>,
+        new_range: Range<usize>,
+        reversed: bool,
+    ) -> Vec<u32> {
+        let len = new_range.len();
+        let mut previous = vec![0u32; len + 1];
+        let mut current = vec![0u32; len + 1];
+
+        let old_indices: Vec<usize> = if reversed {
+            old_range.rev().collect()
+        } else {
+            old_range.collect()
+        };
+
+        for old_index in old_indices {
+            for offset in 1..=len {
+                let new_index = if reversed {
+                    new_range.end - offset
+                } else {
+                    new_range.start + offset - 1
+                };
+
+                current[offset] = if self.equivalent(old_index, new_index) {
+                    previous[offset - 1] + 1
+                } else {
+                    previous[offset].max(current[offset - 1])
+                };
+            }
+
+            std::mem::swap(&mut previous, &mut current);
+        }
+
+        if reversed {
+            previous.reverse();
+        }
+
+        previous
+    }
+
+    fn same(&mut self, old_range: Range<usize>, new_range: Range<usize>) {
+        let (old, new) = (self.old, self.new);
+
+        self.entries
+            .extend(
+                old_range
+                    .zip(new_range)
+                    .map(|(old_index, new_index)| DiffEntry::Same {
+                        old: old.records[old_index].clone(),
+                        new: new.records[new_index].clone(),
+                    }),
+            );
+    }
+
+    fn removed(&mut self, old_range: Range<usize>) {
+        self.entries.extend(
+            self.old.records[old_range]
+                .iter()
+                .cloned()
+                .map(DiffEntry::Removed),
+        );
+    }
+
+    fn inserted(&mut self, new_range: Range<usize>) {
+        self.entries.extend(
+            self.new.records[new_range]
+                .iter()
+                .cloned()
+                .map(DiffEntry::Inserted),
+        );
+    }
+}
+
+/// The address an immediate refers to, if it's labeled in the listing.
+fn label_target(listing: &Listing, record: &ListingRecord) -> Option<Word> {
+    record
+        .immediate_target()
+        .filter(|target| listing.labels.contains(target))
+}
+
+fn record_text(listing: &Listing, record: &ListingRecord, syntax: &dyn SyntaxFormatter) -> String {
+    match &record.content {
+        ListingContent::Instruction(instruction) => {
+            let label = label_target(listing, record).and_then(|addr| listing.label(addr));
+            let instruction = source_form(record.addr, *instruction);
+
+            syntax.instruction_with_label(&instruction, label.as_deref())
+        }
+        ListingContent::Data | ListingContent::Invalid(..) => {
+            let data = record
+                .bytes
+                .iter()
+                .map(|byte| format!("0x{:02x}", byte))
+                .collect::<Vec<_>>()
+                .join(", ");
+
+            format!(".byte {}", data)
+        }
+    }
+}
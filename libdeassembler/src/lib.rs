@@ -1,12 +1,59 @@
 use std::iter::Peekable;
 
-use libisa::{instruction::Instruction, Word};
+use libisa::{
+    instruction::{Instruction, InstructionDeassemblyError},
+    Word,
+};
+use text::{CustomasmSyntax, SyntaxFormatter};
+use thiserror::Error;
+
+pub mod asm;
+pub mod cfg;
+pub mod diff;
+pub mod listing;
+pub mod text;
+pub mod traversal;
+
+#[cfg(test)]
+mod tests;
+
+#[derive(Debug, Error, Clone, Copy, PartialEq, Eq)]
+pub enum DeassemblyError {
+    #[error("Unknown opcode {opcode} in word 0x{word:04x} at 0x{offset:04x}")]
+    UnknownOpcode {
+        offset: Word,
+        word: Word,
+        opcode: usize,
+    },
+
+    #[error("Truncated instruction at 0x{offset:04x}")]
+    TruncatedInstruction { offset: Word },
+
+    #[error("Truncated immediate of instruction at 0x{offset:04x}")]
+    TruncatedImmediate { offset: Word },
+
+    #[error("Out of deassembler bounds at 0x{offset:04x}")]
+    OutOfBounds { offset: Word },
+}
+
+impl DeassemblyError {
+    /// Byte offset of the instruction that failed to deassemble.
+    pub fn offset(&self) -> Word {
+        match self {
+            Self::UnknownOpcode { offset, .. }
+            | Self::TruncatedInstruction { offset }
+            | Self::TruncatedImmediate { offset }
+            | Self::OutOfBounds { offset } => *offset,
+        }
+    }
+}
 
 pub struct Deassembler<'a, I>
 where
     I: Iterator<Item = &'a u8>,
 {
     code_iter: Peekable<I>,
+    offset: Word,
 }
 
 impl<'a, I> Deassembler<'a, I>
@@ -16,10 +63,21 @@ where
     pub fn new(code_iter: I) -> Self {
         Self {
             code_iter: code_iter.peekable(),
+            offset: 0,
         }
     }
 
-    pub fn deassemble(mut self) -> Result<Vec<Instruction>, String> {
+    /// Set the address of the first byte of the code, used for error offsets.
+    pub fn with_offset(mut self, offset: Word) -> Self {
+        self.offset = offset;
+        self
+    }
+
+    pub fn offset(&self) -> Word {
+        self.offset
+    }
+
+    pub fn deassemble(mut self) -> Result<Vec<Instruction>, DeassemblyError> {
         let mut output = Vec::new();
 
         while self.code_iter.peek().is_some() {
@@ -29,31 +87,43 @@ where
         Ok(output)
     }
 
-    pub fn deassemble_text(self) -> String {
-        match self.deassemble() {
-            Ok(deassembly) => deassembly
-                .iter()
-                .map(|instr| format!("{}", instr))
-                .collect(),
+    /// Deassemble everything to text, one instruction per line. Anything undecodable is output as a data word
+    /// along with the error, after which deassembly continues from the next word.
+    pub fn deassemble_text(mut self) -> String {
+        let mut lines = Vec::new();
 
-            Err(e) => e,
+        while self.code_iter.peek().is_some() {
+            lines.push(self.deassemble_instruction_text());
         }
+
+        lines.join("\n")
     }
 
-    pub fn deassemble_instruction(&mut self) -> Result<Instruction, String> {
+    pub fn deassemble_instruction(&mut self) -> Result<Instruction, DeassemblyError> {
+        let offset = self.offset;
+
         if self.code_iter.peek().is_none() {
-            return Err("<out of deassembler bounds>".to_string());
+            return Err(DeassemblyError::OutOfBounds { offset });
         }
 
         let instruction_word = self
             .next_word()
-            .ok_or("<incomplete instruction>".to_string())?;
-
-        let mut instruction = Instruction::deassemble_instruction_word(instruction_word)
-            .map_err(|e| format!("<{}>", e))?;
+            .ok_or(DeassemblyError::TruncatedInstruction { offset })?;
+
+        let mut instruction = Instruction::deassemble_instruction_word(instruction_word).map_err(
+            |InstructionDeassemblyError::UnrecognizedOpcode(opcode)| {
+                DeassemblyError::UnknownOpcode {
+                    offset,
+                    word: instruction_word,
+                    opcode,
+                }
+            },
+        )?;
 
         if instruction.kind.has_immediate() {
-            let immediate = self.next_word().ok_or("<incomplete immediate>")?;
+            let immediate = self
+                .next_word()
+                .ok_or(DeassemblyError::TruncatedImmediate { offset })?;
 
             instruction.immediate = Some(immediate);
         }
@@ -62,15 +132,26 @@ where
     }
 
     pub fn deassemble_instruction_text(&mut self) -> String {
+        self.deassemble_instruction_text_with(&CustomasmSyntax)
+    }
+
+    pub fn deassemble_instruction_text_with(&mut self, syntax: &dyn SyntaxFormatter) -> String {
         match self.deassemble_instruction() {
-            Ok(instr) => format!("{}", instr),
-            Err(e) => e,
+            Ok(instr) => syntax.instruction(&instr),
+            Err(e @ DeassemblyError::UnknownOpcode { word, .. }) => {
+                format!(".word 0x{:04x}  {}", word, syntax.comment(&e.to_string()))
+            }
+            Err(e) => syntax.comment(&e.to_string()),
         }
     }
 
     fn next_word(&mut self) -> Option<Word> {
         let first_byte = *self.code_iter.next()?;
+        self.offset = self.offset.wrapping_add(1);
+
         let second_byte = *self.code_iter.next()?;
+        self.offset = self.offset.wrapping_add(1);
+
         Some(libisa::bytes_to_word([first_byte, second_byte]))
     }
 }
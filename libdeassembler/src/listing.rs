@@ -0,0 +1,258 @@
+use std::{collections::BTreeSet, fmt::Display};
+
+use libisa::{
+    instruction::{kind::InstructionKind, Instruction},
+    mmu::BankMap,
+    Word,
+};
+
+use crate::{
+    text::{source_form, CustomasmSyntax, SyntaxFormatter},
+    Deassembler, DeassemblyError,
+};
+
+/// Maximum amount of data bytes grouped into a single listing record.
+pub const DATA_BYTES_PER_RECORD: usize = 4;
+
+/// Width of the raw bytes column, enough for the longest instruction.
+const BYTES_COLUMN_WIDTH: usize = DATA_BYTES_PER_RECORD * 3 - 1;
+
+/// A deassembled program, one record per instruction or run of data bytes.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Listing {
+    pub records: Vec<ListingRecord>,
+
+    /// Addresses of instructions referred to by immediates elsewhere in the code, most likely jump targets.
+    pub labels: BTreeSet<Word>,
+
+    /// Mapping of the logical addresses of the records to physical memory, shown next to them when given.
+    pub bank_map: Option<BankMap>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ListingRecord {
+    pub addr: Word,
+    pub bytes: Vec<u8>,
+    pub content: ListingContent,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ListingContent {
+    Instruction(Instruction),
+    Data,
+
+    /// Bytes that failed to deassemble, skipped over to continue from the next word.
+    Invalid(DeassemblyError),
+}
+
+impl Listing {
+    /// Deassemble the code linearly from the start, turning anything that can't be decoded into data records.
+    pub fn linear(code: &[u8]) -> Self {
+        let mut records = Vec::new();
+        let mut addr = 0;
+
+        while addr < code.len() {
+            let mut deassembler = Deassembler::new(code[addr..].iter()).with_offset(addr as Word);
+
+            let record = match deassembler.deassemble_instruction() {
+                Ok(instruction) => ListingRecord::instruction(addr, code, instruction),
+                // Skip over a single word and try again from there.
+                Err(e) => ListingRecord::invalid(addr, code, e),
+            };
+
+            addr += record.bytes.len();
+            records.push(record);
+        }
+
+        Self::from_records(records)
+    }
+
+    /// Build a listing from ready records, computing the labels from the immediates in them.
+    pub fn from_records(records: Vec<ListingRecord>) -> Self {
+        let mut listing = Self {
+            records,
+            labels: BTreeSet::new(),
+            bank_map: None,
+        };
+
+        listing.labels = listing
+            .records
+            .iter()
+            .filter_map(|record| record.immediate_target())
+            .filter(|target| listing.is_instruction_addr(*target))
+            .collect();
+
+        listing
+    }
+
+    pub fn with_bank_map(mut self, bank_map: BankMap) -> Self {
+        self.bank_map = Some(bank_map);
+        self
+    }
+
+    pub fn record_at(&self, addr: Word) -> Option<&ListingRecord> {
+        self.records
+            .binary_search_by_key(&addr, |record| record.addr)
+            .ok()
+            .map(|index| &self.records[index])
+    }
+
+    pub fn is_instruction_addr(&self, addr: Word) -> bool {
+        matches!(
+            self.record_at(addr),
+            Some(ListingRecord {
+                content: ListingContent::Instruction(..),
+                ..
+            })
+        )
+    }
+
+    /// The label of the given address, if any instruction refers to it.
+    pub fn label(&self, addr: Word) -> Option<String> {
+        self.labels.contains(&addr).then(|| label_name(addr))
+    }
+}
+
+impl ListingRecord {
+    /// Create an instruction record, taking the instruction's bytes from the code at the given address.
+    pub fn instruction(addr: usize, code: &[u8], instruction: Instruction) -> Self {
+        Self {
+            addr: addr as Word,
+            bytes: code[addr..addr + instruction.kind.len_bytes()].to_vec(),
+            content: ListingContent::Instruction(instruction),
+        }
+    }
+
+    /// Create a data record of at most `len` bytes from the code at the given address.
+    pub fn data(addr: usize, code: &[u8], len: usize) -> Self {
+        let end = (addr + len).min(code.len());
+
+        Self {
+            addr: addr as Word,
+            bytes: code[addr..end].to_vec(),
+            content: ListingContent::Data,
+        }
+    }
+
+    /// Create a record of the word at the given address that failed to deassemble.
+    pub fn invalid(addr: usize, code: &[u8], error: DeassemblyError) -> Self {
+        Self {
+            content: ListingContent::Invalid(error),
+            ..Self::data(addr, code, libisa::BYTES_PER_WORD)
+        }
+    }
+
+    pub fn instruction_ref(&self) -> Option<&Instruction> {
+        match &self.content {
+            ListingContent::Instruction(instruction) => Some(instruction),
+            ListingContent::Data | ListingContent::Invalid(..) => None,
+        }
+    }
+
+    /// Address of the next record following this one.
+    pub fn end_addr(&self) -> usize {
+        self.addr as usize + self.bytes.len()
+    }
+
+    /// The instruction as written in assembly source, see [`source_form`].
+    pub fn source_instruction(&self) -> Option<Instruction> {
+        Some(source_form(self.addr, *self.instruction_ref()?))
+    }
+
+    /// The address referred to by the immediate of a `loadi`, which is how targets of register jumps get into a
+    /// register, or the target of a jump with an immediate or relative target.
+    pub fn immediate_target(&self) -> Option<Word> {
+        match self.instruction_ref()? {
+            Instruction {
+                kind: InstructionKind::LoadI,
+                immediate,
+                ..
+            } => *immediate,
+            instruction => instruction.static_jump_target(self.addr),
+        }
+    }
+}
+
+pub fn label_name(addr: Word) -> String {
+    format!("loc_{:04x}", addr)
+}
+
+impl Listing {
+    /// Render the listing with instructions, labels and comments in the given syntax.
+    pub fn format(&self, syntax: &dyn SyntaxFormatter) -> String {
+        let mut text = String::new();
+
+        for record in &self.records {
+            if let Some(label) = self.label(record.addr) {
+                text.push_str(&syntax.label_definition(&label));
+                text.push('\n');
+            }
+
+            let bytes = record
+                .bytes
+                .iter()
+                .map(|byte| format!("{:02x}", byte))
+                .collect::<Vec<_>>()
+                .join(" ");
+
+            let addr = match &self.bank_map {
+                Some(bank_map) => format!(
+                    "{:04x} ({:05x})",
+                    record.addr,
+                    bank_map.translate(record.addr)
+                ),
+                None => format!("{:04x}", record.addr),
+            };
+
+            text.push_str(&format!(
+                "    {}:  {:<width$}  ",
+                addr,
+                bytes,
+                width = BYTES_COLUMN_WIDTH
+            ));
+
+            match &record.content {
+                ListingContent::Instruction(instruction) => {
+                    text.push_str(&syntax.instruction(&source_form(record.addr, *instruction)));
+
+                    if let Some(label) = record.immediate_target().and_then(|addr| self.label(addr))
+                    {
+                        text.push_str(&format!("  {}", syntax.comment(&label)));
+                    }
+                }
+
+                ListingContent::Data => {
+                    let data = record
+                        .bytes
+                        .iter()
+                        .map(|byte| format!("0x{:02x}", byte))
+                        .collect::<Vec<_>>()
+                        .join(", ");
+
+                    text.push_str(&format!(".byte {}", data));
+                }
+
+                ListingContent::Invalid(error) => {
+                    let data = match record.bytes[..] {
+                        [high, low] => {
+                            format!(".word 0x{:04x}", libisa::bytes_to_word([high, low]))
+                        }
+                        _ => format!(".byte 0x{:02x}", record.bytes[0]),
+                    };
+
+                    text.push_str(&format!("{}  {}", data, syntax.comment(&error.to_string())));
+                }
+            }
+
+            text.push('\n');
+        }
+
+        text
+    }
+}
+
+impl Display for Listing {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.format(&CustomasmSyntax))
+    }
+}
@@ -0,0 +1,350 @@
+use libisa::{
+    instruction::{kind::InstructionKind, Instruction},
+    mmu::BankMap,
+    Word,
+};
+use libplatform::Platform;
+
+use crate::{
+    asm::AsmError,
+    cfg::{ControlFlowGraph, EdgeKind},
+    diff::{DiffEntry, ListingDiff},
+    listing::{Listing, ListingContent, ListingRecord},
+    text::Syntax,
+    traversal::Traversal,
+    Deassembler, DeassemblyError,
+};
+
+const PLATFORM: &str = include_str!("../../customasm/platform.ron");
+
+fn platform() -> Platform {
+    PLATFORM.parse().expect("Error parsing platform.ron")
+}
+
+fn assemble(instructions: &[Instruction]) -> Vec<u8> {
+    libisa::instruction::assembler::assemble(instructions.iter().copied())
+        .unwrap()
+        .machine_code
+}
+
+#[test]
+fn linear_listing_labels_jump_targets() {
+    let code = assemble(&[
+        Instruction::new(InstructionKind::LoadI)
+            .with_reg_a(0)
+            .with_immediate(6),
+        Instruction::new(InstructionKind::Jmp).with_reg_a(0),
+        Instruction::new(InstructionKind::Halt),
+    ]);
+
+    let listing = Listing::linear(&code);
+
+    assert_eq!(listing.records.len(), 3);
+    assert_eq!(listing.records[2].addr, 6);
+    assert!(listing.labels.contains(&6), "Jump target wasn't labeled");
+    assert!(listing.to_string().contains("loc_0006:"));
+}
+
+#[test]
+fn linear_listing_continues_past_undecodable_word() {
+    let mut code = vec![0xFF, 0xFF]; // Opcode 63 doesn't exist.
+    code.extend(assemble(&[Instruction::new(InstructionKind::Halt)]));
+
+    let listing = Listing::linear(&code);
+
+    assert_eq!(
+        listing.records[0].content,
+        ListingContent::Invalid(DeassemblyError::UnknownOpcode {
+            offset: 0,
+            word: 0xFFFF,
+            opcode: 63
+        })
+    );
+    assert!(listing.to_string().contains(".word 0xffff"));
+    assert_eq!(
+        listing.records[1].instruction_ref(),
+        Some(&Instruction::new(InstructionKind::Halt))
+    );
+}
+
+#[test]
+fn traversal_separates_code_from_data() {
+    let mut code = assemble(&[
+        Instruction::new(InstructionKind::LoadI)
+            .with_reg_a(3)
+            .with_immediate(8),
+        Instruction::new(InstructionKind::JmpZ).with_reg_a(3),
+        Instruction::new(InstructionKind::Halt),
+        Instruction::new(InstructionKind::Halt),
+    ]);
+    code.extend(b"Hi\0\0");
+
+    let traversal = Traversal::from_entry(&code, 0);
+
+    assert_eq!(traversal.jump_targets.get(&4), Some(&8));
+    assert!(traversal.unresolved_jumps.is_empty());
+    assert!(traversal.is_reachable(8));
+    assert!(
+        !traversal.is_reachable(10),
+        "Data after halt was treated as code"
+    );
+
+    let listing = Listing::traversed(&code, 0);
+    let last = listing.records.last().unwrap();
+
+    assert_eq!(last.addr, 10);
+    assert_eq!(last.content, ListingContent::Data);
+}
+
+#[test]
+fn traversal_flags_unresolved_jumps() {
+    let code = assemble(&[
+        Instruction::new(InstructionKind::Load)
+            .with_reg_a(0)
+            .with_reg_b(1),
+        Instruction::new(InstructionKind::Jmp).with_reg_a(0),
+    ]);
+
+    let traversal = Traversal::from_entry(&code, 0);
+
+    assert!(traversal.unresolved_jumps.contains(&2));
+}
+
+#[test]
+fn cfg_splits_blocks_at_jumps_and_targets() {
+    let code = assemble(&[
+        Instruction::new(InstructionKind::LoadI)
+            .with_reg_a(3)
+            .with_immediate(8),
+        Instruction::new(InstructionKind::JmpZ).with_reg_a(3),
+        Instruction::new(InstructionKind::Nop),
+        Instruction::new(InstructionKind::Halt),
+    ]);
+
+    let cfg = ControlFlowGraph::recover(&code, 0);
+
+    assert_eq!(cfg.blocks.keys().copied().collect::<Vec<_>>(), [0, 6, 8]);
+
+    let entry_edges = &cfg.blocks[&0].edges;
+    assert!(entry_edges
+        .iter()
+        .any(|edge| edge.target == 8 && edge.kind == EdgeKind::Jump(InstructionKind::JmpZ)));
+    assert!(entry_edges
+        .iter()
+        .any(|edge| edge.target == 6 && edge.kind == EdgeKind::Fallthrough));
+
+    assert!(cfg.to_dot().contains("loc_0000 -> loc_0008"));
+}
+
+#[test]
+fn cfg_labels_show_immediate_jumps() {
+    let code = assemble(&[
+        Instruction::new(InstructionKind::Nop),
+        // Jumps back to the nop, 6 bytes before the next instruction.
+        Instruction::new(InstructionKind::JmpZR).with_immediate(0u16.wrapping_sub(6)),
+        Instruction::new(InstructionKind::JmpI).with_immediate(0),
+    ]);
+
+    let dot = ControlFlowGraph::recover(&code, 0).to_dot();
+
+    assert!(dot.contains("0002  jmpzr $0\\l"), "{}", dot);
+    assert!(dot.contains("0006  jmpi $0\\l"), "{}", dot);
+}
+
+#[test]
+fn customasm_output_uses_labels_and_data() {
+    let mut code = assemble(&[
+        Instruction::new(InstructionKind::LoadI)
+            .with_reg_a(3)
+            .with_immediate(6),
+        Instruction::new(InstructionKind::Jmp).with_reg_a(3),
+        Instruction::new(InstructionKind::Halt),
+    ]);
+    code.extend([0x00, 0x40]); // A nop with register A set, which customasm couldn't produce.
+
+    let asm = Listing::linear(&code).to_customasm(&platform()).unwrap();
+
+    assert!(asm.starts_with("#include \"rules.asm\""));
+    assert!(asm.contains("loc_0006:\n    halt"));
+    assert!(asm.contains("loadi %3, $loc_0006"));
+    assert!(asm.contains("#d 0x00, 0x40"));
+    assert!(!asm.contains("#fill"));
+}
+
+#[test]
+fn customasm_output_leaves_padding_to_code_bank() {
+    let platform = platform();
+    let bank_size = platform.code_region().size as usize;
+
+    let mut code = assemble(&[Instruction::new(InstructionKind::Halt)]);
+    code.resize(bank_size, 0);
+
+    let asm = Listing::linear(&code).to_customasm(&platform).unwrap();
+    assert!(
+        asm.contains("    halt\n    ; Trailing zero bytes are restored by the code bank's #fill.")
+    );
+
+    code.extend([0, 0]);
+    assert_eq!(
+        Listing::linear(&code).to_customasm(&platform),
+        Err(AsmError::DoesntFitCodeBank {
+            len: bank_size + 2,
+            bank_size
+        })
+    );
+}
+
+#[test]
+fn errors_carry_offsets() {
+    let code = assemble(&[
+        Instruction::new(InstructionKind::Nop),
+        Instruction::new(InstructionKind::LoadI)
+            .with_reg_a(0)
+            .with_immediate(0),
+    ]);
+
+    let truncated = &code[..code.len() - 1];
+    let error = Deassembler::new(truncated.iter())
+        .with_offset(0x100)
+        .deassemble()
+        .unwrap_err();
+
+    assert_eq!(error, DeassemblyError::TruncatedImmediate { offset: 0x102 });
+}
+
+#[test]
+fn syntax_flavours_format_operands() {
+    let load = Instruction::new(InstructionKind::Load)
+        .with_reg_a(1)
+        .with_reg_b(2);
+
+    let customasm = Syntax::Customasm.formatter();
+    let verbose = Syntax::Verbose.formatter();
+    let ansi = Syntax::Ansi.formatter();
+
+    assert_eq!(customasm.instruction(&load), load.to_string());
+    assert_eq!(verbose.instruction(&load), "load dest=%1, src_addr=%2");
+    assert!(ansi.instruction(&load).contains("\x1b["));
+    assert_eq!("verbose".parse(), Ok(Syntax::Verbose));
+}
+
+#[test]
+fn diff_treats_shifted_jump_targets_as_equivalent() {
+    let jump_to_halt = |target| {
+        vec![
+            Instruction::new(InstructionKind::LoadI)
+                .with_reg_a(0)
+                .with_immediate(target),
+            Instruction::new(InstructionKind::Jmp).with_reg_a(0),
+        ]
+    };
+
+    let mut old = jump_to_halt(6);
+    old.push(Instruction::new(InstructionKind::Halt));
+
+    // A nop inserted before the halt shifts the jump target.
+    let mut new = jump_to_halt(8);
+    new.push(Instruction::new(InstructionKind::Nop));
+    new.push(Instruction::new(InstructionKind::Halt));
+
+    let old = Listing::linear(&assemble(&old));
+    let new = Listing::linear(&assemble(&new));
+    let diff = ListingDiff::new(&old, &new);
+
+    let changes: Vec<_> = diff
+        .entries
+        .iter()
+        .filter(|entry| !matches!(entry, DiffEntry::Same { .. }))
+        .collect();
+
+    assert!(matches!(changes[..], [DiffEntry::Inserted(ref record)] if record.addr == 6));
+    assert!(diff
+        .format(&old, &new, Syntax::Customasm.formatter())
+        .contains("+      0006  nop"));
+}
+
+#[test]
+fn diff_aligns_changes_throughout_the_listings() {
+    let listing = |immediates: &[Word]| {
+        let instructions: Vec<_> = immediates
+            .iter()
+            .map(|&immediate| {
+                Instruction::new(InstructionKind::AddI)
+                    .with_reg_a(0)
+                    .with_immediate(immediate)
+            })
+            .collect();
+
+        Listing::linear(&assemble(&instructions))
+    };
+
+    let old = listing(&[1, 2, 3, 4, 5, 6, 7, 8]);
+    let new = listing(&[0, 1, 2, 4, 5, 9, 7, 8, 10]);
+
+    let immediate = |record: &ListingRecord| record.instruction_ref().unwrap().immediate.unwrap();
+    let entries: Vec<_> = ListingDiff::new(&old, &new)
+        .entries
+        .iter()
+        .map(|entry| match entry {
+            DiffEntry::Same { old, new } => ('=', Some(immediate(old)), Some(immediate(new))),
+            DiffEntry::Removed(old) => ('-', Some(immediate(old)), None),
+            DiffEntry::Inserted(new) => ('+', None, Some(immediate(new))),
+            DiffEntry::Changed { old, new } => ('!', Some(immediate(old)), Some(immediate(new))),
+        })
+        .collect();
+
+    assert_eq!(
+        entries,
+        [
+            ('+', None, Some(0)),
+            ('=', Some(1), Some(1)),
+            ('=', Some(2), Some(2)),
+            ('-', Some(3), None),
+            ('=', Some(4), Some(4)),
+            ('=', Some(5), Some(5)),
+            ('!', Some(6), Some(9)),
+            ('=', Some(7), Some(7)),
+            ('=', Some(8), Some(8)),
+            ('+', None, Some(10)),
+        ]
+    );
+}
+
+#[test]
+fn relative_jumps_show_absolute_targets() {
+    let code = assemble(&[
+        Instruction::new(InstructionKind::Nop),
+        // Jumps back to the nop, 6 bytes before the next instruction.
+        Instruction::new(InstructionKind::JmpZR).with_immediate(0u16.wrapping_sub(6)),
+        Instruction::new(InstructionKind::Halt),
+    ]);
+
+    let listing = Listing::traversed(&code, 0);
+
+    assert!(listing.labels.contains(&0));
+    assert!(listing.to_string().contains("jmpzr $0  ; loc_0000"));
+    assert!(listing
+        .to_customasm(&platform())
+        .unwrap()
+        .contains("jmpzr $loc_0000"));
+}
+
+#[test]
+fn banked_listing_shows_physical_addresses() {
+    let mut physical = vec![0; 0x21000];
+    physical[0x20000..0x20002]
+        .copy_from_slice(&assemble(&[Instruction::new(InstructionKind::Halt)]));
+
+    let bank_map: BankMap = "0x20".parse().unwrap();
+    let logical = bank_map.logical_view(&physical);
+
+    assert_eq!(
+        logical.len(),
+        0x10000,
+        "Every window is mapped into the image"
+    );
+    assert_eq!(logical[..2], physical[0x20000..0x20002]);
+
+    let listing = Listing::linear(&logical[..2]).with_bank_map(bank_map);
+    assert!(listing.to_string().contains("0000 (20000):  68 00"));
+}
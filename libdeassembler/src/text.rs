@@ -0,0 +1,266 @@
+use std::{
+    fmt::{self, Display},
+    str::FromStr,
+};
+
+use libisa::{
+    instruction::{kind::InstructionKind, Instruction},
+    Immediate, Register, Word,
+};
+
+/// Build a string with `write!` and `writeln!`, whose results can be ignored as writing to a string can't fail.
+pub(crate) fn write_string(write: impl FnOnce(&mut String) -> fmt::Result) -> String {
+    let mut text = String::new();
+    let _ = write(&mut text);
+    text
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operand<'a> {
+    Register(Register),
+    Immediate(Immediate),
+
+    /// An immediate referring to a labeled address.
+    Label(&'a str),
+}
+
+/// Turns deassembled instructions into text in a specific assembly syntax flavour.
+pub trait SyntaxFormatter {
+    fn mnemonic(&self, kind: InstructionKind) -> String;
+
+    /// Format an operand, `role` being its name in `rules.asm` (e.g. `dest` or `src_addr`).
+    fn operand(&self, role: &str, operand: Operand) -> String;
+
+    fn label_definition(&self, label: &str) -> String;
+
+    fn comment(&self, comment: &str) -> String;
+
+    fn instruction(&self, instruction: &Instruction) -> String {
+        self.instruction_with_label(instruction, None)
+    }
+
+    /// Format an instruction, replacing its immediate with the label if one is given.
+    fn instruction_with_label(&self, instruction: &Instruction, label: Option<&str>) -> String {
+        let [reg_a_role, reg_b_role, immediate_role] = operand_roles(instruction.kind);
+
+        let immediate = match (label, instruction.immediate) {
+            (Some(label), Some(_)) => Some(Operand::Label(label)),
+            (_, immediate) => immediate.map(Operand::Immediate),
+        };
+
+        let operands = [
+            (reg_a_role, instruction.reg_a.map(Operand::Register)),
+            (reg_b_role, instruction.reg_b.map(Operand::Register)),
+            (immediate_role, immediate),
+        ]
+        .into_iter()
+        .filter_map(|(role, operand)| Some(self.operand(role, operand?)))
+        .collect::<Vec<_>>();
+
+        let mnemonic = self.mnemonic(instruction.kind);
+
+        if operands.is_empty() {
+            mnemonic
+        } else {
+            format!("{} {}", mnemonic, operands.join(", "))
+        }
+    }
+}
+
+/// Names of the register A, register B and immediate operands of the instruction kind, as in `rules.asm`.
+pub fn operand_roles(kind: InstructionKind) -> [&'static str; 3] {
+    match kind {
+        InstructionKind::LoadI => ["dest", "reg_b", "value"],
+        InstructionKind::Load | InstructionKind::LoadH | InstructionKind::LoadL => {
+            ["dest", "src_addr", "imm"]
+        }
+        InstructionKind::Store | InstructionKind::StoreH | InstructionKind::StoreL => {
+            ["dest_addr", "src", "imm"]
+        }
+        InstructionKind::LoadO | InstructionKind::LoadHO | InstructionKind::LoadLO => {
+            ["dest", "src_addr", "offset"]
+        }
+        InstructionKind::StoreO | InstructionKind::StoreHO | InstructionKind::StoreLO => {
+            ["dest_addr", "src", "offset"]
+        }
+        InstructionKind::Cpy => ["dest", "src", "imm"],
+        InstructionKind::GetF => ["dest", "reg_b", "imm"],
+        InstructionKind::SetF => ["src", "reg_b", "imm"],
+        InstructionKind::GetCr => ["dest", "reg_b", "cr"],
+        InstructionKind::SetCr => ["src", "reg_b", "cr"],
+        InstructionKind::Jmp
+        | InstructionKind::JmpC
+        | InstructionKind::JmpZ
+        | InstructionKind::JmpN
+        | InstructionKind::JmpO
+        | InstructionKind::JmpL => ["addr", "reg_b", "imm"],
+        InstructionKind::Add
+        | InstructionKind::Sub
+        | InstructionKind::AddC
+        | InstructionKind::SubC
+        | InstructionKind::And
+        | InstructionKind::Cmp => ["a", "b", "imm"],
+        InstructionKind::AddI
+        | InstructionKind::SubI
+        | InstructionKind::AndI
+        | InstructionKind::CmpI => ["a", "reg_b", "value"],
+        InstructionKind::JmpI
+        | InstructionKind::JmpCI
+        | InstructionKind::JmpZI
+        | InstructionKind::JmpR
+        | InstructionKind::JmpCR
+        | InstructionKind::JmpZR
+        | InstructionKind::JmpNI
+        | InstructionKind::JmpOI
+        | InstructionKind::JmpLI
+        | InstructionKind::JmpNR
+        | InstructionKind::JmpOR
+        | InstructionKind::JmpLR => ["reg_a", "reg_b", "addr"],
+        // Operands that aren't part of the instruction only show up if their fields are non-zero.
+        InstructionKind::Nop
+        | InstructionKind::Halt
+        | InstructionKind::Sys
+        | InstructionKind::Sret => ["reg_a", "reg_b", "imm"],
+    }
+}
+
+/// The instruction as written in assembly source, relative jumps taking the absolute target address rather than the
+/// encoded offset, given the address of the instruction.
+pub fn source_form(addr: Word, instruction: Instruction) -> Instruction {
+    match instruction.static_jump_target(addr) {
+        Some(target) if instruction.kind.is_relative_jump() => instruction.with_immediate(target),
+        _ => instruction,
+    }
+}
+
+/// The syntax accepted by customasm with `rules.asm`, which the `Display` implementation of instructions also writes,
+/// only without labels.
+pub struct CustomasmSyntax;
+
+impl SyntaxFormatter for CustomasmSyntax {
+    fn mnemonic(&self, kind: InstructionKind) -> String {
+        kind.to_string()
+    }
+
+    fn operand(&self, _role: &str, operand: Operand) -> String {
+        match operand {
+            Operand::Register(reg) => format!("%{}", reg),
+            Operand::Immediate(immediate) => format!("${}", immediate),
+            Operand::Label(label) => format!("${}", label),
+        }
+    }
+
+    fn label_definition(&self, label: &str) -> String {
+        format!("{}:", label)
+    }
+
+    fn comment(&self, comment: &str) -> String {
+        format!("; {}", comment)
+    }
+}
+
+/// Customasm syntax with the role of every operand spelled out, e.g. `load dest=%1, src_addr=%2`.
+pub struct VerboseSyntax;
+
+impl SyntaxFormatter for VerboseSyntax {
+    fn mnemonic(&self, kind: InstructionKind) -> String {
+        kind.to_string()
+    }
+
+    fn operand(&self, role: &str, operand: Operand) -> String {
+        format!("{}={}", role, CustomasmSyntax.operand(role, operand))
+    }
+
+    fn label_definition(&self, label: &str) -> String {
+        CustomasmSyntax.label_definition(label)
+    }
+
+    fn comment(&self, comment: &str) -> String {
+        CustomasmSyntax.comment(comment)
+    }
+}
+
+/// Customasm syntax highlighted with ANSI escape codes for terminal output.
+pub struct AnsiSyntax;
+
+impl AnsiSyntax {
+    const RESET: &'static str = "\x1b[0m";
+    const MNEMONIC: &'static str = "\x1b[1;33m";
+    const REGISTER: &'static str = "\x1b[36m";
+    const IMMEDIATE: &'static str = "\x1b[35m";
+    const LABEL: &'static str = "\x1b[32m";
+    const COMMENT: &'static str = "\x1b[2m";
+
+    fn paint(color: &str, text: String) -> String {
+        format!("{}{}{}", color, text, Self::RESET)
+    }
+}
+
+impl SyntaxFormatter for AnsiSyntax {
+    fn mnemonic(&self, kind: InstructionKind) -> String {
+        Self::paint(Self::MNEMONIC, kind.to_string())
+    }
+
+    fn operand(&self, role: &str, operand: Operand) -> String {
+        let color = match operand {
+            Operand::Register(..) => Self::REGISTER,
+            Operand::Immediate(..) => Self::IMMEDIATE,
+            Operand::Label(..) => Self::LABEL,
+        };
+
+        Self::paint(color, CustomasmSyntax.operand(role, operand))
+    }
+
+    fn label_definition(&self, label: &str) -> String {
+        Self::paint(Self::LABEL, CustomasmSyntax.label_definition(label))
+    }
+
+    fn comment(&self, comment: &str) -> String {
+        Self::paint(Self::COMMENT, CustomasmSyntax.comment(comment))
+    }
+}
+
+/// Selectable syntax flavours, parseable from their names for command line use.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Syntax {
+    #[default]
+    Customasm,
+    Verbose,
+    Ansi,
+}
+
+impl Syntax {
+    pub fn formatter(self) -> &'static dyn SyntaxFormatter {
+        match self {
+            Self::Customasm => &CustomasmSyntax,
+            Self::Verbose => &VerboseSyntax,
+            Self::Ansi => &AnsiSyntax,
+        }
+    }
+}
+
+impl FromStr for Syntax {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "customasm" => Ok(Self::Customasm),
+            "verbose" => Ok(Self::Verbose),
+            "ansi" => Ok(Self::Ansi),
+            _ => Err(format!(
+                "Unknown syntax '{}', expected customasm, verbose or ansi",
+                s
+            )),
+        }
+    }
+}
+
+impl Display for Syntax {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Customasm => "customasm",
+            Self::Verbose => "verbose",
+            Self::Ansi => "ansi",
+        })
+    }
+}
@@ -0,0 +1,135 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use libisa::{
+    instruction::{kind::InstructionKind, Instruction},
+    Register, Word,
+};
+
+use crate::{
+    listing::{Listing, ListingRecord, DATA_BYTES_PER_RECORD},
+    Deassembler,
+};
+
+/// Code discovered by following the control flow of a program from an entry point, as opposed to
+/// blindly deassembling everything from start to end.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Traversal {
+    /// Every reachable instruction by its address.
+    pub instructions: BTreeMap<Word, Instruction>,
+
+    /// Resolved jump targets by the address of the jump instruction.
+    pub jump_targets: BTreeMap<Word, Word>,
+
+    /// Addresses of jump instructions whose target register couldn't be resolved to a constant.
+    pub unresolved_jumps: BTreeSet<Word>,
+}
+
+impl Traversal {
+    pub fn from_entry(code: &[u8], entry: Word) -> Self {
+        let mut traversal = Self::default();
+        let mut pending = vec![entry];
+
+        while let Some(addr) = pending.pop() {
+            traversal.follow(code, addr, &mut pending);
+        }
+
+        traversal
+    }
+
+    pub fn is_reachable(&self, addr: Word) -> bool {
+        self.instructions.contains_key(&addr)
+    }
+
+    /// Follow the fallthrough path starting from the address until it ends, queuing any jump targets found.
+    fn follow(&mut self, code: &[u8], mut addr: Word, pending: &mut Vec<Word>) {
+        // Registers known to contain a constant on the current path, which is how jump targets are resolved.
+        // Not carried over to jump targets, as the register state there may just as well come from elsewhere.
+        let mut constants: HashMap<Register, Word> = HashMap::new();
+
+        while !self.is_reachable(addr) {
+            let Some(instruction) = Self::instruction_at(code, addr) else {
+                return; // Ran out of code or into something undecodable, either way nothing more to follow.
+            };
+
+            self.instructions.insert(addr, instruction);
+
+            if instruction.kind.is_jump() {
+                let target = instruction.static_jump_target(addr).or_else(|| {
+                    instruction
+                        .reg_a
+                        .and_then(|reg| constants.get(&reg).copied())
+                });
+
+                match target {
+                    Some(target) => {
+                        self.jump_targets.insert(addr, target);
+                        pending.push(target);
+                    }
+                    None => {
+                        self.unresolved_jumps.insert(addr);
+                    }
+                }
+
+                if !instruction.kind.is_conditional_jump() {
+                    return;
+                }
+            }
+
+            if instruction.kind.stops_flow() {
+                return;
+            }
+
+            if let Some(reg_a) = instruction
+                .reg_a
+                .filter(|_| instruction.kind.writes_reg_a())
+            {
+                match (instruction.kind, instruction.immediate) {
+                    (InstructionKind::LoadI, Some(immediate)) => constants.insert(reg_a, immediate),
+                    _ => constants.remove(&reg_a),
+                };
+            }
+
+            addr = addr.wrapping_add(instruction.kind.len_bytes() as Word);
+        }
+    }
+
+    fn instruction_at(code: &[u8], addr: Word) -> Option<Instruction> {
+        let code = code.get(addr as usize..)?;
+        Deassembler::new(code.iter()).deassemble_instruction().ok()
+    }
+}
+
+impl Listing {
+    /// Deassemble only the code reachable from the entry point, everything else is turned into data records.
+    pub fn traversed(code: &[u8], entry: Word) -> Self {
+        let traversal = Traversal::from_entry(code, entry);
+
+        let mut records = Vec::new();
+        let mut addr = 0;
+
+        while addr < code.len() {
+            let record = match traversal.instructions.get(&(addr as Word)) {
+                Some(instruction) => ListingRecord::instruction(addr, code, *instruction),
+                None => {
+                    // Don't let data run over the start of the next instruction.
+                    let next_instruction = traversal
+                        .instructions
+                        .range(addr as Word..)
+                        .next()
+                        .map_or(code.len(), |(next_addr, _)| *next_addr as usize);
+
+                    ListingRecord::data(
+                        addr,
+                        code,
+                        DATA_BYTES_PER_RECORD.min(next_instruction - addr),
+                    )
+                }
+            };
+
+            addr = record.end_addr();
+            records.push(record);
+        }
+
+        Self::from_records(records)
+    }
+}
@@ -1,8 +1,18 @@
 use bitflags::bitflags;
 
 bitflags! {
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
     pub struct ALUFlags: u16 {
-        const CARRY = 0b1;
-        const ZERO  = 0b10;
+        const CARRY    = 0b1;
+        const ZERO     = 0b10;
+        const NEGATIVE = 0b100;
+        const OVERFLOW = 0b1000;
+    }
+}
+
+impl ALUFlags {
+    /// Whether the last subtraction had its A operand less than the B operand, both taken as signed.
+    pub fn signed_less(&self) -> bool {
+        self.contains(Self::NEGATIVE) != self.contains(Self::OVERFLOW)
     }
 }
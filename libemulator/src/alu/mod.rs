@@ -1,8 +1,11 @@
 use flags::ALUFlags;
-use libisa::Word;
+use libisa::{Word, WordSigned};
 
 pub mod flags;
 
+#[cfg(test)]
+mod tests;
+
 pub struct ALU {
     pub flags: ALUFlags,
 }
@@ -15,48 +18,62 @@ impl ALU {
     }
 
     pub fn add(&mut self, a: Word, b: Word) -> Word {
-        let (value, carry) = a.overflowing_add(b);
-        self.flags_by(value, carry)
+        self.add_with_carry(a, b, false)
     }
 
     pub fn sub(&mut self, a: Word, b: Word) -> Word {
-        let (value, carry) = a.overflowing_sub(b);
-        self.flags_by(value, carry)
+        self.sub_with_borrow(a, b, false)
+    }
+
+    /// Set the flags like a subtraction would, discarding the result.
+    pub fn compare(&mut self, a: Word, b: Word) {
+        self.sub(a, b);
     }
 
     pub fn and(&mut self, a: Word, b: Word) -> Word {
         let value = a & b;
-        self.flags_by(value, false)
+        self.flags_by(value, false, false)
     }
 
     pub fn addc(&mut self, a: Word, b: Word) -> Word {
-        let carry = if self.flags.contains(ALUFlags::CARRY) {
-            1
-        } else {
-            0
-        };
-        self.add(a, b + carry)
+        let carry = self.flags.contains(ALUFlags::CARRY);
+        self.add_with_carry(a, b, carry)
     }
 
     pub fn subc(&mut self, a: Word, b: Word) -> Word {
-        let carry = if self.flags.contains(ALUFlags::CARRY) {
-            1
-        } else {
-            0
-        };
-        self.sub(a, b + carry)
-    }
-
-    fn flags_by(&mut self, value: Word, carry: bool) -> Word {
-        self.flags = if carry {
-            ALUFlags::CARRY
-        } else {
-            ALUFlags::empty()
-        } | if value == 0 {
-            ALUFlags::ZERO
-        } else {
-            ALUFlags::empty()
-        };
+        let borrow = self.flags.contains(ALUFlags::CARRY);
+        self.sub_with_borrow(a, b, borrow)
+    }
+
+    /// Add with the carry out being the 17th bit of the full result, which `b + carry` alone can't hold.
+    fn add_with_carry(&mut self, a: Word, b: Word, carry: bool) -> Word {
+        let wide = a as u32 + b as u32 + carry as u32;
+        let signed_wide = a as WordSigned as i32 + b as WordSigned as i32 + carry as i32;
+
+        let value = wide as Word;
+        let overflow = signed_wide != value as WordSigned as i32;
+
+        self.flags_by(value, wide > Word::MAX as u32, overflow)
+    }
+
+    /// Subtract with the carry out being the borrow of the full result.
+    fn sub_with_borrow(&mut self, a: Word, b: Word, borrow: bool) -> Word {
+        let wide = a as i32 - b as i32 - borrow as i32;
+        let signed_wide = a as WordSigned as i32 - b as WordSigned as i32 - borrow as i32;
+
+        let value = wide as Word;
+        let overflow = signed_wide != value as WordSigned as i32;
+
+        self.flags_by(value, wide < 0, overflow)
+    }
+
+    fn flags_by(&mut self, value: Word, carry: bool, overflow: bool) -> Word {
+        self.flags = ALUFlags::empty();
+
+        self.flags.set(ALUFlags::CARRY, carry);
+        self.flags.set(ALUFlags::ZERO, value == 0);
+        self.flags.set(ALUFlags::NEGATIVE, (value as WordSigned) < 0);
+        self.flags.set(ALUFlags::OVERFLOW, overflow);
 
         value
     }
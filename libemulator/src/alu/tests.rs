@@ -0,0 +1,238 @@
+use libisa::{
+    instruction::{kind::InstructionKind, Instruction},
+    Word,
+};
+
+use crate::{Emulator, ExecuteOk};
+
+use super::{flags::ALUFlags, ALU};
+
+/// Operands around every boundary the flags depend on, combined with each other and swept against every word.
+const EDGE_OPERANDS: [Word; 12] = [
+    0x0000, 0x0001, 0x0002, 0x00FF, 0x0100, 0x7FFE, 0x7FFF, 0x8000, 0x8001, 0xFF00, 0xFFFE, 0xFFFF,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Add,
+    Sub,
+    AddC,
+    SubC,
+    And,
+}
+
+const OPS: [Op; 5] = [Op::Add, Op::Sub, Op::AddC, Op::SubC, Op::And];
+
+impl Op {
+    fn execute(self, alu: &mut ALU, a: Word, b: Word) -> Word {
+        match self {
+            Op::Add => alu.add(a, b),
+            Op::Sub => alu.sub(a, b),
+            Op::AddC => alu.addc(a, b),
+            Op::SubC => alu.subc(a, b),
+            Op::And => alu.and(a, b),
+        }
+    }
+
+    /// Reference result and flags, computed bitwise rather than the way the ALU does it.
+    fn reference(self, a: Word, b: Word, flags_in: ALUFlags) -> (Word, ALUFlags) {
+        let carry_in = match self {
+            Op::AddC | Op::SubC => flags_in.contains(ALUFlags::CARRY) as u64,
+            _ => 0,
+        };
+
+        let sign = |word: Word| word & 0x8000 != 0;
+
+        let (value, carry, overflow) = match self {
+            Op::Add | Op::AddC => {
+                let full = a as u64 + b as u64 + carry_in;
+                let value = full as Word;
+
+                (value, full >> 16 != 0, sign(a) == sign(b) && sign(value) != sign(a))
+            }
+            Op::Sub | Op::SubC => {
+                let subtrahend = b as u64 + carry_in;
+                let value = (a as u64).wrapping_sub(subtrahend) as Word;
+
+                (value, subtrahend > a as u64, sign(a) != sign(b) && sign(value) != sign(a))
+            }
+            Op::And => (a & b, false, false),
+        };
+
+        let mut flags = ALUFlags::empty();
+        flags.set(ALUFlags::CARRY, carry);
+        flags.set(ALUFlags::ZERO, value == 0);
+        flags.set(ALUFlags::NEGATIVE, sign(value));
+        flags.set(ALUFlags::OVERFLOW, overflow);
+
+        (value, flags)
+    }
+}
+
+fn check(op: Op, a: Word, b: Word, flags_in: ALUFlags) -> ALUFlags {
+    let mut alu = ALU::new();
+    alu.flags = flags_in;
+
+    let value = op.execute(&mut alu, a, b);
+    let expected = op.reference(a, b, flags_in);
+
+    assert_eq!(
+        (value, alu.flags),
+        expected,
+        "{:?} 0x{:04x}, 0x{:04x} with {:?}",
+        op,
+        a,
+        b,
+        flags_in
+    );
+
+    alu.flags
+}
+
+#[test]
+fn negative_and_overflow_flags_get_set() {
+    let mut alu = ALU::new();
+
+    alu.sub(1, 2);
+    assert_eq!(alu.flags, ALUFlags::CARRY | ALUFlags::NEGATIVE);
+
+    alu.add(0x7FFF, 1);
+    assert_eq!(alu.flags, ALUFlags::NEGATIVE | ALUFlags::OVERFLOW);
+
+    alu.sub(0x8000, 1);
+    assert_eq!(alu.flags, ALUFlags::OVERFLOW);
+
+    alu.and(0x8000, 0xFFFF);
+    assert_eq!(alu.flags, ALUFlags::NEGATIVE);
+}
+
+#[test]
+fn signed_less_follows_subtraction() {
+    let mut alu = ALU::new();
+
+    for (a, b) in [(-3i16, 5i16), (5, -3), (i16::MIN, 1), (1, i16::MIN), (-1, -1)] {
+        alu.sub(a as Word, b as Word);
+        assert_eq!(alu.flags.signed_less(), a < b, "{} < {}", a, b);
+    }
+}
+
+#[test]
+fn signed_less_jump_is_taken() -> anyhow::Result<()> {
+    let program = libisa::instruction::assembler::assemble([
+        Instruction::new(InstructionKind::LoadI)
+            .with_reg_a(0)
+            .with_immediate(-3i16 as Word),
+        Instruction::new(InstructionKind::SubI)
+            .with_reg_a(0)
+            .with_immediate(5),
+        Instruction::new(InstructionKind::JmpLI).with_immediate(14),
+        Instruction::new(InstructionKind::Nop),
+        Instruction::new(InstructionKind::Halt),
+    ])?
+    .machine_code;
+
+    let mut emulator = Emulator::new(program)?;
+
+    assert_eq!(emulator.execute_instruction()?, ExecuteOk::Normal);
+    assert_eq!(emulator.execute_instruction()?, ExecuteOk::Normal);
+    assert_eq!(emulator.execute_instruction()?, ExecuteOk::Normal);
+    assert_eq!(emulator.pc, 14);
+    assert_eq!(emulator.execute_instruction()?, ExecuteOk::Halted);
+
+    Ok(())
+}
+
+#[test]
+fn ops_conform_for_every_input_flag_combination() {
+    for op in OPS {
+        for flags_in in (0..=ALUFlags::all().bits()).map(ALUFlags::from_bits_truncate) {
+            for a in EDGE_OPERANDS {
+                for b in EDGE_OPERANDS {
+                    check(op, a, b, flags_in);
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn ops_conform_for_every_word() {
+    for op in OPS {
+        let mut seen_set = ALUFlags::empty();
+        let mut seen_clear = ALUFlags::empty();
+
+        for flags_in in [ALUFlags::empty(), ALUFlags::CARRY] {
+            for edge in EDGE_OPERANDS {
+                for word in 0..=Word::MAX {
+                    for (a, b) in [(word, edge), (edge, word)] {
+                        let flags = check(op, a, b, flags_in);
+
+                        seen_set |= flags;
+                        seen_clear |= flags.complement();
+                    }
+                }
+            }
+        }
+
+        // Make sure the sweep actually exercises every flag the op can produce, both ways.
+        let expected_set = match op {
+            Op::And => ALUFlags::ZERO | ALUFlags::NEGATIVE,
+            _ => ALUFlags::all(),
+        };
+
+        assert_eq!(seen_set, expected_set, "{:?}", op);
+        assert_eq!(seen_clear, ALUFlags::all(), "{:?}", op);
+    }
+}
+
+#[test]
+fn carry_chains_across_words() {
+    let mut alu = ALU::new();
+
+    // 0x0001_FFFF + 0x0000_FFFF = 0x0002_FFFE, the low word carrying into the high one with b = 0xFFFF.
+    assert_eq!(alu.add(0xFFFF, 0xFFFF), 0xFFFE);
+    assert_eq!(alu.addc(0x0001, 0x0000), 0x0002);
+    assert!(!alu.flags.contains(ALUFlags::CARRY));
+
+    alu.flags = ALUFlags::CARRY;
+    assert_eq!(alu.addc(0x0000, 0xFFFF), 0x0000);
+    assert!(alu.flags.contains(ALUFlags::CARRY | ALUFlags::ZERO));
+
+    // 0x0001_0000 - 0x0000_FFFF = 0x0000_0001, the low word borrowing from the high one.
+    assert_eq!(alu.sub(0x0000, 0xFFFF), 0x0001);
+    assert_eq!(alu.subc(0x0001, 0x0000), 0x0000);
+    assert!(!alu.flags.contains(ALUFlags::CARRY));
+
+    alu.flags = ALUFlags::CARRY;
+    assert_eq!(alu.subc(0x0000, 0xFFFF), 0x0000);
+    assert!(alu.flags.contains(ALUFlags::CARRY | ALUFlags::ZERO));
+}
+
+#[test]
+fn flags_round_trip_through_register() -> anyhow::Result<()> {
+    let program = libisa::instruction::assembler::assemble([
+        Instruction::new(InstructionKind::LoadI)
+            .with_reg_a(0)
+            .with_immediate(7),
+        Instruction::new(InstructionKind::CmpI)
+            .with_reg_a(0)
+            .with_immediate(7),
+        Instruction::new(InstructionKind::GetF).with_reg_a(1),
+        Instruction::new(InstructionKind::AddI)
+            .with_reg_a(0)
+            .with_immediate(1),
+        Instruction::new(InstructionKind::SetF).with_reg_a(1),
+        Instruction::new(InstructionKind::Halt),
+    ])?
+    .machine_code;
+
+    let mut emulator = Emulator::new(program)?;
+    emulator.execute_to_halt()?;
+
+    // The compare doesn't write its result, and the flags it set are restored after the addition clobbered them.
+    assert_eq!(emulator.reg_file.get(0).copied(), Some(8));
+    assert_eq!(emulator.reg_file.get(1).copied(), Some(ALUFlags::ZERO.bits()));
+    assert_eq!(emulator.alu.flags, ALUFlags::ZERO);
+
+    Ok(())
+}
@@ -0,0 +1,371 @@
+use std::{collections::BTreeMap, fmt::Display, ops::RangeInclusive, str::FromStr};
+
+use libisa::Word;
+use thiserror::Error;
+
+use crate::PhysAddr;
+
+#[cfg(test)]
+mod tests;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ReplacementPolicy {
+    /// Evict the least recently used line of the set.
+    #[default]
+    Lru,
+
+    /// Evict the line brought in the earliest, no matter how it has been used since.
+    Fifo,
+
+    /// Evict a pseudo-random line, always the same sequence of them for reproducible results.
+    Random,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CacheConfig {
+    pub size_bytes: usize,
+    pub line_bytes: usize,
+    pub associativity: usize,
+    pub policy: ReplacementPolicy,
+}
+
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+pub enum CacheConfigError {
+    #[error("{0} must be a non-zero power of two")]
+    NotPowerOfTwo(&'static str),
+
+    #[error(
+        "Cache of {size_bytes} bytes can't hold {associativity} ways of {line_bytes} byte lines"
+    )]
+    TooSmall {
+        size_bytes: usize,
+        line_bytes: usize,
+        associativity: usize,
+    },
+
+    #[error("Expected SIZE/LINE/WAYS[/POLICY], e.g. 1024/16/2/lru")]
+    Syntax,
+
+    #[error("Unknown replacement policy '{0}', expected lru, fifo or random")]
+    UnknownPolicy(String),
+}
+
+/// Named range of physical addresses whose accesses are reported separately, e.g. code, data or a stack.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CacheRegion {
+    pub name: String,
+    pub addrs: RangeInclusive<PhysAddr>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct AccessStats {
+    pub hits: u64,
+    pub misses: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct CacheStats {
+    pub total: AccessStats,
+    pub by_region: Vec<(CacheRegion, AccessStats)>,
+
+    /// Accesses by the address of the instruction that made them.
+    pub by_pc: BTreeMap<Word, AccessStats>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct CacheLine {
+    tag: usize,
+    inserted_at: u64,
+    used_at: u64,
+}
+
+/// Set-associative cache fed the addresses of memory accesses, counting hits and misses without holding any data.
+#[derive(Debug, Clone)]
+pub struct Cache {
+    config: CacheConfig,
+    sets: Vec<Vec<CacheLine>>,
+
+    /// Access counter, serving as the time for the replacement policies.
+    accesses: u64,
+    random_state: u32,
+
+    pub stats: CacheStats,
+}
+
+impl CacheConfig {
+    pub fn new(
+        size_bytes: usize,
+        line_bytes: usize,
+        associativity: usize,
+        policy: ReplacementPolicy,
+    ) -> Result<Self, CacheConfigError> {
+        for (name, value) in [
+            ("Cache size", size_bytes),
+            ("Line size", line_bytes),
+            ("Associativity", associativity),
+        ] {
+            if !value.is_power_of_two() {
+                return Err(CacheConfigError::NotPowerOfTwo(name));
+            }
+        }
+
+        if line_bytes * associativity > size_bytes {
+            return Err(CacheConfigError::TooSmall {
+                size_bytes,
+                line_bytes,
+                associativity,
+            });
+        }
+
+        Ok(Self {
+            size_bytes,
+            line_bytes,
+            associativity,
+            policy,
+        })
+    }
+
+    pub fn set_count(&self) -> usize {
+        self.size_bytes / (self.line_bytes * self.associativity)
+    }
+}
+
+impl Cache {
+    pub fn new(config: CacheConfig) -> Self {
+        Self {
+            config,
+            sets: vec![Vec::with_capacity(config.associativity); config.set_count()],
+            accesses: 0,
+            random_state: 0x2545_F491,
+            stats: CacheStats::default(),
+        }
+    }
+
+    pub fn with_region(mut self, region: CacheRegion) -> Self {
+        self.stats.by_region.push((region, AccessStats::default()));
+        self
+    }
+
+    pub fn config(&self) -> &CacheConfig {
+        &self.config
+    }
+
+    /// Access the physical address on behalf of the instruction at the PC, bringing its line in on a miss.
+    /// Returns whether the access hit.
+    pub fn access(&mut self, addr: PhysAddr, pc: Word) -> bool {
+        self.accesses += 1;
+
+        let line_index = addr / self.config.line_bytes;
+        let set_index = line_index % self.sets.len();
+        let tag = line_index / self.sets.len();
+
+        let now = self.accesses;
+        let set = &mut self.sets[set_index];
+
+        let hit = match set.iter_mut().find(|line| line.tag == tag) {
+            Some(line) => {
+                line.used_at = now;
+                true
+            }
+            None => {
+                let line = CacheLine {
+                    tag,
+                    inserted_at: now,
+                    used_at: now,
+                };
+
+                if set.len() < self.config.associativity {
+                    set.push(line);
+                } else {
+                    let victim = match self.config.policy {
+                        ReplacementPolicy::Lru => Self::oldest_by(set, |line| line.used_at),
+                        ReplacementPolicy::Fifo => Self::oldest_by(set, |line| line.inserted_at),
+                        ReplacementPolicy::Random => {
+                            Self::next_random(&mut self.random_state) as usize % set.len()
+                        }
+                    };
+
+                    set[victim] = line;
+                }
+
+                false
+            }
+        };
+
+        self.stats.record(addr, pc, hit);
+        hit
+    }
+
+    fn oldest_by(set: &[CacheLine], time: impl Fn(&CacheLine) -> u64) -> usize {
+        set.iter()
+            .enumerate()
+            .min_by_key(|(_, line)| time(line))
+            .map(|(index, _)| index)
+            .unwrap_or(0)
+    }
+
+    /// Xorshift, which is plenty for picking victims.
+    fn next_random(state: &mut u32) -> u32 {
+        *state ^= *state << 13;
+        *state ^= *state >> 17;
+        *state ^= *state << 5;
+        *state
+    }
+}
+
+impl CacheStats {
+    fn record(&mut self, addr: PhysAddr, pc: Word, hit: bool) {
+        self.total.record(hit);
+        self.by_pc.entry(pc).or_default().record(hit);
+
+        self.by_region
+            .iter_mut()
+            .filter(|(region, _)| region.addrs.contains(&addr))
+            .for_each(|(_, stats)| stats.record(hit));
+    }
+}
+
+impl AccessStats {
+    fn record(&mut self, hit: bool) {
+        match hit {
+            true => self.hits += 1,
+            false => self.misses += 1,
+        }
+    }
+
+    pub fn accesses(&self) -> u64 {
+        self.hits + self.misses
+    }
+
+    pub fn hit_rate(&self) -> f64 {
+        match self.accesses() {
+            0 => 0.0,
+            accesses => self.hits as f64 / accesses as f64,
+        }
+    }
+}
+
+impl FromStr for ReplacementPolicy {
+    type Err = CacheConfigError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "lru" => Ok(Self::Lru),
+            "fifo" => Ok(Self::Fifo),
+            "random" => Ok(Self::Random),
+            _ => Err(CacheConfigError::UnknownPolicy(s.to_owned())),
+        }
+    }
+}
+
+impl Display for ReplacementPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Lru => "lru",
+            Self::Fifo => "fifo",
+            Self::Random => "random",
+        })
+    }
+}
+
+impl FromStr for CacheConfig {
+    type Err = CacheConfigError;
+
+    /// Parse SIZE/LINE/WAYS[/POLICY], sizes in bytes and the policy defaulting to LRU.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.split('/');
+        let mut next_number = || {
+            parts
+                .next()
+                .and_then(|part| part.parse::<usize>().ok())
+                .ok_or(CacheConfigError::Syntax)
+        };
+
+        let size_bytes = next_number()?;
+        let line_bytes = next_number()?;
+        let associativity = next_number()?;
+
+        let policy = match parts.next() {
+            Some(policy) => policy.parse()?,
+            None => ReplacementPolicy::default(),
+        };
+
+        if parts.next().is_some() {
+            return Err(CacheConfigError::Syntax);
+        }
+
+        Self::new(size_bytes, line_bytes, associativity, policy)
+    }
+}
+
+impl Display for CacheConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}/{}/{}/{}",
+            self.size_bytes, self.line_bytes, self.associativity, self.policy
+        )
+    }
+}
+
+impl FromStr for CacheRegion {
+    type Err = String;
+
+    /// Parse NAME:START-END, the addresses being hexadecimal and the end inclusive.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let syntax_err = || {
+            format!(
+                "Expected NAME:START-END in hex, e.g. code:0000-03ff, got '{}'",
+                s
+            )
+        };
+
+        let (name, addrs) = s.split_once(':').ok_or_else(syntax_err)?;
+        let (start, end) = addrs.split_once('-').ok_or_else(syntax_err)?;
+
+        let parse_addr = |addr: &str| {
+            PhysAddr::from_str_radix(addr.trim_start_matches("0x"), 16).map_err(|_| syntax_err())
+        };
+
+        Ok(Self {
+            name: name.to_owned(),
+            addrs: parse_addr(start)?..=parse_addr(end)?,
+        })
+    }
+}
+
+impl Display for AccessStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} hits, {} misses ({:.1}% hit rate)",
+            self.hits,
+            self.misses,
+            self.hit_rate() * 100.0
+        )
+    }
+}
+
+impl Display for Cache {
+    /// Report of the accesses, in total, by region and by PC.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{}: {}", self.config, self.stats.total)?;
+
+        for (region, stats) in &self.stats.by_region {
+            writeln!(
+                f,
+                "  {} {:04x}-{:04x}: {}",
+                region.name,
+                region.addrs.start(),
+                region.addrs.end(),
+                stats
+            )?;
+        }
+
+        for (pc, stats) in &self.stats.by_pc {
+            writeln!(f, "  pc {:04x}: {}", pc, stats)?;
+        }
+
+        Ok(())
+    }
+}
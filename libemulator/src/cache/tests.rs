@@ -0,0 +1,160 @@
+use libisa::instruction::{kind::InstructionKind, Instruction};
+
+use crate::{Emulator, PhysAddr};
+
+use super::{AccessStats, Cache, CacheConfig, CacheConfigError, CacheRegion, ReplacementPolicy};
+
+fn hits(
+    policy: ReplacementPolicy,
+    size_bytes: usize,
+    associativity: usize,
+    addrs: &[PhysAddr],
+) -> Vec<bool> {
+    let mut cache = Cache::new(CacheConfig::new(size_bytes, 16, associativity, policy).unwrap());
+
+    addrs.iter().map(|addr| cache.access(*addr, 0)).collect()
+}
+
+#[test]
+fn config_gets_parsed_and_validated() {
+    assert_eq!(
+        "1024/16/2/fifo".parse(),
+        Ok(CacheConfig {
+            size_bytes: 1024,
+            line_bytes: 16,
+            associativity: 2,
+            policy: ReplacementPolicy::Fifo,
+        })
+    );
+
+    assert_eq!(
+        "256/16/4"
+            .parse::<CacheConfig>()
+            .map(|config| config.policy),
+        Ok(ReplacementPolicy::Lru)
+    );
+
+    assert_eq!(
+        "1000/16/2".parse::<CacheConfig>(),
+        Err(CacheConfigError::NotPowerOfTwo("Cache size"))
+    );
+    assert!(matches!(
+        "16/16/2".parse::<CacheConfig>(),
+        Err(CacheConfigError::TooSmall { .. })
+    ));
+    assert_eq!(
+        "1024/16".parse::<CacheConfig>(),
+        Err(CacheConfigError::Syntax)
+    );
+}
+
+#[test]
+fn associativity_avoids_conflict_misses() {
+    // Addresses 0 and 64 map to the same set of a 64 byte cache with 16 byte lines.
+    assert_eq!(
+        hits(ReplacementPolicy::Lru, 64, 1, &[0, 64, 0]),
+        [false, false, false]
+    );
+    assert_eq!(
+        hits(ReplacementPolicy::Lru, 64, 2, &[0, 64, 0]),
+        [false, false, true]
+    );
+
+    // Same line, different words.
+    assert_eq!(
+        hits(ReplacementPolicy::Lru, 64, 1, &[0, 2, 14, 16]),
+        [false, true, true, false]
+    );
+}
+
+#[test]
+fn replacement_policies_pick_different_victims() {
+    // A single set of two ways: the third line evicts the least recently used or the first inserted line.
+    let addrs = [0, 16, 0, 32, 0];
+
+    assert_eq!(
+        hits(ReplacementPolicy::Lru, 32, 2, &addrs),
+        [false, false, true, false, true]
+    );
+    assert_eq!(
+        hits(ReplacementPolicy::Fifo, 32, 2, &addrs),
+        [false, false, true, false, false]
+    );
+}
+
+#[test]
+fn emulator_feeds_fetches_and_data_accesses() -> anyhow::Result<()> {
+    let program = libisa::instruction::assembler::assemble([
+        Instruction::new(InstructionKind::LoadI)
+            .with_reg_a(0)
+            .with_immediate(0x100),
+        Instruction::new(InstructionKind::Load)
+            .with_reg_a(1)
+            .with_reg_b(0),
+        Instruction::new(InstructionKind::Store)
+            .with_reg_a(0)
+            .with_reg_b(1),
+        Instruction::new(InstructionKind::Halt),
+    ])?
+    .machine_code;
+
+    let config = CacheConfig::new(64, 16, 1, ReplacementPolicy::Lru)?;
+    let data_region = CacheRegion {
+        name: "data".to_owned(),
+        addrs: 0x100..=0x1FF,
+    };
+
+    let mut emulator = Emulator::new(program)?
+        .with_instruction_cache(Cache::new(config))
+        .with_data_cache(Cache::new(config).with_region(data_region));
+
+    emulator.execute_to_halt()?;
+
+    // All five instruction words are in the first line.
+    let instruction_cache = emulator.instruction_cache.unwrap();
+    assert_eq!(
+        instruction_cache.stats.total,
+        AccessStats { hits: 4, misses: 1 }
+    );
+
+    let data_cache = emulator.data_cache.unwrap();
+    let expected = AccessStats { hits: 1, misses: 1 };
+
+    assert_eq!(data_cache.stats.total, expected);
+    assert_eq!(data_cache.stats.by_region[0].1, expected);
+    assert_eq!(
+        data_cache.stats.by_pc[&4],
+        AccessStats { hits: 0, misses: 1 }
+    );
+    assert_eq!(
+        data_cache.stats.by_pc[&6],
+        AccessStats { hits: 1, misses: 0 }
+    );
+
+    Ok(())
+}
+
+#[test]
+fn faulting_accesses_arent_counted() -> anyhow::Result<()> {
+    let program = libisa::instruction::assembler::assemble([
+        Instruction::new(InstructionKind::LoadI)
+            .with_reg_a(0)
+            .with_immediate(0x1000),
+        Instruction::new(InstructionKind::Load)
+            .with_reg_a(1)
+            .with_reg_b(0),
+        Instruction::new(InstructionKind::Halt),
+    ])?
+    .machine_code;
+
+    let config = CacheConfig::new(64, 16, 1, ReplacementPolicy::Lru)?;
+
+    let mut emulator = Emulator::new(program)?
+        .with_memory_size(0x100)?
+        .with_data_cache(Cache::new(config));
+
+    assert!(emulator.execute_to_halt().is_err());
+    assert_eq!(emulator.data_cache.unwrap().stats.total.accesses(), 0);
+
+    Ok(())
+}
@@ -1,5 +1,6 @@
 use libisa::{
     instruction::{kind::InstructionKind, Instruction},
+    privilege::{ControlRegister, Mode, TrapCause},
     Word,
 };
 
@@ -10,6 +11,10 @@ impl Emulator {
         &mut self,
         instruction: Instruction,
     ) -> Result<ExecuteOk, ExecuteErr> {
+        if self.mode == Mode::User && instruction.kind.is_privileged() {
+            return Err(ExecuteErr::PrivilegedInstruction(instruction.kind));
+        }
+
         match instruction.kind {
             InstructionKind::Nop => {}
 
@@ -20,20 +25,22 @@ impl Emulator {
                 *dest = value;
             }
 
-            InstructionKind::Load => {
-                let src_addr = *self.reg_b(&instruction);
+            InstructionKind::Load | InstructionKind::LoadO => {
+                let src_addr = self.mem_addr(*self.reg_b(&instruction), &instruction);
                 let src_value = *self.mem_word_or_err(src_addr)?;
+                self.access_data_cache(src_addr, &instruction);
 
                 let mut dest = self.reg_a_mut(&instruction);
                 *dest = src_value;
             }
 
-            InstructionKind::Store => {
-                let dest_addr = *self.reg_a(&instruction);
+            InstructionKind::Store | InstructionKind::StoreO => {
+                let dest_addr = self.mem_addr(*self.reg_a(&instruction), &instruction);
                 let src_value = *self.reg_b(&instruction);
 
-                let mut dest_value = self.mem_word_mut_or_err(dest_addr)?;
-                *dest_value = src_value;
+                *self.mem_word_mut_or_err(dest_addr)? = src_value;
+                self.access_data_cache(dest_addr, &instruction);
+                return self.mmio_store(dest_addr, libisa::BYTES_PER_WORD as Word);
             }
 
             InstructionKind::Cpy => {
@@ -42,20 +49,13 @@ impl Emulator {
                 *dest = src;
             }
 
-            InstructionKind::Jmp => {
-                let addr = *self.reg_a(&instruction);
-                self.pc = addr;
-            }
-
-            InstructionKind::JmpC => {
-                if self.alu.flags.contains(ALUFlags::CARRY) {
-                    let addr = *self.reg_a(&instruction);
-                    self.pc = addr;
-                }
-            }
-
-            InstructionKind::JmpZ => {
-                if self.alu.flags.contains(ALUFlags::ZERO) {
+            InstructionKind::Jmp
+            | InstructionKind::JmpC
+            | InstructionKind::JmpZ
+            | InstructionKind::JmpN
+            | InstructionKind::JmpO
+            | InstructionKind::JmpL => {
+                if self.jump_taken(instruction.kind) {
                     let addr = *self.reg_a(&instruction);
                     self.pc = addr;
                 }
@@ -101,41 +101,127 @@ impl Emulator {
                 *self.reg_a_mut(&instruction) = result;
             }
 
-            InstructionKind::LoadH => {
-                let src_addr = *self.reg_b(&instruction);
+            InstructionKind::LoadH | InstructionKind::LoadHO => {
+                let src_addr = self.mem_addr(*self.reg_b(&instruction), &instruction);
                 let src_value = *self.mem_byte_or_err(src_addr)?;
+                self.access_data_cache(src_addr, &instruction);
 
                 let mut dest = self.reg_a_mut(&instruction);
                 *dest = ((src_value as u16) << 8) | (*dest & 0x00FF);
             }
 
-            InstructionKind::LoadL => {
-                let src_addr = *self.reg_b(&instruction);
+            InstructionKind::LoadL | InstructionKind::LoadLO => {
+                let src_addr = self.mem_addr(*self.reg_b(&instruction), &instruction);
                 let src_value = *self.mem_byte_or_err(src_addr)?;
+                self.access_data_cache(src_addr, &instruction);
 
                 let mut dest = self.reg_a_mut(&instruction);
                 *dest = (*dest & 0xFF00) | (src_value as u16)
             }
 
-            InstructionKind::StoreH => {
+            InstructionKind::StoreH | InstructionKind::StoreHO => {
                 let src_value = *self.reg_b(&instruction);
 
-                let dest_addr = *self.reg_a(&instruction);
-                let mut dest_value = self.mem_byte_mut_or_err(dest_addr)?;
-
-                *dest_value = ((src_value & 0xFF00) >> 8) as u8;
+                let dest_addr = self.mem_addr(*self.reg_a(&instruction), &instruction);
+                *self.mem_byte_mut_or_err(dest_addr)? = ((src_value & 0xFF00) >> 8) as u8;
+                self.access_data_cache(dest_addr, &instruction);
+                return self.mmio_store(dest_addr, 1);
             }
 
-            InstructionKind::StoreL => {
+            InstructionKind::StoreL | InstructionKind::StoreLO => {
                 let src_value = *self.reg_b(&instruction);
 
-                let dest_addr = *self.reg_a(&instruction);
-                let mut dest_value = self.mem_byte_mut_or_err(dest_addr)?;
-
-                *dest_value = (src_value & 0x00FF) as u8;
+                let dest_addr = self.mem_addr(*self.reg_a(&instruction), &instruction);
+                *self.mem_byte_mut_or_err(dest_addr)? = (src_value & 0x00FF) as u8;
+                self.access_data_cache(dest_addr, &instruction);
+                return self.mmio_store(dest_addr, 1);
             }
 
             InstructionKind::Halt => return Ok(ExecuteOk::Halted),
+
+            InstructionKind::AddI => {
+                let b = instruction.immediate.unwrap();
+                let a = *self.reg_a(&instruction);
+
+                let result = self.alu.add(a, b);
+                *self.reg_a_mut(&instruction) = result;
+            }
+
+            InstructionKind::SubI => {
+                let b = instruction.immediate.unwrap();
+                let a = *self.reg_a(&instruction);
+
+                let result = self.alu.sub(a, b);
+                *self.reg_a_mut(&instruction) = result;
+            }
+
+            InstructionKind::AndI => {
+                let b = instruction.immediate.unwrap();
+                let a = *self.reg_a(&instruction);
+
+                let result = self.alu.and(a, b);
+                *self.reg_a_mut(&instruction) = result;
+            }
+
+            InstructionKind::JmpI
+            | InstructionKind::JmpR
+            | InstructionKind::JmpCI
+            | InstructionKind::JmpCR
+            | InstructionKind::JmpZI
+            | InstructionKind::JmpZR
+            | InstructionKind::JmpNI
+            | InstructionKind::JmpNR
+            | InstructionKind::JmpOI
+            | InstructionKind::JmpOR
+            | InstructionKind::JmpLI
+            | InstructionKind::JmpLR => {
+                if self.jump_taken(instruction.kind) {
+                    self.pc = self.static_jump_target(&instruction);
+                }
+            }
+
+            InstructionKind::GetF => {
+                let flags = self.alu.flags.bits();
+                *self.reg_a_mut(&instruction) = flags;
+            }
+
+            InstructionKind::SetF => {
+                let flags = *self.reg_a(&instruction);
+                self.alu.flags = ALUFlags::from_bits_truncate(flags);
+            }
+
+            InstructionKind::Cmp => {
+                let b = *self.reg_b(&instruction);
+                let a = *self.reg_a(&instruction);
+
+                self.alu.compare(a, b);
+            }
+
+            InstructionKind::CmpI => {
+                let b = instruction.immediate.unwrap();
+                let a = *self.reg_a(&instruction);
+
+                self.alu.compare(a, b);
+            }
+
+            // The PC already points to the instruction after it, which the system call returns to.
+            InstructionKind::Sys => return self.trap(TrapCause::SystemCall, self.pc),
+            InstructionKind::Sret => return self.trap_return(),
+
+            InstructionKind::GetCr => {
+                let register = self.control_register_operand(&instruction)?;
+                let value = self.control_register(register);
+
+                let mut dest = self.reg_a_mut(&instruction);
+                *dest = value;
+            }
+
+            InstructionKind::SetCr => {
+                let register = self.control_register_operand(&instruction)?;
+                let value = *self.reg_a(&instruction);
+
+                self.set_control_register(register, value);
+            }
         }
 
         Ok(ExecuteOk::Normal)
@@ -152,4 +238,65 @@ impl Emulator {
     fn reg_b(&self, instruction: &Instruction) -> &Word {
         self.reg_word(instruction.reg_b.unwrap())
     }
+
+    /// Memory address of a load or store, offset by the immediate for the base+offset forms.
+    fn mem_addr(&self, base: Word, instruction: &Instruction) -> Word {
+        base.wrapping_add(instruction.immediate.unwrap_or(0))
+    }
+
+    /// Target of an immediate or relative jump.
+    fn static_jump_target(&self, instruction: &Instruction) -> Word {
+        instruction.static_jump_target(self.instruction_pc(instruction)).unwrap()
+    }
+
+    /// Whether the condition of a jump holds, always the case for unconditional jumps. Both executing jumps and
+    /// counting their cycles go by this.
+    pub(super) fn jump_taken(&self, kind: InstructionKind) -> bool {
+        let flags = self.alu.flags;
+
+        match kind {
+            InstructionKind::JmpC | InstructionKind::JmpCI | InstructionKind::JmpCR => {
+                flags.contains(ALUFlags::CARRY)
+            }
+            InstructionKind::JmpZ | InstructionKind::JmpZI | InstructionKind::JmpZR => {
+                flags.contains(ALUFlags::ZERO)
+            }
+            InstructionKind::JmpN | InstructionKind::JmpNI | InstructionKind::JmpNR => {
+                flags.contains(ALUFlags::NEGATIVE)
+            }
+            InstructionKind::JmpO | InstructionKind::JmpOI | InstructionKind::JmpOR => {
+                flags.contains(ALUFlags::OVERFLOW)
+            }
+            InstructionKind::JmpL | InstructionKind::JmpLI | InstructionKind::JmpLR => {
+                flags.signed_less()
+            }
+            _ => true,
+        }
+    }
+
+    /// Address of the executing instruction, as long as it hasn't jumped, the PC already pointing to the next one.
+    fn instruction_pc(&self, instruction: &Instruction) -> Word {
+        self.pc.wrapping_sub(instruction.kind.len_bytes() as Word)
+    }
+
+    fn control_register_operand(&self, instruction: &Instruction) -> Result<ControlRegister, ExecuteErr> {
+        let number = instruction.immediate.unwrap();
+        ControlRegister::from_number(number).ok_or(ExecuteErr::UnknownControlRegister(number))
+    }
+
+    /// Let the memory-mapped devices react to the store of `len` bytes to `addr`.
+    fn mmio_store(&mut self, addr: Word, len: Word) -> Result<ExecuteOk, ExecuteErr> {
+        self.mmu_store(addr, len)?;
+        self.semihosting_store(addr, len)
+    }
+
+    /// Count a data access that went through at its physical address, before a store to the MMU remaps it.
+    fn access_data_cache(&mut self, addr: Word, instruction: &Instruction) {
+        let pc = self.instruction_pc(instruction);
+        let addr = self.physical_addr(addr);
+
+        if let Some(cache) = &mut self.data_cache {
+            cache.access(addr, pc);
+        }
+    }
 }
@@ -1,28 +1,69 @@
-#![feature(array_chunks, array_windows, trait_alias)]
+#![feature(array_windows, trait_alias)]
 
 mod alu;
+pub mod cache;
 mod execute;
+pub mod mmu;
+pub mod pipeline;
+mod privilege;
+pub mod semihosting;
 mod volatile;
 mod tracing;
 mod volatilehelper;
 
+#[cfg(test)]
+mod tests;
+
 use alu::ALU;
 use anyhow::Context;
+use cache::Cache;
 use libisa::{
-    instruction::{Instruction, InstructionDeassemblyError},
+    executable::Executable,
+    instruction::{kind::InstructionKind, Instruction, InstructionDeassemblyError},
+    privilege::{Mode, CONTROL_REGISTER_COUNT},
+    timing::CycleProfile,
     Word,
 };
 use thiserror::Error;
+use mmu::Mmu;
+use semihosting::Semihosting;
 use tracing::{EmulatorIterationTrace, EmulatorTracing};
 use volatile::Volatile;
 
+/// Address in physical memory, which is larger than the logical address space with the MMU.
+pub type PhysAddr = usize;
+
 pub struct Emulator {
-    pub memory: Volatile<u8, Word>,
+    /// Memory by physical address, which logical addresses map to one to one unless the MMU maps them elsewhere.
+    pub memory: Volatile<u8, PhysAddr>,
+
+    /// Separate memory instructions are fetched from in Harvard mode, loads and stores only ever accessing `memory`.
+    /// `None` if code and data share `memory`.
+    pub instruction_memory: Option<Volatile<u8, PhysAddr>>,
+
     pub reg_file: Volatile<Word, usize>,
     pub tracing: EmulatorTracing,
 
     pub alu: ALU,
     pub pc: Word,
+
+    /// Privilege level, and the control registers configuring traps and the region user mode can't access.
+    pub mode: Mode,
+    pub control_registers: [Word; CONTROL_REGISTER_COUNT],
+
+    /// Cycles the executed instructions would have taken on the microarchitecture of the cycle profile.
+    pub cycles: u64,
+    pub cycle_profile: CycleProfile,
+
+    /// Caches fed every instruction fetch and data access respectively, if simulated.
+    pub instruction_cache: Option<Cache>,
+    pub data_cache: Option<Cache>,
+
+    /// Host I/O through the semihosting doorbell, if enabled.
+    pub semihosting: Option<Semihosting>,
+
+    /// Bank switching of data memory accesses, and of instruction fetches unless in Harvard mode, if enabled.
+    pub mmu: Option<Mmu>,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -38,23 +79,122 @@ pub enum ExecuteErr {
 
     #[error("Illegal instruction ({0})")]
     IllegalInstruction(InstructionDeassemblyError),
+
+    #[error("Unknown semihosting call {0}")]
+    UnknownSemihostingCall(Word),
+
+    #[error("Semihosting I/O failed ({0})")]
+    SemihostingIo(std::io::ErrorKind),
+
+    /// Faults that trap to supervisor mode when they happen while executing an instruction.
+    #[error("Privileged instruction {0} in user mode")]
+    PrivilegedInstruction(InstructionKind),
+
+    #[error("Protection fault at 0x{0:04x}")]
+    ProtectionFault(Word),
+
+    #[error("Unknown control register {0}")]
+    UnknownControlRegister(Word),
 }
 
 impl Emulator {
+    /// Emulator running the program, either a STRM1 executable loaded by its segments and started from its entry
+    /// point, or a raw binary loaded at and started from address 0. Memory images have to be imported beforehand,
+    /// see [`libisa::memimage::import`].
     pub fn new(program: Vec<u8>) -> anyhow::Result<Self> {
+        match Executable::is_executable(&program) {
+            true => {
+                let executable = Executable::parse(&program).context("Parsing executable")?;
+                Self::from_image(executable.image(), executable.entry)
+            }
+            false => Self::from_image(program, 0),
+        }
+    }
+
+    /// Emulator with separate instruction and data memories, the program being fetched from the former and the
+    /// data image being the initial contents of the latter. The executable segments of an executable program are
+    /// loaded to instruction memory, and the rest of them over the data image.
+    pub fn new_harvard(program: Vec<u8>, mut data: Vec<u8>) -> anyhow::Result<Self> {
+        let (code, entry) = match Executable::is_executable(&program) {
+            true => {
+                let executable = Executable::parse(&program).context("Parsing executable")?;
+                let mut code = vec![];
+
+                executable.load_into(&mut code, |segment| segment.permissions.execute);
+                executable.load_into(&mut data, |segment| !segment.permissions.execute);
+
+                (code, executable.entry)
+            }
+            false => (program, 0),
+        };
+
+        let mut emulator = Self::from_image(data, entry)?;
+
+        emulator.instruction_memory = Some(
+            Volatile::new_with_data(code, libisa::ADDRESS_SPACE_SIZE)
+                .with_context(|| "Loading program to instruction memory")?,
+        );
+
+        Ok(emulator)
+    }
+
+    /// Emulator with the image as the initial contents of physical memory, which is grown to fit images larger than
+    /// the logical address space.
+    fn from_image(image: Vec<u8>, entry: Word) -> anyhow::Result<Self> {
+        let memory_size = image.len().max(libisa::ADDRESS_SPACE_SIZE);
+
         Ok(Self {
-            memory: Volatile::new_with_data(program, Word::MAX)
+            memory: Volatile::new_with_data(image, memory_size)
                 .with_context(|| "Loading program to memory")?,
+            instruction_memory: None,
 
             reg_file: Volatile::new(libisa::REGISTER_COUNT),
 
             tracing: EmulatorTracing::default(),
 
             alu: ALU::new(),
-            pc: 0,
+            pc: entry,
+
+            mode: Mode::Supervisor,
+            control_registers: [0; CONTROL_REGISTER_COUNT],
+
+            cycles: 0,
+            cycle_profile: CycleProfile::default(),
+
+            instruction_cache: None,
+            data_cache: None,
+
+            semihosting: None,
+            mmu: None,
         })
     }
 
+    /// Memory instructions are fetched from, which is the data memory unless in Harvard mode.
+    pub fn code_memory(&self) -> &Volatile<u8, PhysAddr> {
+        self.instruction_memory.as_ref().unwrap_or(&self.memory)
+    }
+
+    /// Resize the physical data memory, addresses past it being access violations.
+    pub fn with_memory_size(mut self, size: PhysAddr) -> anyhow::Result<Self> {
+        self.memory.resize(size).context("Program doesn't fit in memory")?;
+        Ok(self)
+    }
+
+    pub fn with_instruction_cache(mut self, cache: Cache) -> Self {
+        self.instruction_cache = Some(cache);
+        self
+    }
+
+    pub fn with_data_cache(mut self, cache: Cache) -> Self {
+        self.data_cache = Some(cache);
+        self
+    }
+
+    pub fn with_cycle_profile(mut self, cycle_profile: CycleProfile) -> Self {
+        self.cycle_profile = cycle_profile;
+        self
+    }
+
     pub fn execute_to_halt(&mut self) -> Result<(), ExecuteErr> {
         loop {
             let exec_ok = self.execute_instruction()?;
@@ -67,8 +207,17 @@ impl Emulator {
 
     pub fn execute_instruction(&mut self) -> Result<ExecuteOk, ExecuteErr> {
         let instruction_pc = self.pc;
-        let instruction = self.parse_next_instruction()?;
-        let exec_result = self.execute_parsed_instruction(instruction);
+        let instruction = match self.parse_next_instruction() {
+            Ok(instruction) => instruction,
+            Err(e) => return self.trap_fault(e, instruction_pc),
+        };
+        let exec_result = self
+            .execute_parsed_instruction(instruction)
+            .or_else(|e| self.trap_fault(e, instruction_pc));
+
+        if exec_result.is_ok() {
+            self.count_cycles(instruction.kind);
+        }
 
         let memory_patches = self.memory.pop_patches().collect();
         let register_patches = self.reg_file.pop_patches().collect();
@@ -81,7 +230,24 @@ impl Emulator {
         exec_result
     }
 
+    fn count_cycles(&mut self, kind: InstructionKind) {
+        let profile = &self.cycle_profile;
+        let mut cycles = profile.instruction_cycles(kind);
+
+        if kind.accesses_memory() {
+            cycles += profile.memory_access_cycles;
+        }
+
+        // Jumps don't change the flags, so their condition still holds as it did when they executed.
+        if kind.is_jump() && self.jump_taken(kind) {
+            cycles += profile.taken_branch_cycles;
+        }
+
+        self.cycles += cycles as u64;
+    }
+
     fn parse_next_instruction(&mut self) -> Result<Instruction, ExecuteErr> {
+        let instruction_pc = self.pc;
         let instruction_word = self.pc_next()?;
 
         let mut instruction = Instruction::deassemble_instruction_word(instruction_word)
@@ -92,11 +258,20 @@ impl Emulator {
             instruction.immediate = Some(immediate_word);
         }
 
+        // Every word of the instruction is fetched separately.
+        for offset in (0..instruction.kind.len_bytes()).step_by(libisa::BYTES_PER_WORD) {
+            let addr = self.physical_fetch_addr(instruction_pc.wrapping_add(offset as Word));
+
+            if let Some(cache) = &mut self.instruction_cache {
+                cache.access(addr, instruction_pc);
+            }
+        }
+
         Ok(instruction)
     }
 
     fn pc_next(&mut self) -> Result<Word, ExecuteErr> {
-        let pc_word = self.mem_word_or_err(self.pc)?;
+        let pc_word = self.fetch_word_or_err(self.pc)?;
 
         self.pc = self
             .pc
@@ -0,0 +1,124 @@
+use libisa::{mmu::BankMap, mmu::WINDOW_COUNT, Word};
+
+use crate::{semihosting, Emulator, ExecuteErr, PhysAddr};
+
+#[cfg(test)]
+mod tests;
+
+/// Name of the platform MMIO range the bank registers are at.
+pub const MMIO_NAME: &str = "mmu";
+
+/// Base address of the bank registers when the platform doesn't give one, below the semihosting doorbell.
+pub const DEFAULT_BASE: Word = 0xffd0;
+
+/// Bytes taken up by the bank registers, one word per window with the register of window `n` at `base + 2 * n`.
+pub const REGISTERS_SIZE: Word = (WINDOW_COUNT * libisa::BYTES_PER_WORD) as Word;
+
+/// Physical memory given to the emulator when the MMU is enabled without a memory size, 256 banks.
+pub const DEFAULT_PHYSICAL_SIZE: PhysAddr = 1 << 20;
+
+/// Memory management unit mapping the windows of the logical address space to banks of physical memory, controlled
+/// by storing bank numbers to its registers. The registers of the MMU and the other devices are never mapped, so a
+/// program can't lose access to them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mmu {
+    pub base: Word,
+    pub bank_map: BankMap,
+}
+
+impl Mmu {
+    /// MMU with the identity mapping, as after reset.
+    pub fn new(base: Word) -> Self {
+        Self {
+            base,
+            bank_map: BankMap::default(),
+        }
+    }
+
+    fn is_register(&self, addr: Word) -> bool {
+        (addr as u32).wrapping_sub(self.base as u32) < REGISTERS_SIZE as u32
+    }
+}
+
+impl Emulator {
+    /// Enable the MMU, storing its bank map to its registers so the program reads back the mapping in effect.
+    pub fn with_mmu(mut self, mmu: Mmu) -> anyhow::Result<Self> {
+        for (window, bank) in mmu.bank_map.banks.iter().enumerate() {
+            let addr = mmu.base as PhysAddr + window * libisa::BYTES_PER_WORD;
+
+            *self.memory.get_mut_multi::<Word>(addr).ok_or_else(|| {
+                anyhow::anyhow!("MMU registers at 0x{:04x} are outside of memory", mmu.base)
+            })? = *bank;
+        }
+
+        // Setting up the registers isn't something the program did.
+        self.memory.pop_patches().for_each(drop);
+
+        self.mmu = Some(mmu);
+        Ok(self)
+    }
+
+    /// Physical address a load, store or fetch of the logical address accesses.
+    pub fn physical_addr(&self, addr: Word) -> PhysAddr {
+        match &self.mmu {
+            Some(mmu) if !self.is_device_register(addr) => mmu.bank_map.translate(addr),
+            _ => addr as PhysAddr,
+        }
+    }
+
+    /// Physical address of the next instruction, in instruction memory in Harvard mode.
+    pub fn physical_pc(&self) -> PhysAddr {
+        self.physical_fetch_addr(self.pc)
+    }
+
+    /// Physical address an instruction word is fetched from, which is untranslated in Harvard mode.
+    pub fn physical_fetch_addr(&self, addr: Word) -> PhysAddr {
+        match self.instruction_memory {
+            Some(_) => addr as PhysAddr,
+            None => self.physical_addr(addr),
+        }
+    }
+
+    /// Whether the address is one of the registers of the MMU or the semihosting doorbell, which only supervisor mode
+    /// may access.
+    pub(super) fn is_device_register(&self, addr: Word) -> bool {
+        let is_semihosting_register = self.semihosting.as_ref().is_some_and(|semihosting| {
+            (addr as u32).wrapping_sub(semihosting.base as u32) < semihosting::REGISTERS_SIZE as u32
+        });
+
+        self.mmu.as_ref().is_some_and(|mmu| mmu.is_register(addr)) || is_semihosting_register
+    }
+
+    /// Reload the bank map if the store of `len` bytes to `addr` hit the bank registers.
+    pub(super) fn mmu_store(&mut self, addr: Word, len: Word) -> Result<(), ExecuteErr> {
+        let Some(base) = self.mmu.as_ref().map(|mmu| mmu.base) else {
+            return Ok(());
+        };
+
+        let store = addr as u32..addr as u32 + len as u32;
+        let registers = base as u32..base as u32 + REGISTERS_SIZE as u32;
+
+        if !(store.start < registers.end && registers.start < store.end) {
+            return Ok(());
+        }
+
+        let banks = (0..WINDOW_COUNT)
+            .map(|window| {
+                let addr = base as PhysAddr + window * libisa::BYTES_PER_WORD;
+
+                self.memory
+                    .get_multi::<Word>(addr)
+                    .map(|bank| *bank)
+                    .ok_or(ExecuteErr::MemoryAccessViolation(base))
+            })
+            .collect::<Result<Vec<Word>, _>>()?;
+
+        self.mmu
+            .as_mut()
+            .unwrap()
+            .bank_map
+            .banks
+            .copy_from_slice(&banks);
+        Ok(())
+    }
+}
@@ -0,0 +1,178 @@
+use libisa::{
+    instruction::{kind::InstructionKind, Instruction},
+    Word,
+};
+
+use crate::{
+    cache::{AccessStats, Cache, CacheConfig, CacheRegion, ReplacementPolicy},
+    mmu::{Mmu, DEFAULT_BASE, DEFAULT_PHYSICAL_SIZE},
+    Emulator, ExecuteErr,
+};
+
+/// Maps the window to the bank, using register 0.
+fn map(window: Word, bank: Word) -> [Instruction; 3] {
+    [
+        Instruction::new(InstructionKind::LoadI)
+            .with_reg_a(0)
+            .with_immediate(DEFAULT_BASE),
+        Instruction::new(InstructionKind::LoadI)
+            .with_reg_a(1)
+            .with_immediate(bank),
+        Instruction::new(InstructionKind::StoreO)
+            .with_reg_a(0)
+            .with_reg_b(1)
+            .with_immediate(window * libisa::BYTES_PER_WORD as Word),
+    ]
+}
+
+/// Stores the value to the address, using register 2.
+fn store(addr: Word, value: Word) -> [Instruction; 2] {
+    [
+        Instruction::new(InstructionKind::LoadI)
+            .with_reg_a(2)
+            .with_immediate(value),
+        Instruction::new(InstructionKind::StoreO)
+            .with_reg_a(2)
+            .with_reg_b(2)
+            .with_immediate(addr.wrapping_sub(value)),
+    ]
+}
+
+fn emulator(instructions: Vec<Instruction>) -> anyhow::Result<Emulator> {
+    let program = libisa::instruction::assembler::assemble(instructions)?.machine_code;
+
+    Emulator::new(program)?
+        .with_memory_size(DEFAULT_PHYSICAL_SIZE)?
+        .with_mmu(Mmu::new(DEFAULT_BASE))
+}
+
+#[test]
+fn bank_registers_map_windows_to_physical_memory() -> anyhow::Result<()> {
+    let mut emulator = emulator(
+        [
+            map(1, 0x20).to_vec(),
+            store(0x1000, 0xbeef).to_vec(),
+            map(1, 1).to_vec(),
+            vec![
+                Instruction::new(InstructionKind::LoadI)
+                    .with_reg_a(3)
+                    .with_immediate(0x1000),
+                Instruction::new(InstructionKind::Load)
+                    .with_reg_a(3)
+                    .with_reg_b(3),
+                Instruction::new(InstructionKind::Halt),
+            ],
+        ]
+        .concat(),
+    )?;
+
+    assert_eq!(emulator.physical_addr(0x1234), 0x1234);
+    assert_eq!(
+        emulator
+            .memory
+            .get_multi::<Word>(DEFAULT_BASE as usize + 2)
+            .as_deref(),
+        Some(&1),
+        "Registers start out with the identity mapping"
+    );
+
+    emulator.execute_to_halt()?;
+
+    assert_eq!(
+        emulator.memory.get_multi::<Word>(0x20000).as_deref(),
+        Some(&0xbeef)
+    );
+    assert_eq!(
+        emulator.reg_file.get(3),
+        Some(&0),
+        "Window mapped back to bank 1"
+    );
+    assert_eq!(emulator.physical_addr(0x1234), 0x1234);
+
+    // The store traced at the physical address it went to.
+    let store_pc = 3 * 4 + 4;
+    assert_eq!(
+        emulator.tracing.memory_word_by_pc(store_pc + 4, 0x20000),
+        Some(0xbeef)
+    );
+
+    Ok(())
+}
+
+#[test]
+fn words_split_across_unrelated_banks_are_violations() -> anyhow::Result<()> {
+    let mut contiguous = emulator(
+        [
+            map(1, 0x21).to_vec(),
+            map(2, 0x22).to_vec(),
+            store(0x1fff, 0x1234).to_vec(),
+            vec![Instruction::new(InstructionKind::Halt)],
+        ]
+        .concat(),
+    )?;
+
+    contiguous.execute_to_halt()?;
+    assert_eq!(
+        contiguous.memory.get_multi::<Word>(0x21fff).as_deref(),
+        Some(&0x1234)
+    );
+
+    let mut split = emulator(
+        [
+            map(1, 0x21).to_vec(),
+            store(0x1fff, 0x1234).to_vec(),
+            vec![Instruction::new(InstructionKind::Halt)],
+        ]
+        .concat(),
+    )?;
+
+    assert_eq!(
+        split.execute_to_halt(),
+        Err(ExecuteErr::MemoryAccessViolation(0x1fff))
+    );
+
+    Ok(())
+}
+
+#[test]
+fn data_cache_tells_banks_apart() -> anyhow::Result<()> {
+    let load = [
+        Instruction::new(InstructionKind::LoadI)
+            .with_reg_a(3)
+            .with_immediate(0x1000),
+        Instruction::new(InstructionKind::Load)
+            .with_reg_a(3)
+            .with_reg_b(3),
+    ];
+
+    let region = |name: &str, start| CacheRegion {
+        name: name.to_owned(),
+        addrs: start..=start + 0xfff,
+    };
+    let cache = Cache::new(CacheConfig::new(1024, 16, 4, ReplacementPolicy::Lru)?)
+        .with_region(region("bank 1", 0x1000))
+        .with_region(region("bank 0x20", 0x20000));
+
+    let mut emulator = emulator(
+        [
+            map(1, 0x20).to_vec(),
+            store(0x1000, 0xbeef).to_vec(),
+            map(1, 1).to_vec(),
+            load.to_vec(),
+            map(1, 0x20).to_vec(),
+            load.to_vec(),
+            vec![Instruction::new(InstructionKind::Halt)],
+        ]
+        .concat(),
+    )?
+    .with_data_cache(cache);
+
+    emulator.execute_to_halt()?;
+
+    // The same logical address misses once in each bank, and hits once back in the first one.
+    let stats = emulator.data_cache.unwrap().stats;
+    assert_eq!(stats.by_region[0].1, AccessStats { hits: 0, misses: 1 });
+    assert_eq!(stats.by_region[1].1, AccessStats { hits: 1, misses: 1 });
+
+    Ok(())
+}
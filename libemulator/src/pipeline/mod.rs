@@ -0,0 +1,268 @@
+use std::fmt::Display;
+
+use libisa::{
+    instruction::{kind::InstructionKind, Instruction},
+    Register, Word,
+};
+
+use crate::{Emulator, ExecuteErr, ExecuteOk};
+
+#[cfg(test)]
+mod tests;
+
+pub const STAGE_COUNT: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stage {
+    Fetch,
+    Decode,
+    Execute,
+    Writeback,
+}
+
+/// An instruction in flight. The instruction is `None` if its words don't decode, which only faults once it gets
+/// executed, as it may just as well be fetched from a path that gets flushed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StageSlot {
+    pub pc: Word,
+    pub instruction: Option<Instruction>,
+}
+
+/// Cycle-stepped model of the pipelined core, with fetch, decode, execute and writeback stages, driving the
+/// functional emulator by executing every instruction as it enters the execute stage.
+///
+/// There is no forwarding, so an instruction reading a register written by the instruction ahead of it stalls in
+/// decode for a cycle. Jumps to immediate targets are redirected in decode, flushing the instruction fetched behind
+/// them, while the other jumps are predicted not taken and resolved in execute, flushing both decode and fetch when
+/// taken. Instructions are fetched from memory as it is at the time, so stores to code in flight aren't observed.
+#[derive(Debug, Clone, Default)]
+pub struct Pipeline {
+    stages: [Option<StageSlot>; STAGE_COUNT],
+    fetch_pc: Word,
+
+    /// Whether the emulator has halted, be it by a halt or by a semihosting exit, after which nothing gets fetched
+    /// anymore.
+    draining: bool,
+    halted: bool,
+
+    pub stats: PipelineStats,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct PipelineStats {
+    pub cycles: u64,
+    pub retired: u64,
+    pub stall_cycles: u64,
+    pub flushed: u64,
+}
+
+/// Occupancy of the stages during a cycle by the PCs of the instructions in them, along with what happened in it.
+/// Flushes take place at the end of the cycle, so the flushed instructions still show up in the stages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PipelineCycle {
+    pub cycle: u64,
+    pub stages: [Option<Word>; STAGE_COUNT],
+    pub stalled: bool,
+    pub flushed: usize,
+    pub halted: bool,
+}
+
+impl Pipeline {
+    pub fn new(entry: Word) -> Self {
+        Self {
+            fetch_pc: entry,
+            ..Default::default()
+        }
+    }
+
+    pub fn stage(&self, stage: Stage) -> Option<&StageSlot> {
+        self.stages[stage as usize].as_ref()
+    }
+
+    /// Whether the instruction that halted the emulator has made it to writeback, with nothing left behind it.
+    pub fn halted(&self) -> bool {
+        self.halted
+    }
+
+    pub fn step(&mut self, emulator: &mut Emulator) -> Result<PipelineCycle, ExecuteErr> {
+        let [fetch, decode, execute, _] = self.stages;
+
+        // Registers are read as an instruction enters execute, and written at the end of writeback.
+        let stalled = match (decode, execute) {
+            (Some(decode), Some(execute)) => Self::reads_written(&decode, &execute),
+            _ => false,
+        };
+
+        self.stages = if stalled {
+            [fetch, decode, None, execute]
+        } else {
+            let fetched = (!self.draining).then(|| self.fetch(emulator));
+            [fetched, fetch, decode, execute]
+        };
+
+        let mut flush_from = None;
+
+        if let Some(executing) = self.stages[Stage::Execute as usize] {
+            if executing.pc != emulator.pc {
+                // The emulator was moved elsewhere from the outside, so start over from there.
+                flush_from = Some(Stage::Execute);
+            } else if emulator.execute_instruction()? == ExecuteOk::Halted {
+                self.draining = true;
+                flush_from = Some(Stage::Decode);
+            } else if emulator.pc != self.next_pc() {
+                flush_from = Some(Stage::Decode);
+            }
+        }
+
+        // Jumps to immediate targets don't need to wait for execute, unless they are flushed anyway.
+        if let (None, false, Some(decoding)) =
+            (flush_from, stalled, self.stages[Stage::Decode as usize])
+        {
+            if let Some(target) = Self::decoded_jump_target(&decoding) {
+                if self.stages[Stage::Fetch as usize].map(|slot| slot.pc) != Some(target) {
+                    self.fetch_pc = target;
+                    flush_from = Some(Stage::Fetch);
+                }
+            }
+        }
+
+        let writeback = self.stages[Stage::Writeback as usize];
+        let halted = self.draining
+            && !self.halted
+            && self.stages[..Stage::Writeback as usize].iter().all(Option::is_none);
+
+        self.halted |= halted;
+        self.stats.cycles += 1;
+        self.stats.retired += writeback.is_some() as u64;
+        self.stats.stall_cycles += stalled as u64;
+
+        let cycle = PipelineCycle {
+            cycle: self.stats.cycles,
+            stages: self.stages.map(|slot| slot.map(|slot| slot.pc)),
+            stalled,
+            flushed: self.flush(flush_from, emulator),
+            halted,
+        };
+
+        Ok(cycle)
+    }
+
+    /// Squash the stage and every stage before it, continuing to fetch from wherever the emulator is unless the
+    /// fetch was redirected by decode.
+    fn flush(&mut self, from: Option<Stage>, emulator: &Emulator) -> usize {
+        let Some(from) = from else {
+            return 0;
+        };
+
+        if from != Stage::Fetch {
+            self.fetch_pc = emulator.pc;
+        }
+
+        let flushed = self.stages[..=from as usize]
+            .iter_mut()
+            .filter_map(Option::take)
+            .count();
+
+        self.stats.flushed += flushed as u64;
+        flushed
+    }
+
+    fn fetch(&mut self, emulator: &Emulator) -> StageSlot {
+        let pc = self.fetch_pc;
+        let word = |addr: Word| emulator.fetch_word_or_err(addr).ok().map(|word| *word);
+
+        let instruction = word(pc)
+            .and_then(|word| Instruction::deassemble_instruction_word(word).ok())
+            .and_then(|mut instruction| {
+                if instruction.kind.has_immediate() {
+                    instruction.immediate =
+                        Some(word(pc.wrapping_add(libisa::BYTES_PER_WORD as Word))?);
+                }
+
+                Some(instruction)
+            });
+
+        let len = instruction.map_or(libisa::BYTES_PER_WORD, |instruction| {
+            instruction.kind.len_bytes()
+        });
+
+        self.fetch_pc = pc.wrapping_add(len as Word);
+        StageSlot { pc, instruction }
+    }
+
+    /// Address of the instruction following the one in execute along the predicted path.
+    fn next_pc(&self) -> Word {
+        self.stages[Stage::Decode as usize]
+            .or(self.stages[Stage::Fetch as usize])
+            .map_or(self.fetch_pc, |slot| slot.pc)
+    }
+
+    fn decoded_jump_target(slot: &StageSlot) -> Option<Word> {
+        let instruction = slot.instruction?;
+
+        match instruction.kind {
+            InstructionKind::JmpI | InstructionKind::JmpR => {
+                instruction.static_jump_target(slot.pc)
+            }
+            _ => None,
+        }
+    }
+
+    fn reads_written(reader: &StageSlot, writer: &StageSlot) -> bool {
+        let (Some(reader), Some(writer)) = (reader.instruction, writer.instruction) else {
+            return false;
+        };
+
+        let written: Option<Register> =
+            writer.kind.writes_reg_a().then_some(writer.reg_a).flatten();
+        let read_a = reader.kind.reads_reg_a().then_some(reader.reg_a).flatten();
+        let read_b = reader.kind.has_reg_b().then_some(reader.reg_b).flatten();
+
+        written.is_some() && (written == read_a || written == read_b)
+    }
+}
+
+impl Display for Stage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Fetch => "F",
+            Self::Decode => "D",
+            Self::Execute => "E",
+            Self::Writeback => "W",
+        })
+    }
+}
+
+impl Display for PipelineCycle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:6}", self.cycle)?;
+
+        let stages = [
+            Stage::Fetch,
+            Stage::Decode,
+            Stage::Execute,
+            Stage::Writeback,
+        ];
+
+        for (stage, pc) in stages.iter().zip(self.stages) {
+            match pc {
+                Some(pc) => write!(f, "  {} {:04x}", stage, pc)?,
+                None => write!(f, "  {} ----", stage)?,
+            }
+        }
+
+        if self.stalled {
+            f.write_str("  stall")?;
+        }
+
+        if self.flushed > 0 {
+            write!(f, "  flush {}", self.flushed)?;
+        }
+
+        if self.halted {
+            f.write_str("  halt")?;
+        }
+
+        Ok(())
+    }
+}
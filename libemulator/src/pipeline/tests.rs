@@ -0,0 +1,154 @@
+use libisa::{
+    instruction::{kind::InstructionKind, Instruction},
+    Word,
+};
+
+use crate::{
+    semihosting::{Semihosting, SemihostingCall, ARG0_OFFSET, COMMAND_OFFSET, DEFAULT_BASE},
+    Emulator,
+};
+
+use super::{Pipeline, PipelineCycle, PipelineStats};
+
+fn run(
+    instructions: impl IntoIterator<Item = Instruction>,
+) -> (Emulator, Pipeline, Vec<PipelineCycle>) {
+    let program = libisa::instruction::assembler::assemble(instructions)
+        .unwrap()
+        .machine_code;
+
+    let mut emulator = Emulator::new(program).unwrap();
+    let mut pipeline = Pipeline::new(0);
+    let mut cycles = Vec::new();
+
+    while !pipeline.halted() {
+        cycles.push(pipeline.step(&mut emulator).unwrap());
+    }
+
+    (emulator, pipeline, cycles)
+}
+
+fn stage_pcs(cycles: &[PipelineCycle]) -> Vec<[Option<Word>; 4]> {
+    cycles.iter().map(|cycle| cycle.stages).collect()
+}
+
+#[test]
+fn independent_instructions_flow_through() {
+    let (_, pipeline, cycles) = run([
+        Instruction::new(InstructionKind::Nop),
+        Instruction::new(InstructionKind::Nop),
+        Instruction::new(InstructionKind::Halt),
+    ]);
+
+    assert_eq!(
+        stage_pcs(&cycles),
+        [
+            [Some(0), None, None, None],
+            [Some(2), Some(0), None, None],
+            [Some(4), Some(2), Some(0), None],
+            [Some(6), Some(4), Some(2), Some(0)],
+            // The halt executes, flushing what was fetched behind it.
+            [Some(8), Some(6), Some(4), Some(2)],
+            [None, None, None, Some(4)],
+        ]
+    );
+
+    assert_eq!(
+        pipeline.stats,
+        PipelineStats {
+            cycles: 6,
+            retired: 3,
+            stall_cycles: 0,
+            flushed: 2,
+        }
+    );
+}
+
+#[test]
+fn dependent_instruction_stalls() {
+    let (emulator, pipeline, cycles) = run([
+        Instruction::new(InstructionKind::LoadI)
+            .with_reg_a(0)
+            .with_immediate(2),
+        Instruction::new(InstructionKind::AddI)
+            .with_reg_a(0)
+            .with_immediate(3),
+        Instruction::new(InstructionKind::Halt),
+    ]);
+
+    assert_eq!(emulator.reg_file.get(0).copied(), Some(5));
+    assert_eq!(pipeline.stats.stall_cycles, 1);
+
+    // The add waits in decode while the load goes through writeback, leaving a bubble in execute.
+    assert!(cycles[3].stalled);
+    assert_eq!(cycles[3].stages, [Some(8), Some(4), None, Some(0)]);
+}
+
+#[test]
+fn jumps_flush_by_resolving_stage() {
+    let immediate = run([
+        Instruction::new(InstructionKind::JmpI).with_immediate(6),
+        Instruction::new(InstructionKind::Nop),
+        Instruction::new(InstructionKind::Halt),
+    ]);
+
+    let register = run([
+        Instruction::new(InstructionKind::LoadI)
+            .with_reg_a(0)
+            .with_immediate(10),
+        Instruction::new(InstructionKind::Jmp).with_reg_a(0),
+        Instruction::new(InstructionKind::Nop),
+        Instruction::new(InstructionKind::Nop),
+        Instruction::new(InstructionKind::Halt),
+    ]);
+
+    // Flushing the fetched nop, and the two fetched behind the halt.
+    assert_eq!(immediate.1.stats.flushed, 1 + 2);
+
+    // Flushing both nops once the jump executes, and the two fetched behind the halt.
+    assert_eq!(register.1.stats.flushed, 2 + 2);
+    assert_eq!(register.1.stats.retired, 3);
+}
+
+#[test]
+fn semihosting_exit_halts_pipeline() {
+    let program = libisa::instruction::assembler::assemble([
+        Instruction::new(InstructionKind::LoadI)
+            .with_reg_a(0)
+            .with_immediate(DEFAULT_BASE),
+        Instruction::new(InstructionKind::LoadI)
+            .with_reg_a(1)
+            .with_immediate(3),
+        Instruction::new(InstructionKind::StoreO)
+            .with_reg_a(0)
+            .with_reg_b(1)
+            .with_immediate(ARG0_OFFSET),
+        Instruction::new(InstructionKind::LoadI)
+            .with_reg_a(1)
+            .with_immediate(SemihostingCall::Exit as Word),
+        Instruction::new(InstructionKind::StoreO)
+            .with_reg_a(0)
+            .with_reg_b(1)
+            .with_immediate(COMMAND_OFFSET),
+        Instruction::new(InstructionKind::LoadI)
+            .with_reg_a(2)
+            .with_immediate(1),
+        Instruction::new(InstructionKind::Halt),
+    ])
+    .unwrap()
+    .machine_code;
+
+    let mut emulator = Emulator::new(program)
+        .unwrap()
+        .with_semihosting(Semihosting::new(DEFAULT_BASE));
+    let mut pipeline = Pipeline::new(0);
+
+    let cycles = (0..100)
+        .map(|_| pipeline.step(&mut emulator).unwrap())
+        .take_while(|cycle| !cycle.halted)
+        .count();
+
+    assert!(pipeline.halted(), "Pipeline didn't halt after {} cycles", cycles);
+    assert_eq!(emulator.semihosting.unwrap().exit_status, Some(3));
+    assert_eq!(emulator.reg_file.get(2).copied(), Some(0), "Nothing runs after the exit");
+}
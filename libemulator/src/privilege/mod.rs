@@ -0,0 +1,81 @@
+use libisa::{
+    privilege::{ControlRegister, Mode, TrapCause, STATUS_PREVIOUS_USER},
+    Word,
+};
+
+use crate::{Emulator, ExecuteErr, ExecuteOk};
+
+#[cfg(test)]
+mod tests;
+
+impl Emulator {
+    pub fn control_register(&self, register: ControlRegister) -> Word {
+        self.control_registers[register as usize]
+    }
+
+    pub fn set_control_register(&mut self, register: ControlRegister, value: Word) {
+        self.control_registers[register as usize] = value;
+    }
+
+    /// Fault if user mode accesses any of the `len` bytes from `addr` in the protected region or the device registers,
+    /// as remapping banks would otherwise get around the protection.
+    pub(super) fn check_protection(&self, addr: Word, len: Word) -> Result<(), ExecuteErr> {
+        if self.mode == Mode::Supervisor {
+            return Ok(());
+        }
+
+        let start = self.control_register(ControlRegister::ProtectStart);
+        let end = self.control_register(ControlRegister::ProtectEnd);
+
+        match (0..len)
+            .map(|offset| addr.wrapping_add(offset))
+            .find(|addr| (start..end).contains(addr) || self.is_device_register(*addr))
+        {
+            Some(addr) => Err(ExecuteErr::ProtectionFault(addr)),
+            None => Ok(()),
+        }
+    }
+
+    /// Enter supervisor mode at the trap vector, `sret` returning to `epc` in the mode trapped from.
+    pub(super) fn trap(&mut self, cause: TrapCause, epc: Word) -> Result<ExecuteOk, ExecuteErr> {
+        let status = match self.mode {
+            Mode::Supervisor => 0,
+            Mode::User => STATUS_PREVIOUS_USER,
+        };
+
+        self.set_control_register(ControlRegister::Status, status);
+        self.set_control_register(ControlRegister::Epc, epc);
+        self.set_control_register(ControlRegister::Cause, cause as Word);
+
+        self.mode = Mode::Supervisor;
+        self.pc = self.control_register(ControlRegister::TrapVector);
+
+        Ok(ExecuteOk::Normal)
+    }
+
+    pub(super) fn trap_return(&mut self) -> Result<ExecuteOk, ExecuteErr> {
+        self.mode = match self.control_register(ControlRegister::Status) & STATUS_PREVIOUS_USER {
+            0 => Mode::Supervisor,
+            _ => Mode::User,
+        };
+        self.pc = self.control_register(ControlRegister::Epc);
+
+        Ok(ExecuteOk::Normal)
+    }
+
+    /// Trap on the faults of the instruction at `pc`, passing on any other error.
+    pub(super) fn trap_fault(
+        &mut self,
+        err: ExecuteErr,
+        pc: Word,
+    ) -> Result<ExecuteOk, ExecuteErr> {
+        match err {
+            ExecuteErr::PrivilegedInstruction(_) => self.trap(TrapCause::PrivilegedInstruction, pc),
+            ExecuteErr::ProtectionFault(addr) => {
+                self.set_control_register(ControlRegister::FaultAddr, addr);
+                self.trap(TrapCause::ProtectionFault, pc)
+            }
+            err => Err(err),
+        }
+    }
+}
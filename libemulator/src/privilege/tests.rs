@@ -0,0 +1,207 @@
+use libisa::{
+    instruction::{kind::InstructionKind, Instruction},
+    privilege::{ControlRegister, Mode, TrapCause},
+    Word,
+};
+
+use crate::{
+    mmu::{self, Mmu},
+    Emulator,
+};
+
+const HANDLER_ADDR: usize = 0x100;
+const USER_ADDR: usize = 0x200;
+const PROTECTED: [Word; 2] = [0x300, 0x400];
+
+fn set_control_register(register: ControlRegister, value: Word) -> [Instruction; 2] {
+    [
+        Instruction::new(InstructionKind::LoadI)
+            .with_reg_a(0)
+            .with_immediate(value),
+        Instruction::new(InstructionKind::SetCr)
+            .with_reg_a(0)
+            .with_immediate(register as Word),
+    ]
+}
+
+fn get_control_register(reg: usize, register: ControlRegister) -> Instruction {
+    Instruction::new(InstructionKind::GetCr)
+        .with_reg_a(reg)
+        .with_immediate(register as Word)
+}
+
+fn assemble(instructions: Vec<Instruction>) -> Vec<u8> {
+    libisa::instruction::assembler::assemble(instructions)
+        .unwrap()
+        .machine_code
+}
+
+/// Monitor protecting a region and dropping to the user code, its trap handler collecting the cause, return address
+/// and fault address in registers 1 to 3 before halting.
+fn monitor(user: Vec<Instruction>) -> Vec<u8> {
+    let mut program = assemble(
+        [
+            set_control_register(ControlRegister::TrapVector, HANDLER_ADDR as Word),
+            set_control_register(ControlRegister::ProtectStart, PROTECTED[0]),
+            set_control_register(ControlRegister::ProtectEnd, PROTECTED[1]),
+            set_control_register(ControlRegister::Epc, USER_ADDR as Word),
+            set_control_register(ControlRegister::Status, 1),
+        ]
+        .concat()
+        .into_iter()
+        .chain([Instruction::new(InstructionKind::Sret)])
+        .collect(),
+    );
+
+    program.resize(HANDLER_ADDR, 0);
+    program.extend(assemble(vec![
+        get_control_register(1, ControlRegister::Cause),
+        get_control_register(2, ControlRegister::Epc),
+        get_control_register(3, ControlRegister::FaultAddr),
+        Instruction::new(InstructionKind::Halt),
+    ]));
+
+    program.resize(USER_ADDR, 0);
+    program.extend(assemble(user));
+    program
+}
+
+fn run_user_code(user: Vec<Instruction>) -> anyhow::Result<Emulator> {
+    let mut emulator = Emulator::new(monitor(user))?;
+    emulator.execute_to_halt()?;
+
+    Ok(emulator)
+}
+
+#[test]
+fn system_calls_trap_to_supervisor_mode() -> anyhow::Result<()> {
+    let emulator = run_user_code(vec![
+        Instruction::new(InstructionKind::Sys),
+        Instruction::new(InstructionKind::Nop),
+    ])?;
+
+    assert_eq!(emulator.mode, Mode::Supervisor);
+    assert_eq!(
+        emulator.reg_file.get(1),
+        Some(&(TrapCause::SystemCall as Word))
+    );
+    assert_eq!(
+        emulator.reg_file.get(2),
+        Some(&(USER_ADDR as Word + 2)),
+        "Returns past sys"
+    );
+    assert_eq!(
+        emulator.control_register(ControlRegister::Status),
+        1,
+        "Trapped from user mode"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn user_mode_faults_on_privileged_instructions() -> anyhow::Result<()> {
+    for user in [
+        set_control_register(ControlRegister::ProtectEnd, 0).to_vec(),
+        vec![
+            Instruction::new(InstructionKind::Nop),
+            Instruction::new(InstructionKind::Halt),
+        ],
+    ] {
+        let faulting_addr = USER_ADDR as Word + user[0].kind.len_bytes() as Word;
+        let emulator = run_user_code(user)?;
+
+        assert_eq!(
+            emulator.reg_file.get(1),
+            Some(&(TrapCause::PrivilegedInstruction as Word))
+        );
+        assert_eq!(emulator.reg_file.get(2), Some(&faulting_addr));
+        assert_eq!(
+            emulator.control_register(ControlRegister::ProtectEnd),
+            PROTECTED[1]
+        );
+    }
+
+    Ok(())
+}
+
+#[test]
+fn user_mode_faults_on_protected_region_accesses() -> anyhow::Result<()> {
+    let emulator = run_user_code(vec![
+        Instruction::new(InstructionKind::LoadI)
+            .with_reg_a(4)
+            .with_immediate(PROTECTED[0] - 1),
+        Instruction::new(InstructionKind::Store)
+            .with_reg_a(4)
+            .with_reg_b(4),
+    ])?;
+
+    assert_eq!(
+        emulator.reg_file.get(1),
+        Some(&(TrapCause::ProtectionFault as Word))
+    );
+    assert_eq!(emulator.reg_file.get(2), Some(&(USER_ADDR as Word + 4)));
+    assert_eq!(
+        emulator.reg_file.get(3),
+        Some(&PROTECTED[0]),
+        "Second byte of the word"
+    );
+    assert_eq!(
+        emulator.memory.get((PROTECTED[0] - 1) as usize),
+        Some(&0),
+        "Store didn't happen"
+    );
+
+    let jumping = run_user_code(vec![
+        Instruction::new(InstructionKind::JmpI).with_immediate(PROTECTED[0])
+    ])?;
+    assert_eq!(
+        jumping.reg_file.get(1),
+        Some(&(TrapCause::ProtectionFault as Word))
+    );
+    assert_eq!(
+        jumping.reg_file.get(2),
+        Some(&PROTECTED[0]),
+        "Fetch faults at the instruction"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn user_mode_cant_switch_banks_around_protection() -> anyhow::Result<()> {
+    // Mapping window 1 onto bank 0 would put the protected region at 0x1300.
+    let program = monitor(vec![
+        Instruction::new(InstructionKind::LoadI)
+            .with_reg_a(4)
+            .with_immediate(mmu::DEFAULT_BASE + 2),
+        Instruction::new(InstructionKind::LoadI)
+            .with_reg_a(5)
+            .with_immediate(0),
+        Instruction::new(InstructionKind::Store)
+            .with_reg_a(4)
+            .with_reg_b(5),
+        Instruction::new(InstructionKind::LoadI)
+            .with_reg_a(4)
+            .with_immediate(0x1000 + PROTECTED[0]),
+        Instruction::new(InstructionKind::Store)
+            .with_reg_a(4)
+            .with_reg_b(4),
+    ]);
+
+    let mut emulator = Emulator::new(program)?.with_mmu(Mmu::new(mmu::DEFAULT_BASE))?;
+    emulator.execute_to_halt()?;
+
+    assert_eq!(
+        emulator.reg_file.get(1),
+        Some(&(TrapCause::ProtectionFault as Word))
+    );
+    assert_eq!(emulator.reg_file.get(3), Some(&(mmu::DEFAULT_BASE + 2)));
+    assert_eq!(
+        emulator.physical_addr(0x1000 + PROTECTED[0]),
+        0x1000 + PROTECTED[0] as usize
+    );
+    assert_eq!(emulator.memory.get(PROTECTED[0] as usize), Some(&0));
+
+    Ok(())
+}
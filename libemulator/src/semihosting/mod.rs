@@ -0,0 +1,178 @@
+use std::io::{self, Read, Write};
+
+use libisa::Word;
+
+use crate::{Emulator, ExecuteErr, ExecuteOk};
+
+#[cfg(test)]
+mod tests;
+
+/// Name of the platform MMIO range the doorbell registers are at.
+pub const MMIO_NAME: &str = "semihosting";
+
+/// Base address of the doorbell registers when the platform doesn't give one, at the top of the address space.
+pub const DEFAULT_BASE: Word = 0xfff0;
+
+/// Offsets of the doorbell registers from the base address. Storing the call number to the command register performs
+/// the call with the arguments stored before it, leaving its result in the result registers.
+pub const COMMAND_OFFSET: Word = 0;
+pub const ARG0_OFFSET: Word = 2;
+pub const ARG1_OFFSET: Word = 4;
+pub const RESULT_OFFSET: Word = 6;
+pub const RESULT_HIGH_OFFSET: Word = 8;
+
+/// Bytes taken up by the doorbell registers.
+pub const REGISTERS_SIZE: Word = 10;
+
+/// Services the host provides the program, by the number stored to the command register.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u16)]
+pub enum SemihostingCall {
+    /// Write the low byte of argument 0.
+    PutChar = 1,
+
+    /// Write the argument 1 bytes of memory from the address in argument 0.
+    Write = 2,
+
+    /// Read a byte of input into the result, 0xffff at the end of input.
+    ReadChar = 3,
+
+    /// Put the cycles counted so far into the result, low word first and high word in the high result.
+    CycleCount = 4,
+
+    /// Halt with argument 0 as the exit status.
+    Exit = 5,
+}
+
+/// Host I/O for the program, requested through MMIO doorbell registers so no instruction has to be set aside for it.
+pub struct Semihosting {
+    pub base: Word,
+
+    /// Status the program exited with through the exit call, `None` if it hasn't.
+    pub exit_status: Option<Word>,
+
+    output: Box<dyn Write>,
+    input: Box<dyn Read>,
+}
+
+impl Semihosting {
+    /// Semihosting through the standard output and input of the host.
+    pub fn new(base: Word) -> Self {
+        Self {
+            base,
+            exit_status: None,
+            output: Box::new(io::stdout()),
+            input: Box::new(io::stdin()),
+        }
+    }
+
+    pub fn with_output(mut self, output: impl Write + 'static) -> Self {
+        self.output = Box::new(output);
+        self
+    }
+
+    pub fn with_input(mut self, input: impl Read + 'static) -> Self {
+        self.input = Box::new(input);
+        self
+    }
+}
+
+impl SemihostingCall {
+    pub fn from_number(number: Word) -> Option<Self> {
+        Some(match number {
+            1 => Self::PutChar,
+            2 => Self::Write,
+            3 => Self::ReadChar,
+            4 => Self::CycleCount,
+            5 => Self::Exit,
+            _ => return None,
+        })
+    }
+}
+
+impl Emulator {
+    pub fn with_semihosting(mut self, semihosting: Semihosting) -> Self {
+        self.semihosting = Some(semihosting);
+        self
+    }
+
+    /// Perform the semihosting call if the store of `len` bytes to `addr` hit the command register.
+    pub(super) fn semihosting_store(
+        &mut self,
+        addr: Word,
+        len: Word,
+    ) -> Result<ExecuteOk, ExecuteErr> {
+        let Some(base) = self
+            .semihosting
+            .as_ref()
+            .map(|semihosting| semihosting.base)
+        else {
+            return Ok(ExecuteOk::Normal);
+        };
+
+        let command_addr = base.wrapping_add(COMMAND_OFFSET) as u32;
+        let store = addr as u32..addr as u32 + len as u32;
+
+        if !(store.start < command_addr + 2 && command_addr < store.end) {
+            return Ok(ExecuteOk::Normal);
+        }
+
+        let register = |emulator: &Self, offset: Word| {
+            emulator
+                .mem_word_or_err(base.wrapping_add(offset))
+                .map(|word| *word)
+        };
+
+        let command = register(self, COMMAND_OFFSET)?;
+        let arg0 = register(self, ARG0_OFFSET)?;
+        let arg1 = register(self, ARG1_OFFSET)?;
+
+        let call = SemihostingCall::from_number(command)
+            .ok_or(ExecuteErr::UnknownSemihostingCall(command))?;
+
+        let (result, result_high) = match call {
+            SemihostingCall::PutChar => {
+                self.semihosting_output(&[arg0 as u8])?;
+                (0, 0)
+            }
+            SemihostingCall::Write => {
+                let bytes = (0..arg1)
+                    .map(|offset| self.mem_byte_or_err(arg0.wrapping_add(offset)).copied())
+                    .collect::<Result<Vec<u8>, _>>()?;
+
+                self.semihosting_output(&bytes)?;
+                (arg1, 0)
+            }
+            SemihostingCall::ReadChar => {
+                let mut byte = [0];
+                let semihosting = self.semihosting.as_mut().unwrap();
+
+                match semihosting.input.read(&mut byte) {
+                    Ok(0) => (Word::MAX, 0),
+                    Ok(_) => (byte[0] as Word, 0),
+                    Err(e) => return Err(ExecuteErr::SemihostingIo(e.kind())),
+                }
+            }
+            SemihostingCall::CycleCount => (self.cycles as Word, (self.cycles >> 16) as Word),
+            SemihostingCall::Exit => {
+                self.semihosting.as_mut().unwrap().exit_status = Some(arg0);
+                return Ok(ExecuteOk::Halted);
+            }
+        };
+
+        *self.mem_word_mut_or_err(base.wrapping_add(RESULT_OFFSET))? = result;
+        *self.mem_word_mut_or_err(base.wrapping_add(RESULT_HIGH_OFFSET))? = result_high;
+
+        Ok(ExecuteOk::Normal)
+    }
+
+    fn semihosting_output(&mut self, bytes: &[u8]) -> Result<(), ExecuteErr> {
+        let output = &mut self.semihosting.as_mut().unwrap().output;
+
+        // Flushed right away so the output interleaves with the emulator's own.
+        output
+            .write_all(bytes)
+            .and_then(|_| output.flush())
+            .map_err(|e| ExecuteErr::SemihostingIo(e.kind()))
+    }
+}
@@ -0,0 +1,145 @@
+use std::{cell::RefCell, io::Write, rc::Rc};
+
+use libisa::{
+    instruction::{kind::InstructionKind, Instruction},
+    Word,
+};
+
+use crate::{
+    semihosting::{
+        Semihosting, SemihostingCall, ARG0_OFFSET, ARG1_OFFSET, COMMAND_OFFSET, DEFAULT_BASE,
+        RESULT_OFFSET,
+    },
+    Emulator, ExecuteErr,
+};
+
+/// Output shared with the test after being handed to the emulator.
+#[derive(Debug, Clone, Default)]
+struct SharedOutput(Rc<RefCell<Vec<u8>>>);
+
+impl Write for SharedOutput {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.borrow_mut().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Stores the arguments and then the call number to the doorbell, using registers 0 and 1.
+fn call(number: Word, arg0: Word, arg1: Word) -> Vec<Instruction> {
+    let store = |value: Word, offset: Word| {
+        [
+            Instruction::new(InstructionKind::LoadI)
+                .with_reg_a(1)
+                .with_immediate(value),
+            Instruction::new(InstructionKind::StoreO)
+                .with_reg_a(0)
+                .with_reg_b(1)
+                .with_immediate(offset),
+        ]
+    };
+
+    [Instruction::new(InstructionKind::LoadI)
+        .with_reg_a(0)
+        .with_immediate(DEFAULT_BASE)]
+    .into_iter()
+    .chain(store(arg0, ARG0_OFFSET))
+    .chain(store(arg1, ARG1_OFFSET))
+    .chain(store(number, COMMAND_OFFSET))
+    .collect()
+}
+
+/// Loads the result of the last call into the register.
+fn load_result(reg: usize) -> Instruction {
+    Instruction::new(InstructionKind::LoadO)
+        .with_reg_a(reg)
+        .with_reg_b(0)
+        .with_immediate(RESULT_OFFSET)
+}
+
+fn assemble(instructions: Vec<Instruction>) -> Vec<u8> {
+    libisa::instruction::assembler::assemble(instructions)
+        .unwrap()
+        .machine_code
+}
+
+#[test]
+fn output_calls_write_to_host_and_exit_halts() -> anyhow::Result<()> {
+    const MESSAGE_ADDR: Word = 0x200;
+
+    let mut program = assemble(
+        [
+            call(SemihostingCall::PutChar as Word, b'H' as Word, 0),
+            call(SemihostingCall::Write as Word, MESSAGE_ADDR, 4),
+            call(SemihostingCall::Exit as Word, 3, 0),
+            vec![Instruction::new(InstructionKind::Halt)],
+        ]
+        .concat(),
+    );
+
+    program.resize(MESSAGE_ADDR as usize, 0);
+    program.extend(b"ello");
+
+    let output = SharedOutput::default();
+    let mut emulator = Emulator::new(program)?
+        .with_semihosting(Semihosting::new(DEFAULT_BASE).with_output(output.clone()));
+
+    emulator.execute_to_halt()?;
+
+    assert_eq!(output.0.borrow().as_slice(), b"Hello");
+    assert_eq!(emulator.semihosting.unwrap().exit_status, Some(3));
+
+    Ok(())
+}
+
+#[test]
+fn input_and_cycle_count_calls_return_results() -> anyhow::Result<()> {
+    let program = assemble(
+        [
+            call(SemihostingCall::ReadChar as Word, 0, 0),
+            vec![load_result(2)],
+            call(SemihostingCall::ReadChar as Word, 0, 0),
+            vec![load_result(3)],
+            call(SemihostingCall::CycleCount as Word, 0, 0),
+            vec![load_result(4), Instruction::new(InstructionKind::Halt)],
+        ]
+        .concat(),
+    );
+
+    let mut emulator = Emulator::new(program)?
+        .with_semihosting(Semihosting::new(DEFAULT_BASE).with_input(&b"A"[..]));
+
+    emulator.execute_to_halt()?;
+
+    assert_eq!(emulator.reg_file.get(2), Some(&(b'A' as Word)));
+    assert_eq!(emulator.reg_file.get(3), Some(&Word::MAX), "End of input");
+    assert!(*emulator.reg_file.get(4).unwrap() as u64 > 0);
+    assert_eq!(emulator.semihosting.unwrap().exit_status, None);
+
+    Ok(())
+}
+
+#[test]
+fn unknown_calls_are_errors_only_with_semihosting() -> anyhow::Result<()> {
+    let program = assemble(
+        [
+            call(0xbad, 0, 0),
+            vec![Instruction::new(InstructionKind::Halt)],
+        ]
+        .concat(),
+    );
+
+    let mut plain = Emulator::new(program.clone())?;
+    plain.execute_to_halt()?;
+
+    let mut semihosted = Emulator::new(program)?.with_semihosting(Semihosting::new(DEFAULT_BASE));
+    assert_eq!(
+        semihosted.execute_to_halt(),
+        Err(ExecuteErr::UnknownSemihostingCall(0xbad))
+    );
+
+    Ok(())
+}
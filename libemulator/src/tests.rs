@@ -0,0 +1,234 @@
+use libisa::{
+    executable::{Executable, Permissions, Segment},
+    instruction::{kind::InstructionKind, Instruction},
+    memimage::{self, ImageError, ImageFormat, ImageLayout},
+    timing::CycleProfile,
+    Word,
+};
+
+use crate::{Emulator, ExecuteErr};
+
+fn cycles_to_halt(cycle_profile: CycleProfile) -> anyhow::Result<u64> {
+    let program = libisa::instruction::assembler::assemble([
+        Instruction::new(InstructionKind::LoadI)
+            .with_reg_a(0)
+            .with_immediate(20),
+        Instruction::new(InstructionKind::Load)
+            .with_reg_a(1)
+            .with_reg_b(0),
+        Instruction::new(InstructionKind::JmpI).with_immediate(12),
+        Instruction::new(InstructionKind::Nop),
+        Instruction::new(InstructionKind::Halt),
+    ])?
+    .machine_code;
+
+    let mut emulator = Emulator::new(program)?.with_cycle_profile(cycle_profile);
+    emulator.execute_to_halt()?;
+
+    Ok(emulator.cycles)
+}
+
+#[test]
+fn cycles_get_counted_by_profile() -> anyhow::Result<()> {
+    assert_eq!(cycles_to_halt(CycleProfile::ideal())?, 4);
+
+    // loadi 2 + 1, load 1 + 1 + 1 memory access, jmpi 2 + 1 + 1 taken branch, skipping the nop, halt 1.
+    assert_eq!(cycles_to_halt(CycleProfile::multicycle())?, 11);
+
+    let slow_memory = CycleProfile::multicycle().with_memory_access_cycles(4);
+    assert_eq!(slow_memory.to_string(), "custom");
+    assert_eq!(cycles_to_halt(slow_memory)?, 14);
+
+    Ok(())
+}
+
+#[test]
+fn jumps_to_next_instruction_count_as_taken_by_condition() -> anyhow::Result<()> {
+    let cycles_comparing_to = |value| -> anyhow::Result<u64> {
+        let program = libisa::instruction::assembler::assemble([
+            Instruction::new(InstructionKind::CmpI)
+                .with_reg_a(0)
+                .with_immediate(value),
+            Instruction::new(InstructionKind::JmpZI).with_immediate(8),
+            Instruction::new(InstructionKind::Halt),
+        ])?
+        .machine_code;
+
+        let profile = CycleProfile::ideal().with_taken_branch_cycles(10);
+        let mut emulator = Emulator::new(program)?.with_cycle_profile(profile);
+        emulator.execute_to_halt()?;
+
+        Ok(emulator.cycles)
+    };
+
+    assert_eq!(cycles_comparing_to(0)?, 3 + 10);
+    assert_eq!(cycles_comparing_to(1)?, 3);
+
+    Ok(())
+}
+
+#[test]
+fn harvard_stores_leave_instruction_memory_alone() -> anyhow::Result<()> {
+    let program = libisa::instruction::assembler::assemble([
+        Instruction::new(InstructionKind::LoadI)
+            .with_reg_a(0)
+            .with_immediate(0),
+        Instruction::new(InstructionKind::Load)
+            .with_reg_a(1)
+            .with_reg_b(0),
+        Instruction::new(InstructionKind::LoadI)
+            .with_reg_a(2)
+            .with_immediate(0xFFFF),
+        Instruction::new(InstructionKind::Store)
+            .with_reg_a(0)
+            .with_reg_b(2),
+        Instruction::new(InstructionKind::Halt),
+    ])?
+    .machine_code;
+
+    let mut emulator = Emulator::new_harvard(program.clone(), vec![0x12, 0x34])?;
+    emulator.execute_to_halt()?;
+
+    // Both memories start at address 0, the load reading data memory and the store writing it.
+    assert_eq!(emulator.reg_file.get(1), Some(&0x1234));
+    assert_eq!(
+        emulator.memory.get_multi::<Word>(0).as_deref(),
+        Some(&0xFFFF)
+    );
+    assert!(emulator
+        .code_memory()
+        .iter_words()
+        .take(program.len())
+        .eq(program.iter()));
+
+    Ok(())
+}
+
+#[test]
+fn executables_load_by_segments() -> anyhow::Result<()> {
+    let code = libisa::instruction::assembler::assemble([
+        Instruction::new(InstructionKind::LoadI)
+            .with_reg_a(0)
+            .with_immediate(0x200),
+        Instruction::new(InstructionKind::Load)
+            .with_reg_a(1)
+            .with_reg_b(0),
+        Instruction::new(InstructionKind::Halt),
+    ])?
+    .machine_code;
+
+    let executable = Executable::new(0x100)
+        .with_segment(Segment::new(0x100, Permissions::READ_EXECUTE, code))
+        .with_segment(Segment::new(
+            0x200,
+            Permissions::READ_WRITE,
+            vec![0xBE, 0xEF],
+        ))
+        .with_symbol("start", 0x100)
+        .with_debug(vec![1, 2, 3]);
+
+    let bytes = executable.to_bytes()?;
+    assert_eq!(Executable::parse(&bytes)?, executable);
+
+    for mut emulator in [
+        Emulator::new(bytes.clone())?,
+        Emulator::new_harvard(bytes, vec![])?,
+    ] {
+        assert_eq!(emulator.pc, 0x100);
+
+        emulator.execute_to_halt()?;
+        assert_eq!(emulator.reg_file.get(1), Some(&0xBEEF));
+    }
+
+    Ok(())
+}
+
+#[test]
+fn memory_images_round_trip() -> anyhow::Result<()> {
+    let program = libisa::instruction::assembler::assemble([
+        Instruction::new(InstructionKind::LoadI)
+            .with_reg_a(0)
+            .with_immediate(0x1234),
+        Instruction::new(InstructionKind::Halt),
+    ])?
+    .machine_code;
+
+    for format in [ImageFormat::IntelHex, ImageFormat::Readmemh] {
+        for layout in [ImageLayout::new(1, 0)?, ImageLayout::new(2, 0x10000)?] {
+            let image = memimage::export(&program, format, layout)?;
+
+            assert_eq!(memimage::import(&image, format, layout)?, program);
+        }
+
+        let layout = ImageLayout::new(1, 0)?;
+        let image = memimage::export(&program, format, layout)?;
+
+        let mut emulator = Emulator::new(memimage::import(&image, format, layout)?)?;
+        emulator.execute_to_halt()?;
+        assert_eq!(emulator.reg_file.get(0), Some(&0x1234));
+    }
+
+    // A stray address far past the memory is an error rather than a huge allocation.
+    assert_eq!(
+        memimage::import(b"@7fffffff\n00", ImageFormat::Readmemh, ImageLayout::default()),
+        Err(ImageError::BeyondLimit {
+            addr: 0x7fffffff,
+            limit: 0x10000
+        })
+    );
+    let small = ImageLayout::default().with_limit(0x100);
+    assert!(memimage::import(b"@100\n00", ImageFormat::Readmemh, small).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn memory_size_limits_accessible_memory() -> anyhow::Result<()> {
+    let program = libisa::instruction::assembler::assemble([
+        Instruction::new(InstructionKind::LoadI)
+            .with_reg_a(0)
+            .with_immediate(0x100),
+        Instruction::new(InstructionKind::Load)
+            .with_reg_a(1)
+            .with_reg_b(0),
+        Instruction::new(InstructionKind::Halt),
+    ])?
+    .machine_code;
+
+    let mut emulator = Emulator::new(program.clone())?.with_memory_size(0x100)?;
+    assert_eq!(
+        emulator.execute_to_halt(),
+        Err(ExecuteErr::MemoryAccessViolation(0x100))
+    );
+
+    assert!(Emulator::new(program)?.with_memory_size(4).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn whole_address_space_is_accessible_by_default() -> anyhow::Result<()> {
+    let program = libisa::instruction::assembler::assemble([
+        Instruction::new(InstructionKind::LoadI)
+            .with_reg_a(0)
+            .with_immediate(Word::MAX),
+        Instruction::new(InstructionKind::StoreL)
+            .with_reg_a(0)
+            .with_reg_b(0),
+        Instruction::new(InstructionKind::LoadH)
+            .with_reg_a(1)
+            .with_reg_b(0),
+        Instruction::new(InstructionKind::Halt),
+    ])?
+    .machine_code;
+
+    for mut emulator in [
+        Emulator::new(program.clone())?,
+        Emulator::new_harvard(program, vec![])?,
+    ] {
+        emulator.execute_to_halt()?;
+        assert_eq!(emulator.reg_file.get(1), Some(&0xFF00));
+    }
+
+    Ok(())
+}
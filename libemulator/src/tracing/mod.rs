@@ -2,7 +2,7 @@ use std::collections::HashMap;
 
 use libisa::{Register, Word};
 
-use crate::volatile::patch::VolatilePatch;
+use crate::{volatile::patch::VolatilePatch, PhysAddr};
 
 #[cfg(test)]
 mod tests;
@@ -19,7 +19,8 @@ pub struct EmulatorTrace {
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct EmulatorIterationTrace {
-    pub memory_patches: HashMap<Word, VolatilePatch<u8>>,
+    /// Patches to physical memory.
+    pub memory_patches: HashMap<PhysAddr, VolatilePatch<u8>>,
     pub register_patches: HashMap<Register, VolatilePatch<Word>>,
 }
 
@@ -41,14 +42,14 @@ impl EmulatorTracing {
             .last() // Get the latest value.
     }
 
-    pub fn memory_byte_by_pc(&self, pc: Word, addr: Word) -> Option<&u8> {
+    pub fn memory_byte_by_pc(&self, pc: Word, addr: PhysAddr) -> Option<&u8> {
         self.iterations_by_pc(pc) // Get all iteration traces up to the PC.
             .filter_map(|iter_trace| iter_trace.memory_patches.get(&addr)) // Filter and map the traces to memory patches on the specified register.
             .map(|memory_patch| &memory_patch.new_value) // Map the patch to the new value it applies.
             .last() // Get the latest value.
     }
 
-    pub fn memory_word_by_pc(&self, pc: Word, addr: Word) -> Option<Word> {
+    pub fn memory_word_by_pc(&self, pc: Word, addr: PhysAddr) -> Option<Word> {
         let lower_byte = *self.memory_byte_by_pc(pc, addr)?;
         let upper_byte = *self.memory_byte_by_pc(pc, addr + 1)?;
 
@@ -46,6 +46,18 @@ impl<W, A> Volatile<W, A> where W: Word, A: Addr {
         })
     }
 
+    /// Grow or shrink to the size, failing rather than cutting off non-zero data.
+    pub fn resize(&mut self, size: A) -> anyhow::Result<()> {
+        let size = size.into();
+
+        if self.data.iter().skip(size).any(|word| !word.is_zero()) {
+            bail!("Data exceeds new size");
+        }
+
+        self.data.resize(size, W::zero());
+        Ok(())
+    }
+
     pub fn get(&self, addr: A) -> Option<&W> {
         self.data.get(Self::addr_to_usize(addr))
     }
@@ -1,7 +1,7 @@
 use libisa::Word;
 
 use crate::{
-    volatile::{multicell::VolatileMultiCell, mutcell::VolatileMutCell, mutmulticell::VolatileMutMultiCell}, Emulator, ExecuteErr
+    volatile::{multicell::VolatileMultiCell, mutcell::VolatileMutCell, mutmulticell::VolatileMutMultiCell}, Emulator, ExecuteErr, PhysAddr
 };
 
 impl Emulator {
@@ -18,26 +18,62 @@ impl Emulator {
     }
 
     pub(super) fn mem_byte_or_err(&self, addr: Word) -> Result<&u8, ExecuteErr> {
+        self.check_protection(addr, 1)?;
+
         self.memory
-            .get(addr)
+            .get(self.physical_addr(addr))
             .ok_or(ExecuteErr::MemoryAccessViolation(addr))
     }
 
-    pub(super) fn mem_byte_mut_or_err(&mut self, addr: Word) -> Result<VolatileMutCell<'_, u8, Word>, ExecuteErr> {
+    pub(super) fn mem_byte_mut_or_err(&mut self, addr: Word) -> Result<VolatileMutCell<'_, u8, PhysAddr>, ExecuteErr> {
+        self.check_protection(addr, 1)?;
+        let physical_addr = self.physical_addr(addr);
+
         self.memory
-            .get_mut(addr)
+            .get_mut(physical_addr)
             .ok_or(ExecuteErr::MemoryAccessViolation(addr))
     }
 
     pub(super) fn mem_word_or_err(&self, addr: Word) -> Result<VolatileMultiCell<Word>, ExecuteErr> {
+        self.check_protection(addr, libisa::BYTES_PER_WORD as Word)?;
+
         self.memory
-            .get_multi(addr)
+            .get_multi(self.physical_word_addr(addr)?)
+            .ok_or(ExecuteErr::MemoryAccessViolation(addr))
+    }
+
+    /// Instruction word at the address, fetched from instruction memory in Harvard mode.
+    pub(super) fn fetch_word_or_err(&self, addr: Word) -> Result<VolatileMultiCell<Word>, ExecuteErr> {
+        self.check_protection(addr, libisa::BYTES_PER_WORD as Word)?;
+
+        // Instruction memory is separate from the memory the MMU maps.
+        let physical_addr = match self.instruction_memory {
+            Some(_) => addr as PhysAddr,
+            None => self.physical_word_addr(addr)?,
+        };
+
+        self.code_memory()
+            .get_multi(physical_addr)
             .ok_or(ExecuteErr::MemoryAccessViolation(addr))
     }
 
-    pub(super) fn mem_word_mut_or_err(&mut self, addr: Word) -> Result<VolatileMutMultiCell<'_, Word, u8, Word>, ExecuteErr> {
+    pub(super) fn mem_word_mut_or_err(&mut self, addr: Word) -> Result<VolatileMutMultiCell<'_, Word, u8, PhysAddr>, ExecuteErr> {
+        self.check_protection(addr, libisa::BYTES_PER_WORD as Word)?;
+        let physical_addr = self.physical_word_addr(addr)?;
+
         self.memory
-            .get_mut_multi(addr)
+            .get_mut_multi(physical_addr)
             .ok_or(ExecuteErr::MemoryAccessViolation(addr))
     }
+
+    /// Physical address of the word at the address, which has to stay in one piece in physical memory when it
+    /// straddles two windows.
+    fn physical_word_addr(&self, addr: Word) -> Result<PhysAddr, ExecuteErr> {
+        let physical_addr = self.physical_addr(addr);
+
+        match self.physical_addr(addr.wrapping_add(1)) == physical_addr + 1 {
+            true => Ok(physical_addr),
+            false => Err(ExecuteErr::MemoryAccessViolation(addr)),
+        }
+    }
 }
@@ -0,0 +1,316 @@
+use std::fmt::Display;
+
+use thiserror::Error;
+
+use crate::{
+    reader::{Reader, Truncated},
+    Word,
+};
+
+/// Magic bytes every executable starts with. Deassembled as a raw program they would be an unassigned opcode, so
+/// raw binaries can't be mistaken for executables.
+pub const MAGIC: [u8; 4] = *b"STRM";
+
+/// Version of the instruction set executables are written for, bumped whenever encodings change incompatibly.
+pub const ISA_VERSION: u16 = 1;
+
+const SECTION_SYMBOLS: u8 = 1;
+const SECTION_DEBUG: u8 = 2;
+
+/// Executable image of a STRM1 program, all fields being stored big-endian like the instruction words:
+///
+/// ```text
+/// magic           4 bytes, "STRM"
+/// isa_version     u16
+/// entry           u16, initial PC
+/// segment_count   u16
+/// section_count   u16
+/// segments        addr u16, permissions u8 (bit 0 read, 1 write, 2 execute), len u32, data
+/// sections        kind u8 (1 symbols, 2 debug), len u32, data
+/// ```
+///
+/// Symbols are stored as addr u16, name length u8 and the UTF-8 name. Sections of unknown kinds are skipped when
+/// parsing, so new kinds can be added without breaking older loaders.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Executable {
+    pub isa_version: u16,
+    pub entry: Word,
+    pub segments: Vec<Segment>,
+    pub symbols: Vec<Symbol>,
+
+    /// Opaque debug information of whatever produced the executable.
+    pub debug: Option<Vec<u8>>,
+}
+
+/// Bytes loaded to consecutive addresses starting from its address.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Segment {
+    pub addr: Word,
+    pub permissions: Permissions,
+    pub data: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Permissions {
+    pub read: bool,
+    pub write: bool,
+    pub execute: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Symbol {
+    pub name: String,
+    pub addr: Word,
+}
+
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+pub enum ExecutableError {
+    #[error("Not a STRM1 executable")]
+    BadMagic,
+
+    #[error("Executable is for ISA version {0}, expected {ISA_VERSION}")]
+    UnsupportedVersion(u16),
+
+    #[error("Executable is truncated")]
+    Truncated,
+
+    #[error("Segment of {len} bytes at 0x{addr:04x} doesn't fit in the address space")]
+    SegmentOutOfRange { addr: Word, len: usize },
+
+    #[error("Symbol name '{0}' is longer than 255 bytes")]
+    SymbolNameTooLong(String),
+
+    #[error("Symbol name isn't valid UTF-8")]
+    InvalidSymbolName,
+}
+
+impl Executable {
+    pub fn new(entry: Word) -> Self {
+        Self {
+            isa_version: ISA_VERSION,
+            entry,
+            segments: vec![],
+            symbols: vec![],
+            debug: None,
+        }
+    }
+
+    pub fn with_segment(mut self, segment: Segment) -> Self {
+        self.segments.push(segment);
+        self
+    }
+
+    pub fn with_symbol(mut self, name: impl Into<String>, addr: Word) -> Self {
+        self.symbols.push(Symbol {
+            name: name.into(),
+            addr,
+        });
+        self
+    }
+
+    pub fn with_debug(mut self, debug: Vec<u8>) -> Self {
+        self.debug = Some(debug);
+        self
+    }
+
+    /// Whether the bytes look like an executable rather than a raw binary.
+    pub fn is_executable(bytes: &[u8]) -> bool {
+        bytes.starts_with(&MAGIC)
+    }
+
+    pub fn parse(bytes: &[u8]) -> Result<Self, ExecutableError> {
+        let mut reader = Reader::<ExecutableError>::new(bytes);
+
+        if reader.take(MAGIC.len())? != MAGIC {
+            return Err(ExecutableError::BadMagic);
+        }
+
+        let isa_version = reader.u16()?;
+
+        if isa_version != ISA_VERSION {
+            return Err(ExecutableError::UnsupportedVersion(isa_version));
+        }
+
+        let mut executable = Self::new(reader.u16()?);
+        let segment_count = reader.u16()?;
+        let section_count = reader.u16()?;
+
+        for _ in 0..segment_count {
+            let addr = reader.u16()?;
+            let permissions = Permissions::from_bits(reader.u8()?);
+            let len = reader.u32()? as usize;
+
+            if addr as usize + len > crate::ADDRESS_SPACE_SIZE {
+                return Err(ExecutableError::SegmentOutOfRange { addr, len });
+            }
+
+            executable.segments.push(Segment {
+                addr,
+                permissions,
+                data: reader.take(len)?.to_vec(),
+            });
+        }
+
+        for _ in 0..section_count {
+            let kind = reader.u8()?;
+            let len = reader.u32()? as usize;
+            let data = reader.take(len)?;
+
+            match kind {
+                SECTION_SYMBOLS => executable.symbols = Self::parse_symbols(data)?,
+                SECTION_DEBUG => executable.debug = Some(data.to_vec()),
+                _ => {}
+            }
+        }
+
+        Ok(executable)
+    }
+
+    fn parse_symbols(bytes: &[u8]) -> Result<Vec<Symbol>, ExecutableError> {
+        let mut reader = Reader::<ExecutableError>::new(bytes);
+        let mut symbols = vec![];
+
+        while !reader.is_empty() {
+            let addr = reader.u16()?;
+            let name = reader.string()?.ok_or(ExecutableError::InvalidSymbolName)?;
+
+            symbols.push(Symbol { name, addr });
+        }
+
+        Ok(symbols)
+    }
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>, ExecutableError> {
+        let mut sections = vec![];
+
+        if !self.symbols.is_empty() {
+            let mut symbols = vec![];
+
+            for symbol in &self.symbols {
+                let name_len = u8::try_from(symbol.name.len())
+                    .map_err(|_| ExecutableError::SymbolNameTooLong(symbol.name.clone()))?;
+
+                symbols.extend(symbol.addr.to_be_bytes());
+                symbols.push(name_len);
+                symbols.extend(symbol.name.as_bytes());
+            }
+
+            sections.push((SECTION_SYMBOLS, symbols));
+        }
+
+        if let Some(debug) = &self.debug {
+            sections.push((SECTION_DEBUG, debug.clone()));
+        }
+
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend(self.isa_version.to_be_bytes());
+        bytes.extend(self.entry.to_be_bytes());
+        bytes.extend((self.segments.len() as u16).to_be_bytes());
+        bytes.extend((sections.len() as u16).to_be_bytes());
+
+        for segment in &self.segments {
+            let len = segment.data.len();
+
+            if segment.addr as usize + len > crate::ADDRESS_SPACE_SIZE {
+                return Err(ExecutableError::SegmentOutOfRange {
+                    addr: segment.addr,
+                    len,
+                });
+            }
+
+            bytes.extend(segment.addr.to_be_bytes());
+            bytes.push(segment.permissions.bits());
+            bytes.extend((len as u32).to_be_bytes());
+            bytes.extend(&segment.data);
+        }
+
+        for (kind, data) in sections {
+            bytes.push(kind);
+            bytes.extend((data.len() as u32).to_be_bytes());
+            bytes.extend(data);
+        }
+
+        Ok(bytes)
+    }
+
+    /// Flat memory image of the segments from address 0, gaps between them being zeroed.
+    pub fn image(&self) -> Vec<u8> {
+        let mut image = vec![];
+        self.load_into(&mut image, |_| true);
+        image
+    }
+
+    /// Copy the segments matching the predicate over the memory image, e.g. only the executable ones to instruction
+    /// memory, growing it as needed.
+    pub fn load_into(&self, image: &mut Vec<u8>, predicate: impl Fn(&Segment) -> bool) {
+        for segment in self.segments.iter().filter(|segment| predicate(segment)) {
+            let start = segment.addr as usize;
+            let end = start + segment.data.len();
+
+            if image.len() < end {
+                image.resize(end, 0);
+            }
+
+            image[start..end].copy_from_slice(&segment.data);
+        }
+    }
+
+    pub fn symbol(&self, name: &str) -> Option<Word> {
+        self.symbols
+            .iter()
+            .find(|symbol| symbol.name == name)
+            .map(|symbol| symbol.addr)
+    }
+}
+
+impl Segment {
+    pub fn new(addr: Word, permissions: Permissions, data: Vec<u8>) -> Self {
+        Self {
+            addr,
+            permissions,
+            data,
+        }
+    }
+}
+
+impl Permissions {
+    pub const READ_EXECUTE: Self = Self {
+        read: true,
+        write: false,
+        execute: true,
+    };
+
+    pub const READ_WRITE: Self = Self {
+        read: true,
+        write: true,
+        execute: false,
+    };
+
+    pub fn bits(&self) -> u8 {
+        self.read as u8 | (self.write as u8) << 1 | (self.execute as u8) << 2
+    }
+
+    pub fn from_bits(bits: u8) -> Self {
+        Self {
+            read: bits & 0b001 != 0,
+            write: bits & 0b010 != 0,
+            execute: bits & 0b100 != 0,
+        }
+    }
+}
+
+impl Display for Permissions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (set, c) in [(self.read, 'r'), (self.write, 'w'), (self.execute, 'x')] {
+            write!(f, "{}", if set { c } else { '-' })?;
+        }
+
+        Ok(())
+    }
+}
+
+impl From<Truncated> for ExecutableError {
+    fn from(_: Truncated) -> Self {
+        Self::Truncated
+    }
+}
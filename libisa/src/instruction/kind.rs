@@ -1,4 +1,4 @@
-use std::fmt::Display;
+use std::{fmt::Display, str::FromStr};
 
 use bimap::BiMap;
 use lazy_static::lazy_static;
@@ -23,6 +23,38 @@ lazy_static! {
         (InstructionKind::StoreH, 24),
         (InstructionKind::StoreL, 25),
         (InstructionKind::Halt, 26),
+        (InstructionKind::AddI, 27),
+        (InstructionKind::SubI, 28),
+        (InstructionKind::AndI, 29),
+        (InstructionKind::JmpI, 30),
+        (InstructionKind::JmpCI, 31),
+        (InstructionKind::JmpZI, 32),
+        (InstructionKind::JmpR, 33),
+        (InstructionKind::JmpCR, 34),
+        (InstructionKind::JmpZR, 35),
+        (InstructionKind::LoadO, 36),
+        (InstructionKind::StoreO, 37),
+        (InstructionKind::LoadHO, 38),
+        (InstructionKind::LoadLO, 39),
+        (InstructionKind::StoreHO, 40),
+        (InstructionKind::StoreLO, 41),
+        (InstructionKind::JmpN, 42),
+        (InstructionKind::JmpO, 43),
+        (InstructionKind::JmpL, 44),
+        (InstructionKind::JmpNI, 45),
+        (InstructionKind::JmpOI, 46),
+        (InstructionKind::JmpLI, 47),
+        (InstructionKind::JmpNR, 48),
+        (InstructionKind::JmpOR, 49),
+        (InstructionKind::JmpLR, 50),
+        (InstructionKind::GetF, 51),
+        (InstructionKind::SetF, 52),
+        (InstructionKind::Cmp, 53),
+        (InstructionKind::CmpI, 54),
+        (InstructionKind::Sys, 55),
+        (InstructionKind::Sret, 56),
+        (InstructionKind::GetCr, 57),
+        (InstructionKind::SetCr, 58),
     ]);
 }
 
@@ -53,6 +85,55 @@ pub enum InstructionKind {
     StoreL,
 
     Halt,
+
+    AddI,
+    SubI,
+    AndI,
+
+    /// Jumps to the address in the immediate.
+    JmpI,
+    JmpCI,
+    JmpZI,
+
+    /// Jumps relative to the address of the next instruction, by the signed offset in the immediate.
+    JmpR,
+    JmpCR,
+    JmpZR,
+
+    /// Loads and stores addressing memory by the address register plus the immediate offset.
+    LoadO,
+    StoreO,
+    LoadHO,
+    LoadLO,
+    StoreHO,
+    StoreLO,
+
+    /// Jumps on the negative and overflow flags, and on signed less-than, i.e. negative differing from overflow.
+    JmpN,
+    JmpO,
+    JmpL,
+    JmpNI,
+    JmpOI,
+    JmpLI,
+    JmpNR,
+    JmpOR,
+    JmpLR,
+
+    /// Copy the ALU flags to and from a register, as the bits they have in the flags register.
+    GetF,
+    SetF,
+
+    /// Subtract only setting the flags, without writing the result.
+    Cmp,
+    CmpI,
+
+    /// Trap to supervisor mode for a system call, and return from a trap to the mode and address it came from.
+    Sys,
+    Sret,
+
+    /// Copy a control register, numbered by the immediate, to and from a register.
+    GetCr,
+    SetCr,
 }
 
 impl InstructionKind {
@@ -68,14 +149,59 @@ impl InstructionKind {
 
     pub const fn has_reg_a(&self) -> bool {
         match self {
-            Self::Nop | Self::Halt => false,
+            Self::Nop
+            | Self::Halt
+            | Self::Sys
+            | Self::Sret
+            | Self::JmpI
+            | Self::JmpCI
+            | Self::JmpZI
+            | Self::JmpR
+            | Self::JmpCR
+            | Self::JmpZR
+            | Self::JmpNI
+            | Self::JmpOI
+            | Self::JmpLI
+            | Self::JmpNR
+            | Self::JmpOR
+            | Self::JmpLR => false,
             _ => true,
         }
     }
 
     pub const fn has_reg_b(&self) -> bool {
         match self {
-            Self::Nop | Self::LoadI | Self::Jmp | Self::JmpC | Self::JmpZ | Self::Halt => false,
+            Self::Nop
+            | Self::LoadI
+            | Self::Jmp
+            | Self::JmpC
+            | Self::JmpZ
+            | Self::Halt
+            | Self::AddI
+            | Self::SubI
+            | Self::AndI
+            | Self::JmpI
+            | Self::JmpCI
+            | Self::JmpZI
+            | Self::JmpR
+            | Self::JmpCR
+            | Self::JmpZR
+            | Self::JmpN
+            | Self::JmpO
+            | Self::JmpL
+            | Self::JmpNI
+            | Self::JmpOI
+            | Self::JmpLI
+            | Self::JmpNR
+            | Self::JmpOR
+            | Self::JmpLR
+            | Self::GetF
+            | Self::SetF
+            | Self::CmpI
+            | Self::Sys
+            | Self::Sret
+            | Self::GetCr
+            | Self::SetCr => false,
             _ => true,
         }
     }
@@ -84,10 +210,160 @@ impl InstructionKind {
         match self {
             // I can't wait to debug for hours when I eventually add another
             // instruction with an immediate but forget to add it here.
-            Self::LoadI => true,
+            Self::LoadI
+            | Self::AddI
+            | Self::SubI
+            | Self::AndI
+            | Self::JmpI
+            | Self::JmpCI
+            | Self::JmpZI
+            | Self::JmpR
+            | Self::JmpCR
+            | Self::JmpZR
+            | Self::LoadO
+            | Self::StoreO
+            | Self::LoadHO
+            | Self::LoadLO
+            | Self::StoreHO
+            | Self::StoreLO
+            | Self::JmpNI
+            | Self::JmpOI
+            | Self::JmpLI
+            | Self::JmpNR
+            | Self::JmpOR
+            | Self::JmpLR
+            | Self::CmpI
+            | Self::GetCr
+            | Self::SetCr => true,
             _ => false,
         }
     }
+
+    pub const fn is_jump(&self) -> bool {
+        matches!(
+            self,
+            Self::Jmp
+                | Self::JmpC
+                | Self::JmpZ
+                | Self::JmpI
+                | Self::JmpCI
+                | Self::JmpZI
+                | Self::JmpR
+                | Self::JmpCR
+                | Self::JmpZR
+                | Self::JmpN
+                | Self::JmpO
+                | Self::JmpL
+                | Self::JmpNI
+                | Self::JmpOI
+                | Self::JmpLI
+                | Self::JmpNR
+                | Self::JmpOR
+                | Self::JmpLR
+        )
+    }
+
+    /// Whether the instruction is a jump that may also fall through to the next instruction.
+    pub const fn is_conditional_jump(&self) -> bool {
+        matches!(
+            self,
+            Self::JmpC
+                | Self::JmpZ
+                | Self::JmpCI
+                | Self::JmpZI
+                | Self::JmpCR
+                | Self::JmpZR
+                | Self::JmpN
+                | Self::JmpO
+                | Self::JmpL
+                | Self::JmpNI
+                | Self::JmpOI
+                | Self::JmpLI
+                | Self::JmpNR
+                | Self::JmpOR
+                | Self::JmpLR
+        )
+    }
+
+    /// Whether the instruction is a jump with its target relative to the address of the next instruction.
+    pub const fn is_relative_jump(&self) -> bool {
+        matches!(
+            self,
+            Self::JmpR | Self::JmpCR | Self::JmpZR | Self::JmpNR | Self::JmpOR | Self::JmpLR
+        )
+    }
+
+    /// Whether the instruction writes its result to the register A operand.
+    pub const fn writes_reg_a(&self) -> bool {
+        matches!(
+            self,
+            Self::LoadI
+                | Self::Load
+                | Self::Cpy
+                | Self::Add
+                | Self::Sub
+                | Self::AddC
+                | Self::SubC
+                | Self::And
+                | Self::LoadH
+                | Self::LoadL
+                | Self::AddI
+                | Self::SubI
+                | Self::AndI
+                | Self::LoadO
+                | Self::LoadHO
+                | Self::LoadLO
+                | Self::GetF
+                | Self::GetCr
+        )
+    }
+
+    /// Whether the instruction reads the register A operand, which loads only do if they merge a single byte into it.
+    pub const fn reads_reg_a(&self) -> bool {
+        self.has_reg_a()
+            && !matches!(
+                self,
+                Self::LoadI | Self::Load | Self::Cpy | Self::LoadO | Self::GetF | Self::GetCr
+            )
+    }
+
+    /// Whether the instruction reads or writes data memory, besides fetching itself.
+    pub const fn accesses_memory(&self) -> bool {
+        matches!(
+            self,
+            Self::Load
+                | Self::Store
+                | Self::LoadH
+                | Self::LoadL
+                | Self::StoreH
+                | Self::StoreL
+                | Self::LoadO
+                | Self::StoreO
+                | Self::LoadHO
+                | Self::LoadLO
+                | Self::StoreHO
+                | Self::StoreLO
+        )
+    }
+
+    /// Whether execution never goes on to the next instruction, the instruction stopping it or returning from a trap.
+    pub const fn stops_flow(&self) -> bool {
+        matches!(self, Self::Halt | Self::Sret)
+    }
+
+    /// Whether the instruction traps in user mode, only being allowed in supervisor mode.
+    pub const fn is_privileged(&self) -> bool {
+        matches!(self, Self::Halt | Self::Sret | Self::GetCr | Self::SetCr)
+    }
+
+    /// Length of the whole instruction in bytes, including the immediate word if the instruction has one.
+    pub const fn len_bytes(&self) -> usize {
+        if self.has_immediate() {
+            crate::BYTES_PER_WORD * 2
+        } else {
+            crate::BYTES_PER_WORD
+        }
+    }
 }
 
 impl Display for InstructionKind {
@@ -111,6 +387,51 @@ impl Display for InstructionKind {
             Self::StoreH => "storeh",
             Self::StoreL => "storel",
             Self::Halt => "halt",
+            Self::AddI => "addi",
+            Self::SubI => "subi",
+            Self::AndI => "andi",
+            Self::JmpI => "jmpi",
+            Self::JmpCI => "jmpci",
+            Self::JmpZI => "jmpzi",
+            Self::JmpR => "jmpr",
+            Self::JmpCR => "jmpcr",
+            Self::JmpZR => "jmpzr",
+            Self::LoadO => "loado",
+            Self::StoreO => "storeo",
+            Self::LoadHO => "loadho",
+            Self::LoadLO => "loadlo",
+            Self::StoreHO => "storeho",
+            Self::StoreLO => "storelo",
+            Self::JmpN => "jmpn",
+            Self::JmpO => "jmpo",
+            Self::JmpL => "jmpl",
+            Self::JmpNI => "jmpni",
+            Self::JmpOI => "jmpoi",
+            Self::JmpLI => "jmpli",
+            Self::JmpNR => "jmpnr",
+            Self::JmpOR => "jmpor",
+            Self::JmpLR => "jmplr",
+            Self::GetF => "getf",
+            Self::SetF => "setf",
+            Self::Cmp => "cmp",
+            Self::CmpI => "cmpi",
+            Self::Sys => "sys",
+            Self::Sret => "sret",
+            Self::GetCr => "getcr",
+            Self::SetCr => "setcr",
         })
     }
 }
+
+impl FromStr for InstructionKind {
+    type Err = String;
+
+    /// Parse the mnemonic of the instruction kind.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        KIND_OPCODE_BIMAP
+            .left_values()
+            .find(|kind| kind.to_string() == s)
+            .copied()
+            .ok_or_else(|| format!("Unknown instruction '{}'", s))
+    }
+}
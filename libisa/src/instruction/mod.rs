@@ -8,6 +8,9 @@ use crate::{Immediate, Register, Word};
 pub mod assembler;
 pub mod kind;
 
+#[cfg(test)]
+mod tests;
+
 #[derive(Debug, Error, Clone, Copy, PartialEq, Eq)]
 pub enum AssemblyError {
     #[error("Missing immediate")]
@@ -16,8 +19,8 @@ pub enum AssemblyError {
 
 #[derive(Debug, Error, Clone, Copy, PartialEq, Eq)]
 pub enum InstructionDeassemblyError {
-    #[error("Unrecognized opcode")]
-    UnrecognizedOpcode,
+    #[error("Unrecognized opcode {0}")]
+    UnrecognizedOpcode(usize),
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -53,13 +56,26 @@ impl Instruction {
         self
     }
 
+    /// Target of a jump with an immediate or relative target, given the address of the jump instruction itself.
+    pub fn static_jump_target(&self, addr: Word) -> Option<Word> {
+        let immediate = self.immediate.filter(|_| self.kind.is_jump())?;
+
+        Some(if self.kind.is_relative_jump() {
+            addr.wrapping_add(self.kind.len_bytes() as Word)
+                .wrapping_add(immediate)
+        } else {
+            immediate
+        })
+    }
+
+    /// The immediate of a relative jump at the given address to reach the target.
+    pub fn relative_jump_offset(kind: InstructionKind, addr: Word, target: Word) -> Immediate {
+        target.wrapping_sub(addr.wrapping_add(kind.len_bytes() as Word))
+    }
+
     pub fn assemble(self) -> Result<Vec<u8>, AssemblyError> {
         let has_immediate = self.kind.has_immediate();
-        let mut output = Vec::with_capacity(if has_immediate {
-            crate::BYTES_PER_WORD * 2
-        } else {
-            crate::BYTES_PER_WORD
-        });
+        let mut output = Vec::with_capacity(self.kind.len_bytes());
 
         output.extend(crate::word_to_bytes(
             (self.kind.opcode() << 10 | self.reg_a.unwrap_or(0) << 6 | self.reg_b.unwrap_or(0) << 2)
@@ -85,7 +101,7 @@ impl Instruction {
         ];
 
         let kind = InstructionKind::from_opcode(opcode)
-            .ok_or(InstructionDeassemblyError::UnrecognizedOpcode)?;
+            .ok_or(InstructionDeassemblyError::UnrecognizedOpcode(opcode))?;
 
         Ok(Self {
             kind,
@@ -98,19 +114,18 @@ impl Instruction {
 }
 
 impl Display for Instruction {
+    /// Customasm syntax, the operands following the mnemonic separated by commas.
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.write_fmt(format_args!("{}", self.kind))?;
 
-        if let Some(reg_a) = self.reg_a {
-            f.write_fmt(format_args!(" %{}", reg_a))?;
-        }
-
-        if let Some(reg_b) = self.reg_b {
-            f.write_fmt(format_args!(", %{}", reg_b))?;
-        }
+        let registers = [self.reg_a, self.reg_b].into_iter().flatten();
+        let operands = registers
+            .map(|reg| format!("%{}", reg))
+            .chain(self.immediate.map(|immediate| format!("${}", immediate)));
 
-        if let Some(immediate) = self.immediate {
-            f.write_fmt(format_args!(", ${}", immediate))?;
+        for (index, operand) in operands.enumerate() {
+            let separator = if index == 0 { " " } else { ", " };
+            f.write_fmt(format_args!("{}{}", separator, operand))?;
         }
 
         Ok(())
@@ -0,0 +1,31 @@
+use super::{kind::InstructionKind, Instruction};
+
+#[test]
+fn display_separates_operands_after_the_first() {
+    assert_eq!(
+        Instruction::new(InstructionKind::JmpI)
+            .with_immediate(0)
+            .to_string(),
+        "jmpi $0"
+    );
+    assert_eq!(
+        Instruction::new(InstructionKind::JmpZR)
+            .with_immediate(8)
+            .to_string(),
+        "jmpzr $8"
+    );
+    assert_eq!(
+        Instruction::new(InstructionKind::LoadI)
+            .with_reg_a(1)
+            .with_immediate(2)
+            .to_string(),
+        "loadi %1, $2"
+    );
+    assert_eq!(
+        Instruction::new(InstructionKind::Add)
+            .with_reg_a(1)
+            .with_reg_b(2)
+            .to_string(),
+        "add %1, %2"
+    );
+}
@@ -1,10 +1,23 @@
+pub mod executable;
 pub mod instruction;
+pub mod memimage;
+pub mod mmu;
+pub mod object;
+pub mod privilege;
+mod reader;
+pub mod timing;
+
+#[cfg(test)]
+mod tests;
 
 pub type Word = u16;
 pub type WordSigned = i16;
 
 pub const BYTES_PER_WORD: usize = 2;
 
+/// Bytes addressable by a word, from 0 up to and including `Word::MAX`.
+pub const ADDRESS_SPACE_SIZE: usize = Word::MAX as usize + 1;
+
 pub type Register = usize;
 pub type Immediate = Word;
 
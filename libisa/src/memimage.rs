@@ -0,0 +1,374 @@
+use std::{fmt::Display, path::Path, str::FromStr};
+
+use thiserror::Error;
+
+/// Text formats memory images are exchanged in with FPGA tooling, besides raw binaries.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ImageFormat {
+    /// The bytes as they are.
+    #[default]
+    Binary,
+
+    /// Intel HEX records, as taken by most vendor tools for initialising block RAM.
+    IntelHex,
+
+    /// A hexadecimal entry per line as read by Verilog's `$readmemh`, in `.mem` or `.hex` files.
+    Readmemh,
+}
+
+/// How the bytes of memory map to the entries of an image, which are the addressable units of the memory it
+/// initialises.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ImageLayout {
+    /// Bytes per entry, 1 for byte-oriented memories and 2 for memories of STRM1 words. Multi-byte entries are
+    /// big-endian like the instruction words.
+    pub width: usize,
+
+    /// Address of the first entry in the image, in entries. Byte 0 of memory ends up there.
+    pub base_addr: u32,
+
+    /// Bytes of memory imported images may fill, the logical address space by default.
+    pub limit: usize,
+}
+
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+pub enum ImageError {
+    #[error("Entries must be 1, 2 or 4 bytes wide, not {0}")]
+    UnsupportedWidth(usize),
+
+    #[error("Line {line}: {message}")]
+    Syntax { line: usize, message: String },
+
+    #[error("Line {0}: Checksum mismatch")]
+    Checksum(usize),
+
+    #[error("Entry at 0x{0:x} is below the base address")]
+    BelowBase(u32),
+
+    #[error("Image of {0} bytes doesn't fit in the address space of the format")]
+    TooLarge(usize),
+
+    #[error("Entry at 0x{addr:x} is beyond the {limit} bytes of memory")]
+    BeyondLimit { addr: u32, limit: usize },
+}
+
+/// Bytes of memory per Intel HEX data record.
+const INTEL_HEX_RECORD_BYTES: usize = 16;
+
+impl Default for ImageLayout {
+    fn default() -> Self {
+        Self {
+            width: crate::BYTES_PER_WORD,
+            base_addr: 0,
+            limit: crate::ADDRESS_SPACE_SIZE,
+        }
+    }
+}
+
+impl ImageLayout {
+    pub fn new(width: usize, base_addr: u32) -> Result<Self, ImageError> {
+        match width {
+            1 | 2 | 4 => Ok(Self {
+                width,
+                base_addr,
+                ..Default::default()
+            }),
+            _ => Err(ImageError::UnsupportedWidth(width)),
+        }
+    }
+
+    pub fn with_limit(mut self, limit: usize) -> Self {
+        self.limit = limit;
+        self
+    }
+}
+
+impl ImageFormat {
+    /// Format of the file going by its extension, `.mem` and `.hex` being taken as `$readmemh` files and `.ihex`
+    /// as Intel HEX.
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        match extension {
+            "bin" => Some(Self::Binary),
+            "ihex" | "ihx" => Some(Self::IntelHex),
+            "mem" | "hex" => Some(Self::Readmemh),
+            _ => None,
+        }
+    }
+
+    /// Format of the file at the path going by its extension, binary if it doesn't have a known one.
+    pub fn from_path(path: &Path) -> Self {
+        path.extension()
+            .and_then(|extension| Self::from_extension(&extension.to_string_lossy()))
+            .unwrap_or_default()
+    }
+}
+
+/// Write the memory contents from address 0 as an image of the format.
+pub fn export(
+    data: &[u8],
+    format: ImageFormat,
+    layout: ImageLayout,
+) -> Result<Vec<u8>, ImageError> {
+    ImageLayout::new(layout.width, layout.base_addr)?;
+
+    let mut entries: Vec<u32> = data
+        .chunks(layout.width)
+        .map(|chunk| {
+            // A partial last entry is padded with zeroes.
+            (0..layout.width).fold(0, |entry, index| {
+                entry << 8 | *chunk.get(index).unwrap_or(&0) as u32
+            })
+        })
+        .collect();
+
+    Ok(match format {
+        ImageFormat::Binary => data.to_vec(),
+        ImageFormat::Readmemh => {
+            let mut text = String::new();
+
+            if layout.base_addr != 0 {
+                text += &format!("@{:x}\n", layout.base_addr);
+            }
+
+            for entry in entries.drain(..) {
+                text += &format!("{:0digits$x}\n", entry, digits = layout.width * 2);
+            }
+
+            text.into_bytes()
+        }
+        ImageFormat::IntelHex => export_intel_hex(&entries, layout)?.into_bytes(),
+    })
+}
+
+fn export_intel_hex(entries: &[u32], layout: ImageLayout) -> Result<String, ImageError> {
+    let entries_per_record = INTEL_HEX_RECORD_BYTES / layout.width;
+    let mut text = String::new();
+    let mut upper_addr = 0;
+
+    for (record_index, record_entries) in entries.chunks(entries_per_record).enumerate() {
+        let addr = layout.base_addr as u64 + (record_index * entries_per_record) as u64;
+        let addr =
+            u32::try_from(addr).map_err(|_| ImageError::TooLarge(entries.len() * layout.width))?;
+
+        // Extended linear address records give the upper 16 bits of the addresses of the records following them.
+        if addr >> 16 != upper_addr {
+            upper_addr = addr >> 16;
+            text += &intel_hex_record(0x04, 0, &(upper_addr as u16).to_be_bytes());
+        }
+
+        let data: Vec<u8> = record_entries
+            .iter()
+            .flat_map(|entry| entry.to_be_bytes()[4 - layout.width..].to_vec())
+            .collect();
+
+        text += &intel_hex_record(0x00, addr as u16, &data);
+    }
+
+    text += &intel_hex_record(0x01, 0, &[]);
+    Ok(text)
+}
+
+fn intel_hex_record(kind: u8, addr: u16, data: &[u8]) -> String {
+    let mut bytes = vec![data.len() as u8];
+    bytes.extend(addr.to_be_bytes());
+    bytes.push(kind);
+    bytes.extend(data);
+
+    let checksum = bytes
+        .iter()
+        .fold(0u8, |sum, byte| sum.wrapping_add(*byte))
+        .wrapping_neg();
+    bytes.push(checksum);
+
+    let hex: String = bytes.iter().map(|byte| format!("{:02X}", byte)).collect();
+    format!(":{}\n", hex)
+}
+
+/// Read an image of the format back into the memory contents from address 0, gaps being zeroed. Entries past the
+/// limit of the layout are an error, binaries being taken as they are.
+pub fn import(
+    bytes: &[u8],
+    format: ImageFormat,
+    layout: ImageLayout,
+) -> Result<Vec<u8>, ImageError> {
+    ImageLayout::new(layout.width, layout.base_addr)?;
+
+    let entries = match format {
+        ImageFormat::Binary => return Ok(bytes.to_vec()),
+        ImageFormat::Readmemh => import_readmemh(&String::from_utf8_lossy(bytes), layout)?,
+        ImageFormat::IntelHex => import_intel_hex(&String::from_utf8_lossy(bytes), layout)?,
+    };
+
+    let mut data = vec![];
+
+    for (addr, entry) in entries {
+        let index = addr
+            .checked_sub(layout.base_addr)
+            .ok_or(ImageError::BelowBase(addr))? as usize
+            * layout.width;
+
+        // Checked before growing the data, as a single stray address could otherwise take up gigabytes.
+        if index + layout.width > layout.limit {
+            return Err(ImageError::BeyondLimit {
+                addr,
+                limit: layout.limit,
+            });
+        }
+
+        if data.len() < index + layout.width {
+            data.resize(index + layout.width, 0);
+        }
+
+        data[index..index + layout.width].copy_from_slice(&entry.to_be_bytes()[4 - layout.width..]);
+    }
+
+    Ok(data)
+}
+
+/// Whitespace separated tokens along with their line numbers, skipping comments.
+fn readmemh_tokens(text: &str) -> impl Iterator<Item = (usize, &str)> {
+    text.lines().enumerate().flat_map(|(index, line)| {
+        let line = line.split_once("//").map_or(line, |(code, _)| code);
+        line.split_whitespace().map(move |token| (index + 1, token))
+    })
+}
+
+fn import_readmemh(text: &str, layout: ImageLayout) -> Result<Vec<(u32, u32)>, ImageError> {
+    // None once the addresses run past the end of the 32-bit range.
+    let mut addr = Some(layout.base_addr);
+    let mut entries = vec![];
+
+    for (line, token) in readmemh_tokens(text) {
+        let parse = |digits: &str| {
+            u32::from_str_radix(&digits.replace('_', ""), 16).map_err(|_| ImageError::Syntax {
+                line,
+                message: format!("Expected a hexadecimal number, got '{}'", token),
+            })
+        };
+
+        match token.strip_prefix('@') {
+            Some(digits) => addr = Some(parse(digits)?),
+            None => {
+                let entry = parse(token)?;
+
+                if layout.width < 4 && entry >> (layout.width * 8) != 0 {
+                    return Err(ImageError::Syntax {
+                        line,
+                        message: format!("Entry '{}' is wider than {} bytes", token, layout.width),
+                    });
+                }
+
+                let entry_addr = addr.ok_or_else(|| ImageError::Syntax {
+                    line,
+                    message: format!("Entry '{}' is past address 0xffffffff", token),
+                })?;
+
+                entries.push((entry_addr, entry));
+                addr = entry_addr.checked_add(1);
+            }
+        }
+    }
+
+    Ok(entries)
+}
+
+fn import_intel_hex(text: &str, layout: ImageLayout) -> Result<Vec<(u32, u32)>, ImageError> {
+    let mut upper_addr: u32 = 0;
+    let mut entries = vec![];
+
+    for (index, record) in text.lines().enumerate() {
+        let line = index + 1;
+        let record = record.trim();
+
+        if record.is_empty() {
+            continue;
+        }
+
+        let syntax_err = |message: &str| ImageError::Syntax {
+            line,
+            message: message.to_owned(),
+        };
+
+        let hex = record
+            .strip_prefix(':')
+            .ok_or_else(|| syntax_err("Expected a record starting with ':'"))?;
+
+        let bytes = (0..hex.len())
+            .step_by(2)
+            .map(|index| {
+                hex.get(index..index + 2)
+                    .and_then(|byte| u8::from_str_radix(byte, 16).ok())
+            })
+            .collect::<Option<Vec<u8>>>()
+            .filter(|bytes| bytes.len() >= 5 && bytes.len() == bytes[0] as usize + 5)
+            .ok_or_else(|| syntax_err("Malformed record"))?;
+
+        if bytes.iter().fold(0u8, |sum, byte| sum.wrapping_add(*byte)) != 0 {
+            return Err(ImageError::Checksum(line));
+        }
+
+        let addr = u16::from_be_bytes([bytes[1], bytes[2]]) as u32;
+        let data = &bytes[4..bytes.len() - 1];
+
+        match bytes[3] {
+            0x00 => {
+                if data.len() % layout.width != 0 {
+                    return Err(syntax_err("Record data isn't a whole number of entries"));
+                }
+
+                let entry_data = data.chunks(layout.width).map(|entry| {
+                    entry
+                        .iter()
+                        .fold(0, |value, byte| value << 8 | *byte as u32)
+                });
+
+                for (index, entry) in entry_data.enumerate() {
+                    // The record's own address and index are at most 16 bits, only the sum with the upper bits
+                    // can overflow.
+                    let entry_addr = upper_addr
+                        .checked_add(addr + index as u32)
+                        .ok_or_else(|| syntax_err("Record data is past address 0xffffffff"))?;
+
+                    entries.push((entry_addr, entry));
+                }
+            }
+            0x01 => break,
+            0x02 if data.len() == 2 => {
+                upper_addr = (u16::from_be_bytes([data[0], data[1]]) as u32) << 4;
+            }
+            0x04 if data.len() == 2 => {
+                upper_addr = (u16::from_be_bytes([data[0], data[1]]) as u32) << 16;
+            }
+            0x03 | 0x05 => {} // Start addresses, irrelevant for memory contents.
+            _ => return Err(syntax_err("Unsupported record type")),
+        }
+    }
+
+    Ok(entries)
+}
+
+impl FromStr for ImageFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "bin" | "binary" => Ok(Self::Binary),
+            "ihex" => Ok(Self::IntelHex),
+            "mem" | "readmemh" => Ok(Self::Readmemh),
+            _ => Err(format!(
+                "Unknown image format '{}', expected bin, ihex or mem",
+                s
+            )),
+        }
+    }
+}
+
+impl Display for ImageFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Binary => "bin",
+            Self::IntelHex => "ihex",
+            Self::Readmemh => "mem",
+        })
+    }
+}
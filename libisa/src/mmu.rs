@@ -0,0 +1,93 @@
+use std::{fmt::Display, str::FromStr};
+
+use crate::Word;
+
+/// Bits of a logical address selecting its offset in the window, the bits above them selecting the window.
+pub const WINDOW_BITS: u32 = 12;
+
+/// Bytes per window of the logical address space, and per bank of physical memory.
+pub const WINDOW_SIZE: usize = 1 << WINDOW_BITS;
+
+pub const WINDOW_COUNT: usize = crate::ADDRESS_SPACE_SIZE / WINDOW_SIZE;
+
+/// Bank registers of the memory management unit, mapping each window of the 16-bit logical address space to a bank
+/// of a larger physical memory. Bank `n` starts at physical address `n * WINDOW_SIZE`, so the default identity
+/// mapping leaves addresses unchanged.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BankMap {
+    pub banks: [Word; WINDOW_COUNT],
+}
+
+impl Default for BankMap {
+    fn default() -> Self {
+        Self {
+            banks: std::array::from_fn(|window| window as Word),
+        }
+    }
+}
+
+impl BankMap {
+    /// Physical address the logical address maps to.
+    pub fn translate(&self, addr: Word) -> usize {
+        let window = addr as usize >> WINDOW_BITS;
+        let offset = addr as usize & (WINDOW_SIZE - 1);
+
+        (self.banks[window] as usize) << WINDOW_BITS | offset
+    }
+
+    /// The logical address space as seen through the mapping, taking the bytes from the physical memory image. Ends
+    /// at the last logical address mapped into the image.
+    pub fn logical_view(&self, physical: &[u8]) -> Vec<u8> {
+        let bytes: Vec<Option<u8>> = (0..=Word::MAX)
+            .map(|addr| physical.get(self.translate(addr)).copied())
+            .collect();
+
+        let len = bytes
+            .iter()
+            .rposition(Option::is_some)
+            .map_or(0, |last| last + 1);
+
+        bytes[..len].iter().map(|byte| byte.unwrap_or(0)).collect()
+    }
+}
+
+impl FromStr for BankMap {
+    type Err = String;
+
+    /// Banks of the windows from the first one on, separated by commas, in decimal or `0x` prefixed hexadecimal.
+    /// Windows left out keep their identity mapping.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut bank_map = Self::default();
+        let banks: Vec<&str> = s.split(',').map(str::trim).collect();
+
+        if banks.len() > WINDOW_COUNT {
+            return Err(format!(
+                "{} banks given for {} windows",
+                banks.len(),
+                WINDOW_COUNT
+            ));
+        }
+
+        for (window, bank) in banks.into_iter().enumerate() {
+            bank_map.banks[window] = match bank.strip_prefix("0x") {
+                Some(hex) => Word::from_str_radix(hex, 16),
+                None => bank.parse(),
+            }
+            .map_err(|_| format!("Invalid bank '{}'", bank))?;
+        }
+
+        Ok(bank_map)
+    }
+}
+
+impl Display for BankMap {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let banks: Vec<String> = self
+            .banks
+            .iter()
+            .map(|bank| format!("0x{:x}", bank))
+            .collect();
+
+        f.write_str(&banks.join(","))
+    }
+}
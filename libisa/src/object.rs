@@ -0,0 +1,374 @@
+use std::fmt::Display;
+
+use thiserror::Error;
+
+use crate::{
+    executable::ISA_VERSION,
+    reader::{Reader, Truncated},
+    Word,
+};
+
+/// Magic bytes every object file starts with.
+pub const MAGIC: [u8; 4] = *b"STRO";
+
+const UNDEFINED_SECTION: u16 = u16::MAX;
+
+/// Most sections, symbols or relocations an object file can hold, counts and indices being stored as u16 with
+/// 0xffff meaning an undefined section.
+pub const MAX_ENTRIES: usize = UNDEFINED_SECTION as usize - 1;
+
+/// Relocatable object file, a piece of a program assembled or compiled separately and combined with others by the
+/// linker. All fields are stored big-endian like the instruction words:
+///
+/// ```text
+/// magic               4 bytes, "STRO"
+/// isa_version         u16
+/// section_count       u16
+/// symbol_count        u16
+/// relocation_count    u16
+/// sections            name, len u32, data
+/// symbols             name, binding u8 (0 local, 1 global), section u16 (0xffff undefined), offset u16
+/// relocations         section u16, offset u16, kind u8 (0 absolute, 1 relative), symbol u16, addend u16
+/// ```
+///
+/// Names are stored as their length in bytes as a u8 followed by the UTF-8 name.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ObjectFile {
+    pub sections: Vec<Section>,
+    pub symbols: Vec<Symbol>,
+    pub relocations: Vec<Relocation>,
+}
+
+/// Code or data placed as a whole in the bank of the same name, e.g. `code` or `ram`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Section {
+    pub name: String,
+    pub data: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Binding {
+    /// Only visible to the relocations of the object defining it.
+    Local,
+
+    /// Visible to every object, and only allowed to be defined once among them.
+    Global,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Symbol {
+    pub name: String,
+    pub binding: Binding,
+
+    /// Section index and offset in it, `None` if the symbol is only referenced and defined by another object.
+    pub definition: Option<(usize, Word)>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RelocationKind {
+    /// The address of the symbol, e.g. for `loadi` or `jmpi`.
+    Absolute,
+
+    /// The address of the symbol relative to the next instruction, for relative jumps.
+    Relative,
+}
+
+/// Immediate of an instruction to be patched with the address of a symbol, plus the addend, once it is known.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Relocation {
+    pub section: usize,
+
+    /// Offset of the instruction in the section, not of its immediate word.
+    pub offset: Word,
+
+    pub kind: RelocationKind,
+    pub symbol: usize,
+    pub addend: Word,
+}
+
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+pub enum ObjectError {
+    #[error("Not a STRM1 object file")]
+    BadMagic,
+
+    #[error("Object file is for ISA version {0}, expected {ISA_VERSION}")]
+    UnsupportedVersion(u16),
+
+    #[error("Object file is truncated")]
+    Truncated,
+
+    #[error("Name '{0}' is longer than 255 bytes")]
+    NameTooLong(String),
+
+    #[error("Name isn't valid UTF-8")]
+    InvalidName,
+
+    #[error("Unknown symbol binding {0}")]
+    UnknownBinding(u8),
+
+    #[error("Unknown relocation kind {0}")]
+    UnknownRelocationKind(u8),
+
+    #[error("Reference to section {0}, which doesn't exist")]
+    NoSuchSection(usize),
+
+    #[error("Reference to symbol {0}, which doesn't exist")]
+    NoSuchSymbol(usize),
+
+    #[error("{count} {what} are more than the {MAX_ENTRIES} an object file can hold")]
+    TooMany { what: &'static str, count: usize },
+}
+
+impl ObjectFile {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a section, returning its index for defining symbols and relocations in it.
+    pub fn add_section(&mut self, name: impl Into<String>, data: Vec<u8>) -> usize {
+        self.sections.push(Section {
+            name: name.into(),
+            data,
+        });
+
+        self.sections.len() - 1
+    }
+
+    /// Add a symbol defined at the offset of the section, returning its index for relocations against it.
+    pub fn define_symbol(
+        &mut self,
+        name: impl Into<String>,
+        binding: Binding,
+        section: usize,
+        offset: Word,
+    ) -> usize {
+        self.push_symbol(Symbol {
+            name: name.into(),
+            binding,
+            definition: Some((section, offset)),
+        })
+    }
+
+    /// Add a symbol defined by another object, returning its index for relocations against it.
+    pub fn reference_symbol(&mut self, name: impl Into<String>) -> usize {
+        self.push_symbol(Symbol {
+            name: name.into(),
+            binding: Binding::Global,
+            definition: None,
+        })
+    }
+
+    fn push_symbol(&mut self, symbol: Symbol) -> usize {
+        self.symbols.push(symbol);
+        self.symbols.len() - 1
+    }
+
+    pub fn add_relocation(&mut self, relocation: Relocation) {
+        self.relocations.push(relocation);
+    }
+
+    /// Whether the bytes look like an object file.
+    pub fn is_object(bytes: &[u8]) -> bool {
+        bytes.starts_with(&MAGIC)
+    }
+
+    pub fn parse(bytes: &[u8]) -> Result<Self, ObjectError> {
+        let mut reader = Reader::<ObjectError>::new(bytes);
+
+        if reader.take(MAGIC.len())? != MAGIC {
+            return Err(ObjectError::BadMagic);
+        }
+
+        let isa_version = reader.u16()?;
+
+        if isa_version != ISA_VERSION {
+            return Err(ObjectError::UnsupportedVersion(isa_version));
+        }
+
+        let section_count = reader.u16()?;
+        let symbol_count = reader.u16()?;
+        let relocation_count = reader.u16()?;
+
+        let mut object = Self::new();
+
+        for _ in 0..section_count {
+            let name = reader.string()?.ok_or(ObjectError::InvalidName)?;
+            let len = reader.u32()? as usize;
+
+            object.add_section(name, reader.take(len)?.to_vec());
+        }
+
+        for _ in 0..symbol_count {
+            let name = reader.string()?.ok_or(ObjectError::InvalidName)?;
+
+            let binding = match reader.u8()? {
+                0 => Binding::Local,
+                1 => Binding::Global,
+                binding => return Err(ObjectError::UnknownBinding(binding)),
+            };
+
+            let section = reader.u16()?;
+            let offset = reader.u16()?;
+
+            object.push_symbol(Symbol {
+                name,
+                binding,
+                definition: (section != UNDEFINED_SECTION).then_some((section as usize, offset)),
+            });
+        }
+
+        for _ in 0..relocation_count {
+            let section = reader.u16()? as usize;
+            let offset = reader.u16()?;
+
+            let kind = match reader.u8()? {
+                0 => RelocationKind::Absolute,
+                1 => RelocationKind::Relative,
+                kind => return Err(ObjectError::UnknownRelocationKind(kind)),
+            };
+
+            object.add_relocation(Relocation {
+                section,
+                offset,
+                kind,
+                symbol: reader.u16()? as usize,
+                addend: reader.u16()?,
+            });
+        }
+
+        object.validate()?;
+        Ok(object)
+    }
+
+    /// Check that every section and symbol referenced exists, and that there aren't more of anything than the format
+    /// can count.
+    pub fn validate(&self) -> Result<(), ObjectError> {
+        let counts = [
+            ("sections", self.sections.len()),
+            ("symbols", self.symbols.len()),
+            ("relocations", self.relocations.len()),
+        ];
+
+        if let Some((what, count)) = counts.into_iter().find(|(_, count)| *count > MAX_ENTRIES) {
+            return Err(ObjectError::TooMany { what, count });
+        }
+
+        let symbol_sections = self
+            .symbols
+            .iter()
+            .filter_map(|symbol| symbol.definition.map(|(section, _)| section));
+        let relocation_sections = self.relocations.iter().map(|relocation| relocation.section);
+
+        if let Some(section) = symbol_sections
+            .chain(relocation_sections)
+            .find(|section| *section >= self.sections.len())
+        {
+            return Err(ObjectError::NoSuchSection(section));
+        }
+
+        if let Some(relocation) = self
+            .relocations
+            .iter()
+            .find(|relocation| relocation.symbol >= self.symbols.len())
+        {
+            return Err(ObjectError::NoSuchSymbol(relocation.symbol));
+        }
+
+        Ok(())
+    }
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>, ObjectError> {
+        self.validate()?;
+
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend(ISA_VERSION.to_be_bytes());
+        bytes.extend((self.sections.len() as u16).to_be_bytes());
+        bytes.extend((self.symbols.len() as u16).to_be_bytes());
+        bytes.extend((self.relocations.len() as u16).to_be_bytes());
+
+        for section in &self.sections {
+            Self::write_name(&mut bytes, &section.name)?;
+            bytes.extend((section.data.len() as u32).to_be_bytes());
+            bytes.extend(&section.data);
+        }
+
+        for symbol in &self.symbols {
+            let (section, offset) = symbol
+                .definition
+                .map_or((UNDEFINED_SECTION, 0), |(section, offset)| {
+                    (section as u16, offset)
+                });
+
+            Self::write_name(&mut bytes, &symbol.name)?;
+            bytes.push(match symbol.binding {
+                Binding::Local => 0,
+                Binding::Global => 1,
+            });
+            bytes.extend(section.to_be_bytes());
+            bytes.extend(offset.to_be_bytes());
+        }
+
+        for relocation in &self.relocations {
+            bytes.extend((relocation.section as u16).to_be_bytes());
+            bytes.extend(relocation.offset.to_be_bytes());
+            bytes.push(match relocation.kind {
+                RelocationKind::Absolute => 0,
+                RelocationKind::Relative => 1,
+            });
+            bytes.extend((relocation.symbol as u16).to_be_bytes());
+            bytes.extend(relocation.addend.to_be_bytes());
+        }
+
+        Ok(bytes)
+    }
+
+    fn write_name(bytes: &mut Vec<u8>, name: &str) -> Result<(), ObjectError> {
+        let len =
+            u8::try_from(name.len()).map_err(|_| ObjectError::NameTooLong(name.to_owned()))?;
+
+        bytes.push(len);
+        bytes.extend(name.as_bytes());
+        Ok(())
+    }
+}
+
+impl Relocation {
+    /// Absolute relocation of the immediate of the instruction at the offset, e.g. a `loadi`.
+    pub fn absolute(section: usize, offset: Word, symbol: usize) -> Self {
+        Self {
+            section,
+            offset,
+            kind: RelocationKind::Absolute,
+            symbol,
+            addend: 0,
+        }
+    }
+
+    /// Relative relocation of the immediate of the relative jump at the offset.
+    pub fn relative(section: usize, offset: Word, symbol: usize) -> Self {
+        Self {
+            kind: RelocationKind::Relative,
+            ..Self::absolute(section, offset, symbol)
+        }
+    }
+
+    pub fn with_addend(mut self, addend: Word) -> Self {
+        self.addend = addend;
+        self
+    }
+}
+
+impl From<Truncated> for ObjectError {
+    fn from(_: Truncated) -> Self {
+        Self::Truncated
+    }
+}
+
+impl Display for Binding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Local => "local",
+            Self::Global => "global",
+        })
+    }
+}
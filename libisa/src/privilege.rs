@@ -0,0 +1,82 @@
+use std::fmt::Display;
+
+use crate::Word;
+
+/// Privilege level the processor runs at. It resets to supervisor mode, where everything is allowed. User mode can't
+/// run privileged instructions or access the protected region, and doing either traps back to supervisor mode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Mode {
+    #[default]
+    Supervisor,
+    User,
+}
+
+/// Control registers read with `getcr` and written with `setcr`, which are both privileged, by their number.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u16)]
+pub enum ControlRegister {
+    /// Bit 0 is set if the last trap came from user mode, which is the mode `sret` returns to.
+    Status = 0,
+
+    /// Address `sret` returns to. The faulting instruction for faults, the one after `sys` for system calls.
+    Epc = 1,
+
+    /// [`TrapCause`] of the last trap.
+    Cause = 2,
+
+    /// Address of the trap handler.
+    TrapVector = 3,
+
+    /// Address whose access caused the last protection fault.
+    FaultAddr = 4,
+
+    /// Start and end of the protected region, in logical addresses with the end excluded. Empty if the end isn't
+    /// past the start.
+    ProtectStart = 5,
+    ProtectEnd = 6,
+}
+
+/// Number of control registers.
+pub const CONTROL_REGISTER_COUNT: usize = 7;
+
+/// Bit of the status register set if the last trap came from user mode.
+pub const STATUS_PREVIOUS_USER: Word = 1;
+
+/// Why the processor trapped to supervisor mode, as found in the cause register.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u16)]
+pub enum TrapCause {
+    /// `sys` was executed.
+    SystemCall = 1,
+
+    /// A privileged instruction was executed in user mode.
+    PrivilegedInstruction = 2,
+
+    /// User mode fetched from, loaded from or stored to the protected region, or accessed the registers of the MMU or
+    /// another device.
+    ProtectionFault = 3,
+}
+
+impl ControlRegister {
+    pub fn from_number(number: Word) -> Option<Self> {
+        Some(match number {
+            0 => Self::Status,
+            1 => Self::Epc,
+            2 => Self::Cause,
+            3 => Self::TrapVector,
+            4 => Self::FaultAddr,
+            5 => Self::ProtectStart,
+            6 => Self::ProtectEnd,
+            _ => return None,
+        })
+    }
+}
+
+impl Display for Mode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Supervisor => "supervisor",
+            Self::User => "user",
+        })
+    }
+}
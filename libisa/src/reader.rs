@@ -0,0 +1,53 @@
+use std::marker::PhantomData;
+
+/// End of data reached while reading, converted to the error of whichever format is being parsed.
+pub(crate) struct Truncated;
+
+/// Cursor over the big-endian fields of the binary container formats, failing with the error of the format.
+pub(crate) struct Reader<'a, E> {
+    bytes: &'a [u8],
+    pos: usize,
+    error: PhantomData<E>,
+}
+
+impl<'a, E> Reader<'a, E>
+where
+    E: From<Truncated>,
+{
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Self {
+            bytes,
+            pos: 0,
+            error: PhantomData,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pos >= self.bytes.len()
+    }
+
+    pub fn take(&mut self, len: usize) -> Result<&'a [u8], E> {
+        let taken = self.bytes.get(self.pos..self.pos + len).ok_or(Truncated)?;
+
+        self.pos += len;
+        Ok(taken)
+    }
+
+    pub fn u8(&mut self) -> Result<u8, E> {
+        Ok(self.take(1)?[0])
+    }
+
+    pub fn u16(&mut self) -> Result<u16, E> {
+        Ok(u16::from_be_bytes(self.take(2)?.try_into().unwrap()))
+    }
+
+    pub fn u32(&mut self) -> Result<u32, E> {
+        Ok(u32::from_be_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    /// String prefixed by its length in bytes as a u8, `None` if it isn't valid UTF-8.
+    pub fn string(&mut self) -> Result<Option<String>, E> {
+        let len = self.u8()? as usize;
+        Ok(String::from_utf8(self.take(len)?.to_vec()).ok())
+    }
+}
@@ -0,0 +1,64 @@
+use crate::executable::{Executable, ExecutableError, Permissions, Segment, ISA_VERSION, MAGIC};
+
+fn executable() -> Executable {
+    Executable::new(0x100)
+        .with_segment(Segment::new(
+            0x100,
+            Permissions::READ_EXECUTE,
+            vec![0x12, 0x34, 0x56, 0x78],
+        ))
+        .with_segment(Segment::new(
+            0x8000,
+            Permissions::READ_WRITE,
+            vec![0xBE, 0xEF],
+        ))
+        .with_symbol("start", 0x100)
+        .with_debug(vec![1, 2, 3])
+}
+
+#[test]
+fn executables_round_trip() -> Result<(), ExecutableError> {
+    let executable = executable();
+
+    assert_eq!(Executable::parse(&executable.to_bytes()?)?, executable);
+    Ok(())
+}
+
+#[test]
+fn other_magic_is_rejected() -> Result<(), ExecutableError> {
+    let mut bytes = executable().to_bytes()?;
+    assert!(bytes.starts_with(&MAGIC));
+
+    bytes[..4].copy_from_slice(b"STRO");
+
+    assert_eq!(Executable::parse(&bytes), Err(ExecutableError::BadMagic));
+    Ok(())
+}
+
+#[test]
+fn other_isa_versions_are_rejected() -> Result<(), ExecutableError> {
+    let mut bytes = executable().to_bytes()?;
+    bytes[4..6].copy_from_slice(&(ISA_VERSION + 1).to_be_bytes());
+
+    assert_eq!(
+        Executable::parse(&bytes),
+        Err(ExecutableError::UnsupportedVersion(ISA_VERSION + 1))
+    );
+    Ok(())
+}
+
+#[test]
+fn truncated_executables_are_rejected() -> Result<(), ExecutableError> {
+    let bytes = executable().to_bytes()?;
+
+    // Cut anywhere, including inside the magic, which isn't taken for a different format.
+    for len in 0..bytes.len() {
+        assert_eq!(
+            Executable::parse(&bytes[..len]),
+            Err(ExecutableError::Truncated),
+            "cut at {len} bytes"
+        );
+    }
+
+    Ok(())
+}
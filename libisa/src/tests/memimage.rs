@@ -0,0 +1,136 @@
+use crate::memimage::{self, ImageError, ImageFormat, ImageLayout};
+
+/// Intel HEX record of the type at the address, with its checksum.
+fn intel_hex_record(record_type: u8, addr: u16, data: &[u8]) -> String {
+    let mut bytes = vec![data.len() as u8];
+    bytes.extend(addr.to_be_bytes());
+    bytes.push(record_type);
+    bytes.extend(data);
+
+    let checksum = bytes
+        .iter()
+        .fold(0u8, |sum, byte| sum.wrapping_add(*byte))
+        .wrapping_neg();
+    bytes.push(checksum);
+
+    let hex: String = bytes.iter().map(|byte| format!("{:02X}", byte)).collect();
+    format!(":{}\n", hex)
+}
+
+#[test]
+fn images_round_trip() -> Result<(), ImageError> {
+    // Long enough for several Intel HEX records, and not a whole number of 4-byte entries.
+    let data: Vec<u8> = (0..=40).collect();
+
+    for format in [
+        ImageFormat::Binary,
+        ImageFormat::IntelHex,
+        ImageFormat::Readmemh,
+    ] {
+        for width in [1, 2, 4] {
+            // Base addresses past 16 bits make Intel HEX images start with an extended address record.
+            for base_addr in [0, 0x12345] {
+                let layout = ImageLayout::new(width, base_addr)?;
+                let mut expected = data.clone();
+
+                if format != ImageFormat::Binary {
+                    expected.resize(data.len().next_multiple_of(width), 0);
+                }
+
+                let image = memimage::export(&data, format, layout)?;
+                assert_eq!(
+                    memimage::import(&image, format, layout)?,
+                    expected,
+                    "{format} of {width}-byte entries at 0x{base_addr:x}"
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn intel_hex_checksum_mismatches_are_rejected() {
+    let mut image = [
+        intel_hex_record(0x00, 0, &[0x12, 0x34]),
+        intel_hex_record(0x01, 0, &[]),
+    ]
+    .concat();
+
+    // The second data byte, 0x34.
+    image.replace_range(11..13, "35");
+
+    assert_eq!(
+        memimage::import(
+            image.as_bytes(),
+            ImageFormat::IntelHex,
+            ImageLayout::default()
+        ),
+        Err(ImageError::Checksum(1))
+    );
+}
+
+#[test]
+fn truncated_intel_hex_records_are_rejected() {
+    let record = intel_hex_record(0x00, 0, &[0x12, 0x34]);
+    let record = record.trim_end();
+
+    for len in 1..record.len() {
+        assert!(
+            matches!(
+                memimage::import(
+                    &record.as_bytes()[..len],
+                    ImageFormat::IntelHex,
+                    ImageLayout::default()
+                ),
+                Err(ImageError::Syntax { line: 1, .. })
+            ),
+            "cut at {len} characters"
+        );
+    }
+}
+
+#[test]
+fn intel_hex_data_past_32_bit_addresses_is_a_syntax_error() {
+    let image = [
+        intel_hex_record(0x04, 0, &[0xff, 0xff]),
+        intel_hex_record(0x00, 0xffff, &[0x12, 0x34, 0x56, 0x78]),
+        intel_hex_record(0x01, 0, &[]),
+    ]
+    .concat();
+
+    assert!(matches!(
+        memimage::import(
+            image.as_bytes(),
+            ImageFormat::IntelHex,
+            ImageLayout::default()
+        ),
+        Err(ImageError::Syntax { line: 2, .. })
+    ));
+}
+
+#[test]
+fn readmemh_entries_past_32_bit_addresses_are_a_syntax_error() {
+    assert!(matches!(
+        memimage::import(
+            b"@FFFFFFFF 1234 5678",
+            ImageFormat::Readmemh,
+            ImageLayout::default()
+        ),
+        Err(ImageError::Syntax { line: 1, .. })
+    ));
+
+    // The last address itself is fine, just beyond the memory.
+    assert!(matches!(
+        memimage::import(
+            b"@FFFFFFFF 1234",
+            ImageFormat::Readmemh,
+            ImageLayout::default()
+        ),
+        Err(ImageError::BeyondLimit {
+            addr: 0xffffffff,
+            ..
+        })
+    ));
+}
@@ -0,0 +1,3 @@
+mod executable;
+mod memimage;
+mod object;
@@ -0,0 +1,90 @@
+use crate::{
+    executable::ISA_VERSION,
+    object::{Binding, ObjectError, ObjectFile, Relocation, MAGIC, MAX_ENTRIES},
+};
+
+fn object() -> ObjectFile {
+    let mut object = ObjectFile::new();
+
+    let code = object.add_section("code", vec![0x12, 0x34, 0x56, 0x78]);
+    let ram = object.add_section("ram", vec![0; 4]);
+
+    object.define_symbol("main", Binding::Global, code, 0);
+    let counter = object.define_symbol("counter", Binding::Local, ram, 2);
+    let external = object.reference_symbol("external");
+
+    object.add_relocation(Relocation::absolute(code, 0, counter));
+    object.add_relocation(Relocation::relative(code, 2, external).with_addend(4));
+    object
+}
+
+#[test]
+fn objects_round_trip() -> Result<(), ObjectError> {
+    let object = object();
+
+    assert_eq!(ObjectFile::parse(&object.to_bytes()?)?, object);
+    Ok(())
+}
+
+#[test]
+fn other_magic_is_rejected() -> Result<(), ObjectError> {
+    let mut bytes = object().to_bytes()?;
+    assert!(ObjectFile::is_object(&bytes));
+
+    bytes[..4].copy_from_slice(b"STRM");
+
+    assert!(!ObjectFile::is_object(&bytes));
+    assert_eq!(ObjectFile::parse(&bytes), Err(ObjectError::BadMagic));
+    Ok(())
+}
+
+#[test]
+fn other_isa_versions_are_rejected() -> Result<(), ObjectError> {
+    let mut bytes = object().to_bytes()?;
+    assert_eq!(bytes[..4], MAGIC);
+
+    bytes[4..6].copy_from_slice(&(ISA_VERSION + 1).to_be_bytes());
+
+    assert_eq!(
+        ObjectFile::parse(&bytes),
+        Err(ObjectError::UnsupportedVersion(ISA_VERSION + 1))
+    );
+    Ok(())
+}
+
+#[test]
+fn truncated_objects_are_rejected() -> Result<(), ObjectError> {
+    let bytes = object().to_bytes()?;
+
+    for len in 0..bytes.len() {
+        assert_eq!(
+            ObjectFile::parse(&bytes[..len]),
+            Err(ObjectError::Truncated),
+            "cut at {len} bytes"
+        );
+    }
+
+    Ok(())
+}
+
+#[test]
+fn counts_past_the_format_are_rejected() {
+    let mut object = ObjectFile::new();
+
+    for _ in 0..MAX_ENTRIES {
+        object.reference_symbol("external");
+    }
+
+    assert!(object.to_bytes().is_ok());
+
+    // The next index would be written as the undefined section marker.
+    object.reference_symbol("external");
+
+    assert_eq!(
+        object.to_bytes(),
+        Err(ObjectError::TooMany {
+            what: "symbols",
+            count: MAX_ENTRIES + 1
+        })
+    );
+}
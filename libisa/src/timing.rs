@@ -0,0 +1,151 @@
+use std::{collections::HashMap, fmt::Display, str::FromStr};
+
+use crate::instruction::kind::InstructionKind;
+
+/// Cycle costs of the instructions on some microarchitecture, for performance estimates of programs.
+///
+/// An instruction costs the cycles of fetching its words and executing it, plus the memory access cycles if it
+/// accesses data memory and the taken branch cycles if it jumps.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CycleProfile {
+    /// Name of the microarchitecture, or `custom` once any of its costs are overridden.
+    name: &'static str,
+
+    /// Cycles per fetched instruction word, the immediate word included.
+    pub fetch_cycles: u32,
+
+    /// Cycles executing any instruction without an override.
+    pub execute_cycles: u32,
+    execute_overrides: HashMap<InstructionKind, u32>,
+
+    pub memory_access_cycles: u32,
+    pub taken_branch_cycles: u32,
+}
+
+impl CycleProfile {
+    /// Every instruction takes a single cycle, no matter what it does.
+    pub fn ideal() -> Self {
+        Self {
+            name: "ideal",
+            fetch_cycles: 0,
+            execute_cycles: 1,
+            execute_overrides: HashMap::new(),
+            memory_access_cycles: 0,
+            taken_branch_cycles: 0,
+        }
+    }
+
+    /// Unpipelined core fetching a word per cycle from single-port memory, which data accesses have to wait for.
+    pub fn multicycle() -> Self {
+        Self {
+            name: "multicycle",
+            fetch_cycles: 1,
+            execute_cycles: 1,
+            execute_overrides: HashMap::from_iter([
+                (InstructionKind::Nop, 0),
+                (InstructionKind::Halt, 0),
+            ]),
+            memory_access_cycles: 1,
+            taken_branch_cycles: 1,
+        }
+    }
+
+    pub fn with_fetch_cycles(mut self, cycles: u32) -> Self {
+        self.fetch_cycles = cycles;
+        self.customized()
+    }
+
+    /// Set the cycles executing any instruction without an override.
+    pub fn with_default_execute_cycles(mut self, cycles: u32) -> Self {
+        self.execute_cycles = cycles;
+        self.customized()
+    }
+
+    pub fn with_execute_cycles(mut self, kind: InstructionKind, cycles: u32) -> Self {
+        self.execute_overrides.insert(kind, cycles);
+        self.customized()
+    }
+
+    pub fn with_memory_access_cycles(mut self, cycles: u32) -> Self {
+        self.memory_access_cycles = cycles;
+        self.customized()
+    }
+
+    pub fn with_taken_branch_cycles(mut self, cycles: u32) -> Self {
+        self.taken_branch_cycles = cycles;
+        self.customized()
+    }
+
+    /// The costs no longer being those of the named microarchitecture.
+    fn customized(mut self) -> Self {
+        self.name = "custom";
+        self
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Cycles of fetching and executing the instruction, without the memory access or taken branch costs.
+    pub fn instruction_cycles(&self, kind: InstructionKind) -> u32 {
+        let words = (kind.len_bytes() / crate::BYTES_PER_WORD) as u32;
+        let execute = self
+            .execute_overrides
+            .get(&kind)
+            .copied()
+            .unwrap_or(self.execute_cycles);
+
+        self.fetch_cycles * words + execute
+    }
+}
+
+impl Default for CycleProfile {
+    fn default() -> Self {
+        Self::multicycle()
+    }
+}
+
+impl FromStr for CycleProfile {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ideal" => Ok(Self::ideal()),
+            "multicycle" => Ok(Self::multicycle()),
+            _ => Err(format!(
+                "Unknown cycle profile '{}', expected ideal or multicycle",
+                s
+            )),
+        }
+    }
+}
+
+/// Cycles executing one kind of instruction, overriding the profile's, parsed from KIND=CYCLES with the mnemonic.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExecuteCycles {
+    pub kind: InstructionKind,
+    pub cycles: u32,
+}
+
+impl FromStr for ExecuteCycles {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (kind, cycles) = s
+            .split_once('=')
+            .ok_or_else(|| format!("Expected KIND=CYCLES, e.g. load=2, got '{}'", s))?;
+
+        Ok(Self {
+            kind: kind.parse()?,
+            cycles: cycles
+                .parse()
+                .map_err(|_| format!("Invalid cycle count '{}'", cycles))?,
+        })
+    }
+}
+
+impl Display for CycleProfile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name)
+    }
+}
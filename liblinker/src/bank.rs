@@ -0,0 +1,171 @@
+use std::str::FromStr;
+
+use thiserror::Error;
+
+/// Range of addresses sections get placed in one after another, as defined by a customasm `#bankdef`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Bank {
+    pub name: String,
+    pub addr: usize,
+
+    /// Address right after the last one in the bank.
+    pub addr_end: usize,
+
+    /// Byte offset of the bank's contents in the output image, `None` if the bank isn't output, e.g. RAM.
+    pub outp: Option<usize>,
+
+    /// Whether the bank is padded to its full size in the output image.
+    pub fill: bool,
+}
+
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+pub enum BankError {
+    #[error("Line {line}: {message}")]
+    Syntax { line: usize, message: String },
+
+    #[error("Bank '{0}' has no #addr")]
+    MissingAddr(String),
+
+    #[error("Bank '{0}' has neither #size nor #addr_end")]
+    MissingSize(String),
+
+    #[error("Bank '{bank}' has {bits} bit words, only 8 bit banks are supported")]
+    UnsupportedBits { bank: String, bits: usize },
+
+    #[error("No banks defined")]
+    NoBanks,
+}
+
+/// Banks of a customasm source like `banks.asm`, everything besides the `#bankdef` blocks being ignored.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BankDescription {
+    pub banks: Vec<Bank>,
+}
+
+impl BankDescription {
+    pub fn bank(&self, name: &str) -> Option<&Bank> {
+        self.banks.iter().find(|bank| bank.name == name)
+    }
+}
+
+impl Bank {
+    pub fn size(&self) -> usize {
+        self.addr_end.saturating_sub(self.addr)
+    }
+}
+
+impl FromStr for BankDescription {
+    type Err = BankError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut banks = vec![];
+        let mut lines = s
+            .lines()
+            .enumerate()
+            .map(|(index, line)| (index + 1, strip_comment(line).trim()));
+
+        while let Some((line, text)) = lines.next() {
+            let Some(header) = text.strip_prefix("#bankdef") else {
+                continue;
+            };
+
+            let syntax_err = |message: &str| BankError::Syntax {
+                line,
+                message: message.to_owned(),
+            };
+
+            let name = header
+                .trim()
+                .strip_suffix('{')
+                .map(str::trim)
+                .filter(|name| !name.is_empty())
+                .ok_or_else(|| syntax_err("Expected #bankdef NAME {"))?
+                .to_owned();
+
+            let mut addr = None;
+            let mut addr_end = None;
+            let mut size = None;
+            let mut outp = None;
+            let mut bits = 8;
+            let mut fill = false;
+
+            loop {
+                let (line, text) = lines
+                    .next()
+                    .ok_or_else(|| syntax_err("Unclosed #bankdef"))?;
+
+                if text == "}" {
+                    break;
+                }
+
+                if text.is_empty() {
+                    continue;
+                }
+
+                let (directive, value) = text.split_once(char::is_whitespace).unwrap_or((text, ""));
+                let value = || {
+                    evaluate(value.trim()).ok_or_else(|| BankError::Syntax {
+                        line,
+                        message: format!("Can't evaluate '{}'", value.trim()),
+                    })
+                };
+
+                match directive {
+                    "#addr" => addr = Some(value()?),
+                    "#addr_end" => addr_end = Some(value()?),
+                    "#size" => size = Some(value()?),
+                    "#outp" => outp = Some(value()? / 8),
+                    "#bits" => bits = value()?,
+                    "#fill" => fill = true,
+                    _ => {} // Irrelevant for linking, e.g. #labelalign.
+                }
+            }
+
+            if bits != 8 {
+                return Err(BankError::UnsupportedBits { bank: name, bits });
+            }
+
+            let addr = addr.ok_or_else(|| BankError::MissingAddr(name.clone()))?;
+            let addr_end = addr_end
+                .or(size.map(|size| addr + size))
+                .ok_or_else(|| BankError::MissingSize(name.clone()))?;
+
+            banks.push(Bank {
+                name,
+                addr,
+                addr_end,
+                outp,
+                fill,
+            });
+        }
+
+        if banks.is_empty() {
+            return Err(BankError::NoBanks);
+        }
+
+        Ok(Self { banks })
+    }
+}
+
+fn strip_comment(line: &str) -> &str {
+    line.split_once(';').map_or(line, |(code, _)| code)
+}
+
+/// Evaluate sums of products of decimal or 0x prefixed hexadecimal numbers, like `0*8` or `0x400 + 2*512`.
+fn evaluate(expression: &str) -> Option<usize> {
+    expression
+        .split('+')
+        .map(|term| {
+            term.split('*')
+                .map(|factor| {
+                    let factor = factor.trim();
+
+                    match factor.strip_prefix("0x") {
+                        Some(hex) => usize::from_str_radix(hex, 16).ok(),
+                        None => factor.parse().ok(),
+                    }
+                })
+                .product::<Option<usize>>()
+        })
+        .sum()
+}
@@ -0,0 +1,452 @@
+use std::{
+    collections::{hash_map::Entry, HashMap, HashSet},
+    fmt::Display,
+};
+
+use bank::{Bank, BankDescription};
+use libisa::{
+    executable::{Executable, Permissions, Segment},
+    instruction::Instruction,
+    object::{Binding, ObjectFile, RelocationKind},
+    Word,
+};
+use thiserror::Error;
+
+pub mod bank;
+
+#[cfg(test)]
+mod tests;
+
+/// Symbol the entry point of executables is taken from, the start of the first bank if it isn't defined.
+pub const ENTRY_SYMBOL: &str = "_start";
+
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+pub enum LinkError {
+    #[error("Section '{section}' of '{object}' has no bank of the same name to go in")]
+    UnknownBank { object: String, section: String },
+
+    #[error("Bank '{bank}' overflows by {overflow} bytes")]
+    BankOverflow { bank: String, overflow: usize },
+
+    #[error("Symbol '{name}' is defined in both '{first}' and '{second}'")]
+    DuplicateSymbol {
+        name: String,
+        first: String,
+        second: String,
+    },
+
+    #[error("Undefined symbol '{name}' referenced in '{object}'")]
+    UndefinedSymbol { name: String, object: String },
+
+    #[error(
+        "Relocation at 0x{offset:04x} of section '{section}' in '{object}' isn't on {expected}"
+    )]
+    BadRelocationTarget {
+        object: String,
+        section: String,
+        offset: Word,
+        expected: &'static str,
+    },
+}
+
+/// Every problem found while linking, so they can be fixed all at once.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LinkErrors(pub Vec<LinkError>);
+
+/// Combines object files into a single program, placing their sections in the banks of the same name one after
+/// another in the order the objects were added, and patching the relocated immediates with the symbol addresses.
+#[derive(Debug, Clone)]
+pub struct Linker {
+    banks: BankDescription,
+    objects: Vec<(String, ObjectFile)>,
+}
+
+/// Where a section of an object ended up.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Placement {
+    pub object: String,
+    pub section: String,
+    pub addr: Word,
+    pub len: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MapSymbol {
+    pub name: String,
+    pub addr: Word,
+    pub binding: Binding,
+    pub object: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LinkOutput {
+    /// Banks along with their contents from their first address, only as long as the sections placed in them.
+    pub banks: Vec<(Bank, Vec<u8>)>,
+    pub placements: Vec<Placement>,
+
+    /// Every defined symbol, sorted by address.
+    pub symbols: Vec<MapSymbol>,
+}
+
+impl Linker {
+    pub fn new(banks: BankDescription) -> Self {
+        Self {
+            banks,
+            objects: vec![],
+        }
+    }
+
+    /// Add an object, named for error messages and the symbol map, e.g. by its path.
+    pub fn with_object(mut self, name: impl Into<String>, object: ObjectFile) -> Self {
+        self.objects.push((name.into(), object));
+        self
+    }
+
+    pub fn link(&self) -> Result<LinkOutput, LinkErrors> {
+        let mut errors = vec![];
+
+        let mut contents: Vec<Vec<u8>> = vec![vec![]; self.banks.banks.len()];
+        let mut placements = vec![];
+
+        // Address of every section of every object.
+        let mut section_addrs: Vec<Vec<Option<Word>>> = vec![];
+
+        for (object_name, object) in &self.objects {
+            let mut object_section_addrs = vec![];
+
+            for section in &object.sections {
+                let Some(bank_index) = self
+                    .banks
+                    .banks
+                    .iter()
+                    .position(|bank| bank.name == section.name)
+                else {
+                    errors.push(LinkError::UnknownBank {
+                        object: object_name.clone(),
+                        section: section.name.clone(),
+                    });
+                    object_section_addrs.push(None);
+                    continue;
+                };
+
+                let bank = &self.banks.banks[bank_index];
+                let bank_contents = &mut contents[bank_index];
+
+                // Sections start word aligned, as they are mostly made of instructions.
+                bank_contents.resize(
+                    bank_contents.len().next_multiple_of(libisa::BYTES_PER_WORD),
+                    0,
+                );
+
+                let addr = bank.addr + bank_contents.len();
+                bank_contents.extend(&section.data);
+
+                placements.push(Placement {
+                    object: object_name.clone(),
+                    section: section.name.clone(),
+                    addr: addr as Word,
+                    len: section.data.len(),
+                });
+                object_section_addrs.push(Some(addr as Word));
+            }
+
+            section_addrs.push(object_section_addrs);
+        }
+
+        for (bank, bank_contents) in self.banks.banks.iter().zip(&contents) {
+            if bank_contents.len() > bank.size() {
+                errors.push(LinkError::BankOverflow {
+                    bank: bank.name.clone(),
+                    overflow: bank_contents.len() - bank.size(),
+                });
+            }
+        }
+
+        let (globals, symbols) = self.resolve_symbols(&section_addrs, &mut errors);
+        self.relocate(&section_addrs, &globals, &mut contents, &mut errors);
+
+        if !errors.is_empty() {
+            return Err(LinkErrors(errors));
+        }
+
+        Ok(LinkOutput {
+            banks: self.banks.banks.iter().cloned().zip(contents).collect(),
+            placements,
+            symbols,
+        })
+    }
+
+    /// Addresses of the global symbols by name, and every defined symbol for the symbol map.
+    fn resolve_symbols(
+        &self,
+        section_addrs: &[Vec<Option<Word>>],
+        errors: &mut Vec<LinkError>,
+    ) -> (HashMap<String, Word>, Vec<MapSymbol>) {
+        let mut globals: HashMap<String, (Word, &str)> = HashMap::new();
+        let mut symbols = vec![];
+
+        for ((object_name, object), object_section_addrs) in self.objects.iter().zip(section_addrs)
+        {
+            let mut locals = HashSet::new();
+
+            for symbol in &object.symbols {
+                let Some((section, offset)) = symbol.definition else {
+                    continue;
+                };
+
+                let Some(section_addr) = object_section_addrs[section] else {
+                    continue; // Already reported as an unknown bank.
+                };
+
+                let addr = section_addr.wrapping_add(offset);
+
+                let duplicate = match symbol.binding {
+                    Binding::Global => match globals.entry(symbol.name.clone()) {
+                        Entry::Occupied(entry) => Some(entry.get().1.to_owned()),
+                        Entry::Vacant(entry) => {
+                            entry.insert((addr, object_name));
+                            None
+                        }
+                    },
+                    Binding::Local => (!locals.insert(&symbol.name)).then(|| object_name.clone()),
+                };
+
+                if let Some(first) = duplicate {
+                    errors.push(LinkError::DuplicateSymbol {
+                        name: symbol.name.clone(),
+                        first,
+                        second: object_name.clone(),
+                    });
+                    continue;
+                }
+
+                symbols.push(MapSymbol {
+                    name: symbol.name.clone(),
+                    addr,
+                    binding: symbol.binding,
+                    object: object_name.clone(),
+                });
+            }
+        }
+
+        symbols.sort_by(|a, b| (a.addr, &a.name).cmp(&(b.addr, &b.name)));
+
+        let globals = globals
+            .into_iter()
+            .map(|(name, (addr, _))| (name, addr))
+            .collect();
+
+        (globals, symbols)
+    }
+
+    fn relocate(
+        &self,
+        section_addrs: &[Vec<Option<Word>>],
+        globals: &HashMap<String, Word>,
+        contents: &mut [Vec<u8>],
+        errors: &mut Vec<LinkError>,
+    ) {
+        for ((object_name, object), object_section_addrs) in self.objects.iter().zip(section_addrs)
+        {
+            for relocation in &object.relocations {
+                let symbol = &object.symbols[relocation.symbol];
+                let section = &object.sections[relocation.section];
+
+                let Some(section_addr) = object_section_addrs[relocation.section] else {
+                    continue; // Already reported as an unknown bank.
+                };
+
+                // Symbols defined by the object are resolved directly, the others by the globals of the others.
+                let target = match symbol.definition {
+                    Some((section, offset)) => object_section_addrs[section]
+                        .map(|section_addr| section_addr.wrapping_add(offset)),
+                    None => globals.get(&symbol.name).copied(),
+                };
+
+                let Some(target) = target else {
+                    let error = LinkError::UndefinedSymbol {
+                        name: symbol.name.clone(),
+                        object: object_name.clone(),
+                    };
+
+                    if !errors.contains(&error) {
+                        errors.push(error);
+                    }
+                    continue;
+                };
+
+                let bad_target = |expected| LinkError::BadRelocationTarget {
+                    object: object_name.clone(),
+                    section: section.name.clone(),
+                    offset: relocation.offset,
+                    expected,
+                };
+
+                let offset = relocation.offset as usize;
+                let instruction = section
+                    .data
+                    .get(offset..offset + libisa::BYTES_PER_WORD)
+                    .and_then(|word| {
+                        Instruction::deassemble_instruction_word(libisa::bytes_to_word(
+                            word.try_into().unwrap(),
+                        ))
+                        .ok()
+                    })
+                    .filter(|instruction| {
+                        instruction.kind.has_immediate()
+                            && offset + instruction.kind.len_bytes() <= section.data.len()
+                    });
+
+                let Some(instruction) = instruction else {
+                    errors.push(bad_target("an instruction with an immediate"));
+                    continue;
+                };
+
+                let instruction_addr = section_addr.wrapping_add(relocation.offset);
+                let target = target.wrapping_add(relocation.addend);
+
+                let immediate = match relocation.kind {
+                    RelocationKind::Absolute => target,
+                    RelocationKind::Relative if instruction.kind.is_relative_jump() => {
+                        Instruction::relative_jump_offset(
+                            instruction.kind,
+                            instruction_addr,
+                            target,
+                        )
+                    }
+                    RelocationKind::Relative => {
+                        errors.push(bad_target("a relative jump"));
+                        continue;
+                    }
+                };
+
+                let bank_index = self
+                    .banks
+                    .banks
+                    .iter()
+                    .position(|bank| bank.name == section.name)
+                    .unwrap();
+
+                let immediate_index = (instruction_addr as usize
+                    - self.banks.banks[bank_index].addr)
+                    + libisa::BYTES_PER_WORD;
+
+                contents[bank_index][immediate_index..immediate_index + libisa::BYTES_PER_WORD]
+                    .copy_from_slice(&libisa::word_to_bytes(immediate));
+            }
+        }
+    }
+}
+
+impl LinkOutput {
+    /// Raw binary like customasm outputs, with the banks having an output offset at it and the filled ones padded
+    /// to their full size. Banks without an output offset, like RAM, are left out along with their contents.
+    pub fn image(&self) -> Vec<u8> {
+        let mut image = vec![];
+
+        for (bank, contents) in &self.banks {
+            let Some(outp) = bank.outp else {
+                continue;
+            };
+
+            let len = if bank.fill {
+                bank.size()
+            } else {
+                contents.len()
+            };
+
+            if image.len() < outp + len {
+                image.resize(outp + len, 0);
+            }
+
+            image[outp..outp + contents.len()].copy_from_slice(contents);
+        }
+
+        image
+    }
+
+    /// Executable with a segment per non-empty bank, the output ones being executable and the others, like RAM,
+    /// writable. Starts from the entry symbol, or the first bank without one.
+    pub fn executable(&self) -> Executable {
+        let entry = self
+            .symbol(ENTRY_SYMBOL)
+            .or(self.banks.first().map(|(bank, _)| bank.addr as Word))
+            .unwrap_or(0);
+
+        let segments = self
+            .banks
+            .iter()
+            .filter(|(_, contents)| !contents.is_empty())
+            .map(|(bank, contents)| {
+                let permissions = match bank.outp {
+                    Some(_) => Permissions::READ_EXECUTE,
+                    None => Permissions::READ_WRITE,
+                };
+
+                Segment::new(bank.addr as Word, permissions, contents.clone())
+            });
+
+        let executable = segments.fold(Executable::new(entry), Executable::with_segment);
+
+        self.symbols.iter().fold(executable, |executable, symbol| {
+            executable.with_symbol(symbol.name.clone(), symbol.addr)
+        })
+    }
+
+    pub fn symbol(&self, name: &str) -> Option<Word> {
+        self.symbols
+            .iter()
+            .find(|symbol| symbol.name == name && symbol.binding == Binding::Global)
+            .map(|symbol| symbol.addr)
+    }
+
+    /// Text listing where every section and symbol ended up.
+    pub fn symbol_map(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl Display for LinkOutput {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Sections:")?;
+
+        for placement in &self.placements {
+            writeln!(
+                f,
+                "  {:04x}-{:04x}  {:<8} {}",
+                placement.addr,
+                (placement.addr as usize + placement.len).saturating_sub(1),
+                placement.section,
+                placement.object
+            )?;
+        }
+
+        writeln!(f, "Symbols:")?;
+
+        for symbol in &self.symbols {
+            writeln!(
+                f,
+                "  {:04x}  {:<6} {:<24} {}",
+                symbol.addr, symbol.binding, symbol.name, symbol.object
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Display for LinkErrors {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (index, error) in self.0.iter().enumerate() {
+            if index > 0 {
+                writeln!(f)?;
+            }
+
+            write!(f, "{}", error)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl std::error::Error for LinkErrors {}
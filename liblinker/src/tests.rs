@@ -0,0 +1,182 @@
+use libdeassembler::Deassembler;
+use libisa::{
+    executable::Permissions,
+    instruction::{kind::InstructionKind, Instruction},
+    object::{Binding, ObjectFile, Relocation},
+};
+
+use crate::{bank::BankDescription, LinkError, Linker};
+
+const BANKS: &str = include_str!("../../customasm/banks.asm");
+
+fn assemble(instructions: &[Instruction]) -> Vec<u8> {
+    libisa::instruction::assembler::assemble(instructions.iter().copied())
+        .unwrap()
+        .machine_code
+}
+
+fn banks() -> BankDescription {
+    BANKS.parse().expect("Error parsing banks.asm")
+}
+
+/// Loads the address of `counter` and jumps to `helper`, both defined by `lib_object`.
+fn main_object() -> ObjectFile {
+    let mut object = ObjectFile::new();
+
+    let code = object.add_section(
+        "code",
+        assemble(&[
+            Instruction::new(InstructionKind::LoadI)
+                .with_reg_a(0)
+                .with_immediate(0),
+            Instruction::new(InstructionKind::JmpR).with_immediate(0),
+        ]),
+    );
+
+    object.define_symbol("_start", Binding::Global, code, 0);
+
+    let counter = object.reference_symbol("counter");
+    let helper = object.reference_symbol("helper");
+
+    object.add_relocation(Relocation::absolute(code, 0, counter).with_addend(2));
+    object.add_relocation(Relocation::relative(code, 4, helper));
+
+    object
+}
+
+fn lib_object() -> ObjectFile {
+    let mut object = ObjectFile::new();
+
+    let code = object.add_section("code", assemble(&[Instruction::new(InstructionKind::Halt)]));
+    let ram = object.add_section("ram", vec![0; 4]);
+
+    object.define_symbol("helper", Binding::Global, code, 0);
+    object.define_symbol("counter", Binding::Global, ram, 0);
+
+    object
+}
+
+#[test]
+fn banks_of_banks_asm_get_parsed() {
+    let banks = banks();
+
+    let code = banks.bank("code").expect("No code bank");
+    assert_eq!(
+        (code.addr, code.addr_end, code.outp, code.fill),
+        (0, 1024, Some(0), true)
+    );
+
+    let ram = banks.bank("ram").expect("No ram bank");
+    assert_eq!(
+        (ram.addr, ram.addr_end, ram.outp, ram.fill),
+        (1024, 0xffd0, None, false)
+    );
+}
+
+#[test]
+fn objects_get_placed_and_relocated() {
+    let main = main_object();
+    let lib = lib_object();
+
+    // Objects survive being written out and read back in.
+    let main = ObjectFile::parse(&main.to_bytes().unwrap()).unwrap();
+    assert_eq!(main, main_object());
+
+    let output = Linker::new(banks())
+        .with_object("main.o", main)
+        .with_object("lib.o", lib)
+        .link()
+        .expect("Error linking");
+
+    assert_eq!(output.symbol("_start"), Some(0));
+    assert_eq!(output.symbol("helper"), Some(8));
+    assert_eq!(output.symbol("counter"), Some(1024));
+
+    let image = output.image();
+    assert_eq!(image.len(), 1024, "Code bank wasn't filled");
+
+    let instructions = Deassembler::new(image[..10].iter())
+        .deassemble()
+        .expect("Error deassembling linked code");
+    assert_eq!(instructions[0].immediate, Some(1024 + 2));
+    assert_eq!(
+        instructions[1].static_jump_target(4),
+        Some(8),
+        "Relative jump doesn't reach helper"
+    );
+    assert_eq!(instructions[2].kind, InstructionKind::Halt);
+
+    let executable = output.executable();
+    assert_eq!(executable.entry, 0);
+    assert_eq!(executable.segments.len(), 2);
+    assert_eq!(executable.segments[1].addr, 1024);
+    assert_eq!(executable.segments[1].permissions, Permissions::READ_WRITE);
+
+    let map = output.symbol_map();
+    assert!(map.contains("0400  global counter"));
+    assert!(map.contains("0008-0009  code     lib.o"));
+}
+
+#[test]
+fn undefined_and_duplicate_symbols_get_reported() {
+    let errors = Linker::new(banks())
+        .with_object("main.o", main_object())
+        .with_object("main_again.o", main_object())
+        .link()
+        .expect_err("Linking succeeded");
+
+    assert!(errors.0.contains(&LinkError::DuplicateSymbol {
+        name: "_start".to_owned(),
+        first: "main.o".to_owned(),
+        second: "main_again.o".to_owned(),
+    }));
+
+    for object in ["main.o", "main_again.o"] {
+        for name in ["counter", "helper"] {
+            assert!(errors.0.contains(&LinkError::UndefinedSymbol {
+                name: name.to_owned(),
+                object: object.to_owned(),
+            }));
+        }
+    }
+
+    assert_eq!(errors.0.len(), 5);
+}
+
+#[test]
+fn overflowing_bank_is_an_error() {
+    let mut object = ObjectFile::new();
+    object.add_section("code", vec![0; 1026]);
+
+    let errors = Linker::new(banks())
+        .with_object("big.o", object)
+        .link()
+        .expect_err("Linking succeeded");
+
+    assert_eq!(
+        errors.0,
+        [LinkError::BankOverflow {
+            bank: "code".to_owned(),
+            overflow: 2,
+        }]
+    );
+}
+
+#[test]
+fn relocation_must_target_a_fitting_instruction() {
+    let mut object = lib_object();
+    let helper = 0;
+
+    // The halt has no immediate to patch.
+    object.add_relocation(Relocation::absolute(0, 0, helper));
+
+    let errors = Linker::new(banks())
+        .with_object("lib.o", object)
+        .link()
+        .expect_err("Linking succeeded");
+
+    assert!(matches!(
+        errors.0.as_slice(),
+        [LinkError::BadRelocationTarget { offset: 0, .. }]
+    ));
+}
@@ -0,0 +1,55 @@
+use std::fmt::Write;
+
+use crate::Platform;
+
+impl Platform {
+    /// Customasm `#bankdef` blocks for the regions, to be included by `rules.asm`, and constants for the addresses of
+    /// the MMIO ranges. Output regions are laid out one after another in the output in the order they're defined,
+    /// each filled to its full size so the following ones end up where expected.
+    pub fn customasm_banks(&self, source_name: &str) -> String {
+        let mut asm = format!(
+            "; Generated from {} by `platform banks`, edit that and regenerate instead of editing this.\n",
+            source_name
+        );
+        let mut outp = 0;
+
+        for region in &self.regions {
+            // Writing to a string can't fail.
+            let _ = writeln!(asm, "\n#bankdef {} {{", region.name);
+
+            if region.output {
+                let _ = writeln!(asm, "    #outp {}*8", outp);
+                outp += region.size;
+            }
+
+            let _ = writeln!(asm, "    #addr 0x{:04x}", region.addr);
+            let _ = writeln!(asm, "    #size 0x{:04x}", region.size);
+            let _ = writeln!(asm, "    #bits 8");
+
+            // Only 8-bit alignment should be required, this is just a workaround for endianness problems in the
+            // emulator.
+            let _ = writeln!(asm, "    #labelalign 8");
+
+            if region.output {
+                let _ = writeln!(asm, "    #fill");
+            }
+
+            asm += "}\n";
+        }
+
+        if !self.mmio.is_empty() {
+            asm += "\n";
+        }
+
+        for range in &self.mmio {
+            let _ = writeln!(
+                asm,
+                "MMIO_{} = 0x{:04x}",
+                range.name.to_uppercase(),
+                range.addr
+            );
+        }
+
+        asm
+    }
+}
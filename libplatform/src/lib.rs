@@ -0,0 +1,213 @@
+use std::{ops::Range, str::FromStr};
+
+use libisa::{Register, Word};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+pub mod customasm;
+
+#[cfg(test)]
+mod tests;
+
+/// Size of the STRM1 address space in bytes.
+pub const ADDRESS_SPACE_SIZE: u32 = libisa::ADDRESS_SPACE_SIZE as u32;
+
+/// Description of the machine programs run on, shared by the assembler banks, the compiler backend and the emulator
+/// so they agree on where things are. Written in RON, like `customasm/platform.ron`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Platform {
+    /// Memory regions, which must not overlap each other or the MMIO ranges.
+    pub regions: Vec<Region>,
+
+    /// Name of the region code is placed in.
+    pub code: String,
+
+    /// Name of the region variables kept in memory are placed in. Variables go right after the code if it's the code
+    /// region.
+    pub data: String,
+
+    #[serde(default)]
+    pub mmio: Vec<MmioRange>,
+
+    /// Registers the compiler never allocates, e.g. for use by hand-written runtime code.
+    #[serde(default)]
+    pub reserved_registers: Vec<Register>,
+
+    /// Address execution starts from, which must be in the code region.
+    #[serde(default)]
+    pub reset_pc: Word,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Region {
+    pub name: String,
+    pub addr: u32,
+    pub size: u32,
+
+    /// Whether the region's contents are part of the program image, e.g. ROM as opposed to RAM.
+    #[serde(default)]
+    pub output: bool,
+}
+
+/// Range of addresses belonging to a memory-mapped device rather than memory.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MmioRange {
+    pub name: String,
+    pub addr: u32,
+    pub size: u32,
+}
+
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+pub enum PlatformError {
+    #[error("Parsing platform: {0}")]
+    Parse(#[from] ron::error::SpannedError),
+
+    #[error("No memory regions defined")]
+    NoRegions,
+
+    #[error("'{0}' isn't a valid name, expected letters, digits and underscores")]
+    InvalidName(String),
+
+    #[error("'{0}' is defined more than once")]
+    DuplicateName(String),
+
+    #[error("'{0}' is empty or extends past the end of the address space")]
+    OutOfRange(String),
+
+    #[error("'{first}' and '{second}' overlap")]
+    Overlap { first: String, second: String },
+
+    #[error("No region named '{0}'")]
+    UnknownRegion(String),
+
+    #[error("Register {0} doesn't exist")]
+    InvalidRegister(Register),
+
+    #[error("Reset PC 0x{0:04x} is outside the code region")]
+    ResetPcOutsideCode(Word),
+}
+
+impl Platform {
+    pub fn region(&self, name: &str) -> Option<&Region> {
+        self.regions.iter().find(|region| region.name == name)
+    }
+
+    pub fn mmio_range(&self, name: &str) -> Option<&MmioRange> {
+        self.mmio.iter().find(|range| range.name == name)
+    }
+
+    pub fn code_region(&self) -> &Region {
+        self.region(&self.code)
+            .expect("Code region of validated platform missing")
+    }
+
+    pub fn data_region(&self) -> &Region {
+        self.region(&self.data)
+            .expect("Data region of validated platform missing")
+    }
+
+    /// Bytes of memory needed to back every region and MMIO range.
+    pub fn memory_size(&self) -> u32 {
+        let region_ends = self.regions.iter().map(|region| region.range().end);
+        let mmio_ends = self.mmio.iter().map(|range| range.range().end);
+
+        region_ends.chain(mmio_ends).max().unwrap_or(0)
+    }
+
+    /// Check the description is consistent, which parsing also does.
+    pub fn validate(&self) -> Result<(), PlatformError> {
+        if self.regions.is_empty() {
+            return Err(PlatformError::NoRegions);
+        }
+
+        let ranges: Vec<(&str, Range<u32>)> = self
+            .regions
+            .iter()
+            .map(|region| (region.name.as_str(), region.range()))
+            .chain(
+                self.mmio
+                    .iter()
+                    .map(|range| (range.name.as_str(), range.range())),
+            )
+            .collect();
+
+        for (index, (name, range)) in ranges.iter().enumerate() {
+            if !is_identifier(name) {
+                return Err(PlatformError::InvalidName(name.to_string()));
+            }
+
+            if range.is_empty() || range.end > ADDRESS_SPACE_SIZE {
+                return Err(PlatformError::OutOfRange(name.to_string()));
+            }
+
+            for (other_name, other_range) in &ranges[..index] {
+                if other_name == name {
+                    return Err(PlatformError::DuplicateName(name.to_string()));
+                }
+
+                if range.start < other_range.end && other_range.start < range.end {
+                    return Err(PlatformError::Overlap {
+                        first: other_name.to_string(),
+                        second: name.to_string(),
+                    });
+                }
+            }
+        }
+
+        for name in [&self.code, &self.data] {
+            if self.region(name).is_none() {
+                return Err(PlatformError::UnknownRegion(name.clone()));
+            }
+        }
+
+        if let Some(reg) = self
+            .reserved_registers
+            .iter()
+            .find(|reg| **reg >= libisa::REGISTER_COUNT)
+        {
+            return Err(PlatformError::InvalidRegister(*reg));
+        }
+
+        if !self.code_region().range().contains(&(self.reset_pc as u32)) {
+            return Err(PlatformError::ResetPcOutsideCode(self.reset_pc));
+        }
+
+        Ok(())
+    }
+}
+
+impl Region {
+    pub fn range(&self) -> Range<u32> {
+        self.addr..self.addr.saturating_add(self.size)
+    }
+}
+
+impl MmioRange {
+    pub fn range(&self) -> Range<u32> {
+        self.addr..self.addr.saturating_add(self.size)
+    }
+
+    pub fn contains(&self, addr: Word) -> bool {
+        self.range().contains(&(addr as u32))
+    }
+}
+
+/// Names end up as customasm bank and constant names, so they're limited to what those accept.
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+impl FromStr for Platform {
+    type Err = PlatformError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let platform: Self = ron::from_str(s)?;
+        platform.validate()?;
+        Ok(platform)
+    }
+}
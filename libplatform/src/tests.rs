@@ -0,0 +1,95 @@
+use crate::{MmioRange, Platform, PlatformError, Region};
+
+const PLATFORM: &str = include_str!("../../customasm/platform.ron");
+const BANKS: &str = include_str!("../../customasm/banks.asm");
+
+fn platform() -> Platform {
+    PLATFORM.parse().expect("Error parsing platform.ron")
+}
+
+#[test]
+fn platform_ron_gets_parsed() {
+    let platform = platform();
+
+    assert_eq!(platform.code_region().range(), 0..0x400);
+    assert_eq!(platform.data_region().range(), 0x400..0xffd0);
+    assert_eq!(platform.memory_size(), 0x10000);
+    assert_eq!(platform.reset_pc, 0);
+}
+
+#[test]
+fn banks_asm_is_up_to_date() {
+    assert_eq!(
+        platform().customasm_banks("platform.ron"),
+        BANKS,
+        "banks.asm differs from what platform.ron generates, regenerate it with `platform banks`"
+    );
+}
+
+#[test]
+fn mmio_ranges_become_constants() {
+    let mut platform = platform();
+    platform.regions[1].size = 0xfb00;
+    platform.mmio.push(MmioRange {
+        name: "uart".to_owned(),
+        addr: 0xff00,
+        size: 2,
+    });
+
+    assert_eq!(platform.validate(), Ok(()));
+    assert!(platform
+        .customasm_banks("platform.ron")
+        .ends_with("\nMMIO_UART = 0xff00\n"));
+}
+
+#[test]
+fn inconsistent_platforms_are_rejected() {
+    let mut overlapping = platform();
+    overlapping.mmio.push(MmioRange {
+        name: "uart".to_owned(),
+        addr: 0xff00,
+        size: 2,
+    });
+
+    assert_eq!(
+        overlapping.validate(),
+        Err(PlatformError::Overlap {
+            first: "ram".to_owned(),
+            second: "uart".to_owned(),
+        })
+    );
+
+    let mut too_big = platform();
+    too_big.regions.push(Region {
+        name: "high".to_owned(),
+        addr: 0x10000,
+        size: 1,
+        output: false,
+    });
+
+    assert_eq!(
+        too_big.validate(),
+        Err(PlatformError::OutOfRange("high".to_owned()))
+    );
+
+    let mut unknown_data = platform();
+    unknown_data.data = "sram".to_owned();
+
+    assert_eq!(
+        unknown_data.validate(),
+        Err(PlatformError::UnknownRegion("sram".to_owned()))
+    );
+
+    let mut bad_reset = platform();
+    bad_reset.reset_pc = 0x400;
+
+    assert_eq!(
+        bad_reset.validate(),
+        Err(PlatformError::ResetPcOutsideCode(0x400))
+    );
+
+    assert!(matches!(
+        "Platform(regions: [], code: \"code\", data: \"code\")".parse::<Platform>(),
+        Err(PlatformError::NoRegions)
+    ));
+}
@@ -21,19 +21,27 @@ use std::collections::HashMap;
 use anyhow::{anyhow, Context};
 use itertools::Itertools;
 use lazy_static::lazy_static;
-use libisa::instruction::{kind::InstructionKind, Instruction as TargetInstruction};
+use libisa::{
+    instruction::{kind::InstructionKind, Instruction as TargetInstruction},
+    Word,
+};
+use libplatform::Platform;
 use varalloc::{
     allocator::{AllocRequirement, VarAllocator, VarDefinition},
     AllocMap, MemVarAlloc, RegVarAlloc, VarAlloc,
 };
 
 use crate::{
-    backend::strm1::codegen::prealloc::{VarId, VarKey},
+    backend::strm1::{
+        codegen::prealloc::{VarId, VarKey},
+        MemoryLayout,
+    },
+    lir::LIRLabelId,
     transformer::{extra::Extras, Transformer},
 };
 
 use super::prealloc::{
-    varidspace::VarIdSpace, MemVarKey, PreallocInstruction, RegVarKey, VarTrait,
+    varidspace::VarIdSpace, Immediate, MemVarKey, PreallocInstruction, RegVarKey, VarTrait,
 };
 
 mod varalloc;
@@ -46,19 +54,39 @@ const EXTRAS_ALLOC_MAP_KEY: &'static str = "strm1_alloc_map";
 const EXTRAS_ALLOC_METADATA_KEY: &'static str = "strm1_alloc_metadata";
 
 lazy_static! {
-    static ref INTERNAL_VAR_SPACE: VarIdSpace = VarIdSpace::new();
+    /// Register holding the start address of the memory variables, which are all addressed relative to it.
+    static ref DATA_BASE_VAR_ID: VarId = VarId(VarIdSpace::new(), 0);
 }
 
 #[derive(Debug, Default)]
 pub struct AllocTransformer {
     alloc_map: AllocMap,
     alloc_metadata: HashMap<VarId, VarDefinition>,
+
+    /// Address of the first memory variable, computed in the Neumann offset computation prepass.
+    data_base: Word,
+
+    /// Addresses of the LIR labels, computed in the label address prepass.
+    label_addrs: HashMap<LIRLabelId, Word>,
+
+    layout: MemoryLayout,
+    platform: Option<Platform>,
 }
 
 impl AllocTransformer {
     pub fn new() -> Self {
         Self::default()
     }
+
+    pub fn with_layout(mut self, layout: MemoryLayout) -> Self {
+        self.layout = layout;
+        self
+    }
+
+    pub fn with_platform(mut self, platform: Option<Platform>) -> Self {
+        self.platform = platform;
+        self
+    }
 }
 
 impl Transformer for AllocTransformer {
@@ -71,6 +99,7 @@ impl Transformer for AllocTransformer {
             "Von Neumann offset computation prepass",
             Self::neumann_offset_computation_prepass,
         ),
+        ("label address prepass", Self::label_address_prepass),
     ];
 
     fn transform(&mut self, input: Extras<Self::Input>) -> anyhow::Result<Extras<Self::Output>> {
@@ -86,7 +115,12 @@ impl AllocTransformer {
         &mut self,
         input: &Extras<<Self as Transformer>::Input>,
     ) -> anyhow::Result<()> {
-        let mut allocator = VarAllocator::new();
+        let mut allocator = match &self.platform {
+            Some(platform) => VarAllocator::new()
+                .with_reserved_registers(platform.reserved_registers.iter().copied())
+                .with_memory_size(platform.data_region().size as usize),
+            None => VarAllocator::new(),
+        };
 
         for (instruction_index, instruction) in input.data.iter().enumerate() {
             match instruction {
@@ -100,22 +134,6 @@ impl AllocTransformer {
                     allocator.define(*key.id(), instruction_index, 0, alloc_requirement)?;
                 }
 
-                PreallocInstruction::StoreVar { dest, .. } => {
-                    let dest_var = allocator.get_definition(dest.id()).ok_or_else(|| {
-                        anyhow!("Undefined variable")
-                            .context("dest")
-                            .context("StoreVar")
-                    })?;
-
-                    if dest_var.alloc_requirement != AllocRequirement::Register {
-                        // The destination variable is heading towards memory, so the main pass requires an internal
-                        // register for storing it's memory address, allocate that here.
-                        let id = VarId(*INTERNAL_VAR_SPACE, instruction_index as u64);
-                        allocator.define(id, instruction_index, 0, AllocRequirement::Register)?;
-                        allocator.extend_lifetime(&id, instruction_index + 1)?; // The register is only needed for this instruction.
-                    }
-                }
-
                 PreallocInstruction::ExplicitRegister { .. }
                 | PreallocInstruction::ExplicitMemory { .. } => todo!(), // TODO: Explicit register and memory addresses
 
@@ -132,8 +150,58 @@ impl AllocTransformer {
             }
         }
 
-        self.alloc_metadata = allocator.definition_map().clone();
-        self.alloc_map = allocator.build()?;
+        let alloc_map = allocator.clone().build()?;
+
+        if alloc_map.contains_memory_allocs() {
+            // Memory variables are accessed with base+offset addressing, the base register being reserved for the
+            // whole program. Allocate everything again, as reserving it may push other variables to memory.
+            allocator.define(*DATA_BASE_VAR_ID, 0, usize::MAX, AllocRequirement::Register)?;
+            allocator.extend_lifetime(&DATA_BASE_VAR_ID, input.data.len())?;
+
+            self.alloc_metadata = allocator.definition_map().clone();
+            self.alloc_map = allocator.build()?;
+        } else {
+            self.alloc_metadata = allocator.definition_map().clone();
+            self.alloc_map = alloc_map;
+        }
+
+        Ok(())
+    }
+
+    fn label_address_prepass(
+        &mut self,
+        input: &Extras<<Self as Transformer>::Input>,
+    ) -> anyhow::Result<()> {
+        let code_base = match &self.platform {
+            Some(platform) => platform.code_region().addr as Word,
+            None => 0,
+        };
+
+        let mut addr = self.prologue().iter().fold(code_base, |addr, instruction| {
+            addr.wrapping_add(instruction.kind.len_bytes() as Word)
+        });
+
+        for instruction in &input.data {
+            if let PreallocInstruction::Label(label) = instruction {
+                if self.label_addrs.insert(*label, addr).is_some() {
+                    return Err(anyhow!("Label {} is defined more than once", label));
+                }
+            }
+
+            for target_instruction in self.transform_instruction(instruction.clone())? {
+                addr = addr.wrapping_add(target_instruction.kind.len_bytes() as Word);
+            }
+        }
+
+        if let Some(label) = input
+            .data
+            .iter()
+            .filter_map(PreallocInstruction::referenced_label)
+            .find(|label| !self.label_addrs.contains_key(label))
+        {
+            return Err(anyhow!("Label {} is referenced but never defined", label));
+        }
+
         Ok(())
     }
 
@@ -141,36 +209,46 @@ impl AllocTransformer {
         &mut self,
         prealloc_ir: Vec<PreallocInstruction>,
     ) -> anyhow::Result<Vec<TargetInstruction>> {
-        prealloc_ir
+        let body: Vec<_> = prealloc_ir
             .into_iter()
-            .enumerate()
-            .map(|(instruction_index, instruction)| {
-                self.transform_instruction(instruction_index, instruction)
-            })
+            .map(|instruction| self.transform_instruction(instruction))
             .flatten_ok()
-            .try_collect()
+            .try_collect()?;
+
+        Ok(self.prologue().into_iter().chain(body).collect())
+    }
+
+    /// Code run before the program itself, setting up the data base register if there are memory variables.
+    fn prologue(&self) -> Vec<TargetInstruction> {
+        match self.alloc_map.get_by_id(&DATA_BASE_VAR_ID) {
+            Some(VarAlloc::Register(base_reg)) => {
+                vec![TargetInstruction::new(InstructionKind::LoadI)
+                    .with_reg_a(base_reg.0)
+                    .with_immediate(self.data_base)]
+            }
+            _ => vec![],
+        }
     }
 
     fn transform_instruction(
         &mut self,
-        instruction_index: usize,
         instruction: PreallocInstruction,
     ) -> anyhow::Result<Vec<TargetInstruction>> {
-        // NOTE: Remember to also update the Neumann offset computation prepass!
-        //       Going to be a very nasty bug to find when variables are overwriting code otherwise.
-
         Ok(match instruction {
             // Handled in prepasses
             PreallocInstruction::DefineVar(..)
             | PreallocInstruction::ExplicitRegister { .. }
             | PreallocInstruction::ExplicitMemory { .. } => vec![],
 
+            // Only marks an address, handled in the label address prepass.
+            PreallocInstruction::Label(..) => vec![],
+
             PreallocInstruction::LoadImmediate { dest, value } => {
                 let dest_reg = self.reg_var(&dest).context("LoadImmediate")?.0;
 
                 vec![TargetInstruction::new(InstructionKind::LoadI)
                     .with_reg_a(dest_reg)
-                    .with_immediate(value)]
+                    .with_immediate(self.immediate(value))]
             }
 
             PreallocInstruction::LoadVar { dest, src } => {
@@ -185,14 +263,11 @@ impl AllocTransformer {
                             .with_reg_b(src_reg.0),
                     ],
                     VarAlloc::Memory(src_mem) => vec![
-                        // Load the source address to dest_reg.
-                        TargetInstruction::new(InstructionKind::LoadI)
+                        // Load the value at the source offset from the data base.
+                        TargetInstruction::new(InstructionKind::LoadO)
                             .with_reg_a(dest_reg)
-                            .with_immediate(src_mem.0),
-                        // And now load the value of that address to dest_reg.
-                        TargetInstruction::new(InstructionKind::Load)
-                            .with_reg_a(dest_reg)
-                            .with_reg_b(dest_reg),
+                            .with_reg_b(self.data_base_reg().context("LoadVar")?)
+                            .with_immediate(src_mem.0.wrapping_sub(self.data_base)),
                     ],
                 }
             }
@@ -208,21 +283,11 @@ impl AllocTransformer {
                             .with_reg_b(src_reg)]
                     }
                     VarAlloc::Memory(dest_mem) => {
-                        // Allocated in prepass.
-                        let internal_addr_var_id =
-                            &RegVarKey(VarId(*INTERNAL_VAR_SPACE, instruction_index as u64));
-                        let internal_addr_reg = self.reg_var(internal_addr_var_id)?.0;
-
-                        vec![
-                            // Load the memory address of the destination variable to the internal register.
-                            TargetInstruction::new(InstructionKind::LoadI)
-                                .with_reg_a(internal_addr_reg)
-                                .with_immediate(dest_mem.0),
-                            // Store the source value to the loaded address (destination variable).
-                            TargetInstruction::new(InstructionKind::Store)
-                                .with_reg_a(internal_addr_reg)
-                                .with_reg_b(src_reg),
-                        ]
+                        // Store the source value at the destination offset from the data base.
+                        vec![TargetInstruction::new(InstructionKind::StoreO)
+                            .with_reg_a(self.data_base_reg().context("StoreVar")?)
+                            .with_reg_b(src_reg)
+                            .with_immediate(dest_mem.0.wrapping_sub(self.data_base))]
                     }
                 }
             }
@@ -242,6 +307,19 @@ impl AllocTransformer {
                 .context("addr")
                 .context("JmpZ")?,
 
+            PreallocInstruction::JmpI(addr) => {
+                vec![TargetInstruction::new(InstructionKind::JmpI)
+                    .with_immediate(self.immediate(addr))]
+            }
+            PreallocInstruction::JmpCI(addr) => {
+                vec![TargetInstruction::new(InstructionKind::JmpCI)
+                    .with_immediate(self.immediate(addr))]
+            }
+            PreallocInstruction::JmpZI(addr) => {
+                vec![TargetInstruction::new(InstructionKind::JmpZI)
+                    .with_immediate(self.immediate(addr))]
+            }
+
             PreallocInstruction::Add(a_reg, b_reg) => self
                 .transform_dual_reg_operand(InstructionKind::Add, a_reg, b_reg)
                 .context("Add")?,
@@ -258,6 +336,16 @@ impl AllocTransformer {
                 .transform_dual_reg_operand(InstructionKind::And, a_reg, b_reg)
                 .context("And")?,
 
+            PreallocInstruction::AddI(a_reg, value) => self
+                .transform_reg_immediate_operand(InstructionKind::AddI, a_reg, value)
+                .context("AddI")?,
+            PreallocInstruction::SubI(a_reg, value) => self
+                .transform_reg_immediate_operand(InstructionKind::SubI, a_reg, value)
+                .context("SubI")?,
+            PreallocInstruction::AndI(a_reg, value) => self
+                .transform_reg_immediate_operand(InstructionKind::AndI, a_reg, value)
+                .context("AndI")?,
+
             PreallocInstruction::TargetPassthrough { instructions } => instructions,
         })
     }
@@ -287,6 +375,34 @@ impl AllocTransformer {
             .with_reg_b(reg_b)])
     }
 
+    fn transform_reg_immediate_operand(
+        &self,
+        kind: InstructionKind,
+        a: RegVarKey,
+        value: Word,
+    ) -> anyhow::Result<Vec<TargetInstruction>> {
+        let reg_a = self.reg_var(&a).context("a")?.0;
+
+        Ok(vec![TargetInstruction::new(kind)
+            .with_reg_a(reg_a)
+            .with_immediate(value)])
+    }
+
+    /// The value of the immediate, labels being at address 0 until the label address prepass has laid out the code.
+    /// The prepasses before it only generate code for its length, which the immediates don't affect.
+    fn immediate(&self, immediate: Immediate) -> Word {
+        match immediate {
+            Immediate::Value(value) => value,
+            Immediate::Label(label) => self.label_addrs.get(&label).copied().unwrap_or(0),
+        }
+    }
+
+    fn data_base_reg(&self) -> anyhow::Result<libisa::Register> {
+        self.reg_var(&RegVarKey(*DATA_BASE_VAR_ID))
+            .context("Data base register")
+            .map(|reg| reg.0)
+    }
+
     fn var(&self, key: &VarKey) -> anyhow::Result<&VarAlloc> {
         self.alloc_map
             .get(key)
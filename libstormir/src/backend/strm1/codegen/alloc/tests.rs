@@ -11,6 +11,7 @@ use crate::{
         codegen::prealloc::{self, VarId, VarKey, VarTrait},
         machinecode::EXTRAS_INSTRUCTION_TO_BYTE_INDEX_MAP_KEY,
         tests::Test,
+        MemoryLayout,
     },
     lir::LIRVarId,
 };
@@ -36,7 +37,11 @@ impl EmulatorTest {
     pub fn new(inner: Test) -> anyhow::Result<Self> {
         let program = inner.compilation_output.data.clone();
 
-        let emulator = Emulator::new(program).context("Error creating emulator")?;
+        let emulator = match inner.layout {
+            MemoryLayout::VonNeumann => Emulator::new(program),
+            MemoryLayout::Harvard => Emulator::new_harvard(program, vec![]),
+        }
+        .context("Error creating emulator")?;
 
         let alloc_map = inner
             .compilation_output
@@ -86,7 +91,7 @@ impl EmulatorTest {
             VarAlloc::Memory(MemVarAlloc(mem_addr)) => self
                 .emulator
                 .tracing
-                .memory_word_by_pc(var_alive_byte_index, mem_addr),
+                .memory_word_by_pc(var_alive_byte_index, mem_addr as usize),
             VarAlloc::Register(RegVarAlloc(reg_index)) => todo!(), // TODO
         }
     }
@@ -102,9 +107,12 @@ impl EmulatorTest {
             VarAlloc::Register(RegVarAlloc(reg_index)) => {
                 self.emulator.reg_file.get(*reg_index).copied()
             }
-            VarAlloc::Memory(MemVarAlloc(mem_addr)) => {
-                self.emulator.memory.word(*mem_addr).as_deref().copied()
-            }
+            VarAlloc::Memory(MemVarAlloc(mem_addr)) => self
+                .emulator
+                .memory
+                .get_multi::<Word>(*mem_addr as usize)
+                .as_deref()
+                .copied(),
         }
     }
 
@@ -2,16 +2,22 @@ use std::{collections::HashMap, ops::Range};
 
 use anyhow::anyhow;
 use itertools::Itertools;
-use libisa::Word;
+use libisa::{Register, Word};
 use serde::{Deserialize, Serialize};
 
 use crate::backend::strm1::codegen::prealloc::VarId;
 
 use super::{usagemap::RangedUsageMap, AllocMap, MemVarAlloc, RegVarAlloc, VarAlloc};
 
-#[derive(Debug, Default)]
+#[derive(Debug, Clone, Default)]
 pub struct VarAllocator {
     definitions: HashMap<VarId, VarDefinition>,
+
+    /// Registers never allocated to variables.
+    reserved_registers: Vec<Register>,
+
+    /// Bytes of memory available to variables, the whole address space if not limited.
+    memory_size: Option<usize>,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
@@ -35,6 +41,19 @@ impl VarAllocator {
         Self::default()
     }
 
+    pub fn with_reserved_registers(
+        mut self,
+        registers: impl IntoIterator<Item = Register>,
+    ) -> Self {
+        self.reserved_registers.extend(registers);
+        self
+    }
+
+    pub fn with_memory_size(mut self, memory_size: usize) -> Self {
+        self.memory_size = Some(memory_size);
+        self
+    }
+
     pub fn define(
         &mut self,
         id: VarId,
@@ -80,7 +99,9 @@ impl VarAllocator {
     }
 
     pub fn build(self) -> anyhow::Result<AllocMap> {
-        InnerBuilder::new().build(self.definitions)
+        let memory_size = self.memory_size.unwrap_or(libisa::ADDRESS_SPACE_SIZE);
+
+        InnerBuilder::new(&self.reserved_registers, memory_size).build(self.definitions)
     }
 
     pub fn definition_map(&self) -> &HashMap<VarId, VarDefinition> {
@@ -90,10 +111,6 @@ impl VarAllocator {
     pub fn contains_id(&self, id: &VarId) -> bool {
         self.definitions.contains_key(id)
     }
-
-    pub fn get_definition(&self, id: &VarId) -> Option<&VarDefinition> {
-        self.definitions.get(id)
-    }
 }
 
 struct InnerBuilder {
@@ -102,10 +119,18 @@ struct InnerBuilder {
 }
 
 impl InnerBuilder {
-    pub fn new() -> Self {
+    pub fn new(reserved_registers: &[Register], memory_size: usize) -> Self {
+        let mut reg_usage_map = RangedUsageMap::new(libisa::REGISTER_COUNT).preallocated();
+
+        // Reserved registers are in use for the whole program, so no variable's lifetime fits in them.
+        for reg in reserved_registers.iter().unique() {
+            reg_usage_map.reserve(*reg, 0..usize::MAX);
+        }
+
         Self {
-            reg_usage_map: RangedUsageMap::new(libisa::REGISTER_COUNT).preallocated(),
-            mem_usage_map: RangedUsageMap::new(Word::MAX as usize),
+            reg_usage_map,
+            // Memory slots are whole words, not bytes.
+            mem_usage_map: RangedUsageMap::new(memory_size / libisa::BYTES_PER_WORD),
         }
     }
 
@@ -155,8 +180,8 @@ impl InnerBuilder {
     }
 
     fn allocate_mem(&mut self, lifetime: Range<usize>) -> Option<VarAlloc> {
-        self.mem_usage_map
-            .reserve_free(lifetime)
-            .map(|mem_addr| VarAlloc::Memory(MemVarAlloc(mem_addr as Word)))
+        self.mem_usage_map.reserve_free(lifetime).map(|mem_slot| {
+            VarAlloc::Memory(MemVarAlloc((mem_slot * libisa::BYTES_PER_WORD) as Word))
+        })
     }
 }
@@ -48,6 +48,12 @@ impl AllocMap {
         }
     }
 
+    pub fn contains_memory_allocs(&self) -> bool {
+        self.0
+            .values()
+            .any(|alloc| matches!(alloc, VarAlloc::Memory(..)))
+    }
+
     #[allow(unused)] // It's here for consistency with get_reg and potential future use.
     pub fn get_mem(&self, key: &MemVarKey) -> Option<&MemVarAlloc> {
         match self.0.get(key.id()) {
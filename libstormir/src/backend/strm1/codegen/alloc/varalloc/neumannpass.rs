@@ -1,9 +1,9 @@
-use anyhow::Context;
+use anyhow::anyhow;
 use itertools::Itertools;
 use libisa::Word;
 
 use crate::{
-    backend::strm1::codegen::{alloc::AllocTransformer, prealloc::PreallocInstruction},
+    backend::strm1::{codegen::alloc::AllocTransformer, MemoryLayout},
     transformer::{extra::Extras, Transformer},
 };
 
@@ -14,11 +14,73 @@ impl AllocTransformer {
         &mut self,
         input: &Extras<<Self as Transformer>::Input>,
     ) -> anyhow::Result<()> {
+        // Variables already start from address 0, which is where they belong in their own memory.
+        if self.layout == MemoryLayout::Harvard && self.platform.is_none() {
+            return Ok(());
+        }
+
+        // The instruction lengths only depend on whether variables are in registers or memory, not on their
+        // addresses, so the code can be generated once here just for its length.
+        let prologue_len: Word = self
+            .prologue()
+            .iter()
+            .map(|instruction| instruction.kind.len_bytes() as Word)
+            .sum();
+
         let code_len = input
             .data
             .iter()
-            .map(|instruction| self.compute_instruction_code_len(instruction))
-            .fold_ok(0, |acc, instruction_code_len| acc + instruction_code_len)?;
+            .map(|instruction| self.transform_instruction(instruction.clone()))
+            .flatten_ok()
+            .map_ok(|instruction| instruction.kind.len_bytes() as Word)
+            .fold_ok(prologue_len, |acc, instruction_len| acc + instruction_len)?;
+
+        let data_base = match &self.platform {
+            Some(platform) => {
+                let code = platform.code_region();
+                let data = platform.data_region();
+
+                if code_len as u32 > code.size {
+                    return Err(anyhow!(
+                        "{} bytes of code don't fit in the {} bytes of region '{}'",
+                        code_len,
+                        code.size,
+                        code.name
+                    ));
+                }
+
+                // Sharing the code region only matters when code and data share a memory.
+                let data_base = match self.layout == MemoryLayout::VonNeumann && code == data {
+                    true => code.addr + code_len as u32,
+                    false => data.addr,
+                };
+
+                let data_end = self
+                    .alloc_map
+                    .0
+                    .values()
+                    .filter_map(|alloc| match alloc {
+                        VarAlloc::Memory(mem_alloc) => {
+                            Some(mem_alloc.0 as u32 + libisa::BYTES_PER_WORD as u32)
+                        }
+                        VarAlloc::Register(..) => None,
+                    })
+                    .max()
+                    .unwrap_or(0);
+
+                if data_base + data_end > data.range().end {
+                    return Err(anyhow!(
+                        "{} bytes of variables don't fit in region '{}' from 0x{:04x}",
+                        data_end,
+                        data.name,
+                        data_base
+                    ));
+                }
+
+                data_base as Word
+            }
+            None => code_len,
+        };
 
         // Scary access to the alloc map, let's not fuck anything up as I have a tendency to O_O
         let mem_allocs = self
@@ -33,50 +95,10 @@ impl AllocTransformer {
         // TODO: Explicit addresses shouldn't be changed here when they get implemented.
         // Quite safe to assume for this project that the backend doesn't do any polymorphism that this would break.
         for mem_alloc in mem_allocs {
-            mem_alloc.0 += code_len;
+            mem_alloc.0 += data_base;
         }
 
+        self.data_base = data_base;
         Ok(())
     }
-
-    fn compute_instruction_code_len(
-        &self,
-        instruction: &PreallocInstruction,
-    ) -> anyhow::Result<Word> {
-        Ok(match instruction {
-            PreallocInstruction::DefineVar(..)
-            | PreallocInstruction::ExplicitRegister { .. }
-            | PreallocInstruction::ExplicitMemory { .. } => 0,
-
-            PreallocInstruction::LoadVar { src, .. } => {
-                let src_alloc = self.var(&src).context("src").context("LoadVar")?;
-
-                match src_alloc {
-                    VarAlloc::Register(..) => 1,
-                    VarAlloc::Memory(..) => 2,
-                }
-            }
-
-            PreallocInstruction::StoreVar { dest, .. } => {
-                let dest_alloc = self.var(&dest).context("dest").context("StoreVar")?;
-
-                match dest_alloc {
-                    VarAlloc::Register(..) => 1,
-                    VarAlloc::Memory(..) => 2,
-                }
-            }
-
-            PreallocInstruction::TargetPassthrough { instructions } => instructions.len() as Word,
-
-            PreallocInstruction::LoadImmediate { .. }
-            | PreallocInstruction::Jmp(..)
-            | PreallocInstruction::JmpC(..)
-            | PreallocInstruction::JmpZ(..)
-            | PreallocInstruction::Add(..)
-            | PreallocInstruction::Sub(..)
-            | PreallocInstruction::AddC(..)
-            | PreallocInstruction::SubC(..)
-            | PreallocInstruction::And(..) => 1,
-        })
-    }
 }
@@ -3,20 +3,38 @@ mod prealloc;
 
 use alloc::AllocTransformer;
 use libisa::instruction::Instruction as TargetInstruction;
+use libplatform::Platform;
 use prealloc::codegen::PreallocCodegenTransformer;
 
 use crate::{
+    backend::strm1::MemoryLayout,
     lir::{shim::cmp::CmpShimTransformer, LIRInstruction},
     transformer::{
         chain::TransformerChainExt, extra::Extras, runner::TransformerRunnerExt, Transformer,
     },
 };
 
-pub struct CodegenTransformer {}
+pub struct CodegenTransformer {
+    layout: MemoryLayout,
+    platform: Option<Platform>,
+}
 
 impl CodegenTransformer {
     pub fn new() -> Self {
-        Self {}
+        Self {
+            layout: MemoryLayout::default(),
+            platform: None,
+        }
+    }
+
+    pub fn with_layout(mut self, layout: MemoryLayout) -> Self {
+        self.layout = layout;
+        self
+    }
+
+    pub fn with_platform(mut self, platform: Option<Platform>) -> Self {
+        self.platform = platform;
+        self
     }
 }
 
@@ -26,8 +44,12 @@ impl Transformer for CodegenTransformer {
 
     fn transform(&mut self, input: Extras<Self::Input>) -> anyhow::Result<Extras<Self::Output>> {
         (CmpShimTransformer) // Remember to remove if codegen learns all the cmp tricks.
-            .chain(PreallocCodegenTransformer {})
-            .chain(AllocTransformer::new())
+            .chain(PreallocCodegenTransformer::default())
+            .chain(
+                AllocTransformer::new()
+                    .with_layout(self.layout)
+                    .with_platform(self.platform.clone()),
+            )
             .runner()
             .run_with_extras(input)
     }
@@ -1,15 +1,18 @@
+use std::collections::{HashMap, HashSet};
+
 use anyhow::anyhow;
 use itertools::Itertools;
 use lazy_static::lazy_static;
 use libdeassembler::Deassembler;
+use libisa::Word;
 
 use crate::{
     backend::strm1::codegen::prealloc::{RegVarKey, VarId, VarKey},
     lir::{LIRInstruction, LIRValue, LIRVarId},
-    transformer::{extra::Extras, Transformer},
+    transformer::{extra::Extras, PrepassFn, Transformer},
 };
 
-use super::{varidspace::VarIdSpace, PreallocInstruction};
+use super::{varidspace::VarIdSpace, Immediate, PreallocInstruction};
 
 lazy_static! {
     // Public for VarAllocTest
@@ -21,12 +24,25 @@ lazy_static! {
     static ref SECOND_INTERNAL_VAR_SPACE: VarIdSpace = VarIdSpace::new();
 }
 
-pub struct PreallocCodegenTransformer {}
+#[derive(Debug, Default)]
+pub struct PreallocCodegenTransformer {
+    /// LIR variables assigned so far. A variable may be assigned more than once, but is only defined on the first.
+    defined_lir_vars: HashSet<LIRVarId>,
+
+    /// Constant values of LIR variables only ever assigned once by a `Const`, usable as instruction immediates.
+    constant_lir_vars: HashMap<LIRVarId, Immediate>,
+
+    /// Constant LIR variables only ever used as immediates, which are left out of the code altogether.
+    folded_lir_vars: HashSet<LIRVarId>,
+}
 
 impl Transformer for PreallocCodegenTransformer {
     type Input = Vec<LIRInstruction>;
     type Output = Vec<PreallocInstruction>;
 
+    const PREPASSES: &[(&'static str, PrepassFn<Self>)] =
+        &[("constant variable prepass", Self::constant_prepass)];
+
     fn transform(&mut self, input: Extras<Self::Input>) -> anyhow::Result<Extras<Self::Output>> {
         input.try_map_data(|lir| {
             lir.into_iter()
@@ -41,33 +57,110 @@ impl Transformer for PreallocCodegenTransformer {
 }
 
 impl PreallocCodegenTransformer {
+    fn constant_prepass(&mut self, input: &Extras<Vec<LIRInstruction>>) -> anyhow::Result<()> {
+        let mut assignment_counts: HashMap<LIRVarId, usize> = HashMap::new();
+
+        for instruction in &input.data {
+            for id in instruction.introduced_var_ids() {
+                *assignment_counts.entry(*id).or_default() += 1;
+            }
+        }
+
+        self.constant_lir_vars = input
+            .data
+            .iter()
+            .filter_map(|instruction| match instruction {
+                LIRInstruction::Const { id, value } if assignment_counts.get(id) == Some(&1) => {
+                    Some((*id, Self::immediate(*value)?))
+                }
+                _ => None,
+            })
+            .collect();
+
+        // Whether every use of the variable is folded into an immediate, by variable.
+        let mut folded_uses: HashMap<LIRVarId, bool> = HashMap::new();
+
+        for instruction in &input.data {
+            for (id, folded) in self.operand_uses(instruction) {
+                *folded_uses.entry(id).or_insert(true) &= folded;
+            }
+        }
+
+        self.folded_lir_vars = folded_uses
+            .into_iter()
+            .filter(|(_, folded)| *folded)
+            .map(|(id, _)| id)
+            .collect();
+
+        Ok(())
+    }
+
+    /// The variables the instruction reads, along with whether each is folded into an immediate instead of read.
+    /// This has to agree with what `transform_instruction` picks.
+    fn operand_uses(&self, instruction: &LIRInstruction) -> Vec<(LIRVarId, bool)> {
+        match *instruction {
+            LIRInstruction::Copy { src, .. } => vec![(src, false)],
+
+            LIRInstruction::Add { a, b, .. } => {
+                match (self.constant_value(a), self.constant_value(b)) {
+                    (_, Some(..)) => vec![(a, false), (b, true)],
+                    (Some(..), None) => vec![(a, true), (b, false)],
+                    (None, None) => vec![(a, false), (b, false)],
+                }
+            }
+
+            LIRInstruction::Sub { a, b, .. } => {
+                vec![(a, false), (b, self.constant_value(b).is_some())]
+            }
+
+            LIRInstruction::Mul { a, b, .. } => vec![(a, false), (b, false)],
+
+            LIRInstruction::Branch { addr } => vec![(addr, self.constant(addr).is_some())],
+            LIRInstruction::BranchZero { addr, test } => {
+                vec![(addr, self.constant(addr).is_some()), (test, false)]
+            }
+            LIRInstruction::BranchEqual { addr, a, b } => {
+                vec![(addr, false), (a, false), (b, false)]
+            }
+
+            LIRInstruction::Const { .. }
+            | LIRInstruction::Label { .. }
+            | LIRInstruction::NativeMachinecode { .. } => vec![],
+        }
+    }
+
     fn transform_instruction(
         &mut self,
         instruction_index: usize,
         instruction: LIRInstruction,
     ) -> anyhow::Result<Vec<PreallocInstruction>> {
         Ok(match instruction {
+            // Only ever used as an immediate, so there's no need for the variable.
+            LIRInstruction::Const { id, .. } if self.folded_lir_vars.contains(&id) => vec![],
+
             LIRInstruction::Const { id, value } => {
                 let tmp_reg_key = Self::internal_reg_key(instruction_index);
                 let out_var_key = Self::lir_var_key(id);
 
-                match value {
-                    LIRValue::Uint16(value) => vec![
+                match Self::immediate(value) {
+                    Some(value) => [
                         // First load the value to a temporary register...
                         PreallocInstruction::DefineVar(VarKey::Register(tmp_reg_key)),
                         PreallocInstruction::LoadImmediate {
                             dest: tmp_reg_key,
                             value,
                         },
-                        // ...and then store to the new output variable.
-                        PreallocInstruction::DefineVar(out_var_key),
-                        PreallocInstruction::StoreVar {
-                            dest: out_var_key,
-                            src: tmp_reg_key,
-                        },
-                    ],
+                    ]
+                    .into_iter()
+                    // ...and then store to the output variable.
+                    .chain(self.define_lir_var(id))
+                    .chain([PreallocInstruction::StoreVar {
+                        dest: out_var_key,
+                        src: tmp_reg_key,
+                    }])
+                    .collect(),
 
-                    _ => todo!(),
+                    None => todo!(),
                 }
             }
 
@@ -76,83 +169,129 @@ impl PreallocCodegenTransformer {
                 let out_var_key = Self::lir_var_key(id);
                 let src_var_key = Self::lir_var_key(src);
 
-                vec![
+                [
                     // First load the source value to a temporary register...
                     PreallocInstruction::DefineVar(VarKey::Register(tmp_reg_key)),
                     PreallocInstruction::LoadVar {
                         dest: tmp_reg_key,
                         src: src_var_key,
                     },
-                    // ...and then store to the new output variable.
-                    PreallocInstruction::DefineVar(out_var_key),
-                    PreallocInstruction::StoreVar {
-                        dest: out_var_key,
-                        src: tmp_reg_key,
-                    },
                 ]
+                .into_iter()
+                // ...and then store to the output variable.
+                .chain(self.define_lir_var(id))
+                .chain([PreallocInstruction::StoreVar {
+                    dest: out_var_key,
+                    src: tmp_reg_key,
+                }])
+                .collect()
             }
 
             LIRInstruction::Add { id, a, b } => {
-                self.transform_dual_operand(instruction_index, id, a, b, |a, b| {
-                    PreallocInstruction::Add(a, b)
-                })
+                // Addition is commutative, so either operand can be the immediate.
+                match (self.constant_value(a), self.constant_value(b)) {
+                    (_, Some(value)) => {
+                        self.transform_immediate_operand(instruction_index, id, a, |a| {
+                            PreallocInstruction::AddI(a, value)
+                        })
+                    }
+                    (Some(value), None) => {
+                        self.transform_immediate_operand(instruction_index, id, b, |b| {
+                            PreallocInstruction::AddI(b, value)
+                        })
+                    }
+                    (None, None) => {
+                        self.transform_dual_operand(instruction_index, id, a, b, |a, b| {
+                            PreallocInstruction::Add(a, b)
+                        })
+                    }
+                }
             }
 
-            LIRInstruction::Sub { id, a, b } => {
-                self.transform_dual_operand(instruction_index, id, a, b, |a, b| {
+            LIRInstruction::Sub { id, a, b } => match self.constant_value(b) {
+                Some(value) => self.transform_immediate_operand(instruction_index, id, a, |a| {
+                    PreallocInstruction::SubI(a, value)
+                }),
+                None => self.transform_dual_operand(instruction_index, id, a, b, |a, b| {
                     PreallocInstruction::Sub(a, b)
-                })
-            }
+                }),
+            },
 
             LIRInstruction::Mul { id, a, b } => {
                 todo!()
             }
 
-            LIRInstruction::Branch { addr } => {
-                let addr_var_key = Self::lir_var_key(addr);
-                let tmp_addr_reg_key = Self::internal_reg_key(instruction_index);
+            LIRInstruction::Branch { addr } => match self.constant(addr) {
+                Some(target) => vec![PreallocInstruction::JmpI(target)],
+                None => {
+                    let addr_var_key = Self::lir_var_key(addr);
+                    let tmp_addr_reg_key = Self::internal_reg_key(instruction_index);
 
-                vec![
-                    // Load the address variable to a temporary register and jump to it.
-                    PreallocInstruction::DefineVar(VarKey::Register(tmp_addr_reg_key)),
-                    PreallocInstruction::LoadVar {
-                        dest: tmp_addr_reg_key,
-                        src: addr_var_key,
-                    },
-                    PreallocInstruction::Jmp(tmp_addr_reg_key),
-                ]
-            }
+                    vec![
+                        // Load the address variable to a temporary register and jump to it.
+                        PreallocInstruction::DefineVar(VarKey::Register(tmp_addr_reg_key)),
+                        PreallocInstruction::LoadVar {
+                            dest: tmp_addr_reg_key,
+                            src: addr_var_key,
+                        },
+                        PreallocInstruction::Jmp(tmp_addr_reg_key),
+                    ]
+                }
+            },
 
-            LIRInstruction::BranchZero { addr, test } => {
-                let addr_var_key = Self::lir_var_key(addr);
-                let test_var_key = Self::lir_var_key(test);
+            LIRInstruction::BranchZero { addr, test } => match self.constant(addr) {
+                Some(target) => {
+                    let test_var_key = Self::lir_var_key(test);
+                    let tmp_test_reg_key = Self::internal_reg_key(instruction_index);
 
-                let tmp_addr_reg_key = Self::internal_reg_key(instruction_index);
-                let tmp_test_reg_key =
-                    Self::custom_internal_reg_key(&SECOND_INTERNAL_VAR_SPACE, instruction_index);
+                    vec![
+                        // Load the test variable to a temporary register so its flags can be loaded.
+                        PreallocInstruction::DefineVar(VarKey::Register(tmp_test_reg_key)),
+                        PreallocInstruction::LoadVar {
+                            dest: tmp_test_reg_key,
+                            src: test_var_key,
+                        },
+                        // And the test register with itself to load its flags.
+                        PreallocInstruction::And(tmp_test_reg_key, tmp_test_reg_key),
+                        // Finally jump straight to the address if the zero flag was set.
+                        PreallocInstruction::JmpZI(target),
+                    ]
+                }
+                None => {
+                    let addr_var_key = Self::lir_var_key(addr);
+                    let test_var_key = Self::lir_var_key(test);
 
-                vec![
-                    // Load the address variable to a temporary register so it can be jumped to if the branch is taken.
-                    PreallocInstruction::DefineVar(VarKey::Register(tmp_addr_reg_key)),
-                    PreallocInstruction::LoadVar {
-                        dest: tmp_addr_reg_key,
-                        src: addr_var_key,
-                    },
-                    // Load the test variable to a temporary register so its flags can be loaded.
-                    PreallocInstruction::DefineVar(VarKey::Register(tmp_test_reg_key)),
-                    PreallocInstruction::LoadVar {
-                        dest: tmp_test_reg_key,
-                        src: test_var_key,
-                    },
-                    // And the test register with itself to load its flags.
-                    PreallocInstruction::And(tmp_test_reg_key, tmp_test_reg_key),
-                    // Finally jump by the address register if the zero flag was set.
-                    PreallocInstruction::JmpZ(tmp_addr_reg_key),
-                ]
-            }
+                    let tmp_addr_reg_key = Self::internal_reg_key(instruction_index);
+                    let tmp_test_reg_key = Self::custom_internal_reg_key(
+                        &SECOND_INTERNAL_VAR_SPACE,
+                        instruction_index,
+                    );
+
+                    vec![
+                        // Load the address variable to a temporary register so it can be jumped to if the branch is taken.
+                        PreallocInstruction::DefineVar(VarKey::Register(tmp_addr_reg_key)),
+                        PreallocInstruction::LoadVar {
+                            dest: tmp_addr_reg_key,
+                            src: addr_var_key,
+                        },
+                        // Load the test variable to a temporary register so its flags can be loaded.
+                        PreallocInstruction::DefineVar(VarKey::Register(tmp_test_reg_key)),
+                        PreallocInstruction::LoadVar {
+                            dest: tmp_test_reg_key,
+                            src: test_var_key,
+                        },
+                        // And the test register with itself to load its flags.
+                        PreallocInstruction::And(tmp_test_reg_key, tmp_test_reg_key),
+                        // Finally jump by the address register if the zero flag was set.
+                        PreallocInstruction::JmpZ(tmp_addr_reg_key),
+                    ]
+                }
+            },
 
             LIRInstruction::BranchEqual { .. } => unimplemented!("Relying on cmp shim"),
 
+            LIRInstruction::Label { id } => vec![PreallocInstruction::Label(id)],
+
             LIRInstruction::NativeMachinecode { code } => {
                 let deassembler = Deassembler::new(code.iter());
 
@@ -166,7 +305,7 @@ impl PreallocCodegenTransformer {
     }
 
     fn transform_dual_operand<F>(
-        &self,
+        &mut self,
         instruction_index: usize,
         out_id: u64,
         a_id: u64,
@@ -184,7 +323,7 @@ impl PreallocCodegenTransformer {
         let tmp_b_reg_key =
             Self::custom_internal_reg_key(&SECOND_INTERNAL_VAR_SPACE, instruction_index);
 
-        vec![
+        [
             // Load the A variable to the A tmp register.
             PreallocInstruction::DefineVar(VarKey::Register(tmp_a_reg_key)),
             PreallocInstruction::LoadVar {
@@ -199,11 +338,77 @@ impl PreallocCodegenTransformer {
             },
             // Run the operation on the A and B tmp registers and then store the output from the A tmp register.
             op_callback(tmp_a_reg_key, tmp_b_reg_key),
-            PreallocInstruction::StoreVar {
-                dest: out_var_key,
-                src: tmp_a_reg_key,
+        ]
+        .into_iter()
+        .chain(self.define_lir_var(out_id))
+        .chain([PreallocInstruction::StoreVar {
+            dest: out_var_key,
+            src: tmp_a_reg_key,
+        }])
+        .collect()
+    }
+
+    fn transform_immediate_operand<F>(
+        &mut self,
+        instruction_index: usize,
+        out_id: u64,
+        a_id: u64,
+        op_callback: F,
+    ) -> Vec<PreallocInstruction>
+    where
+        F: FnOnce(RegVarKey) -> PreallocInstruction,
+    {
+        let out_var_key = Self::lir_var_key(out_id);
+        let a_var_key = Self::lir_var_key(a_id);
+
+        let tmp_a_reg_key = Self::internal_reg_key(instruction_index);
+
+        [
+            // Load the A variable to the A tmp register.
+            PreallocInstruction::DefineVar(VarKey::Register(tmp_a_reg_key)),
+            PreallocInstruction::LoadVar {
+                dest: tmp_a_reg_key,
+                src: a_var_key,
             },
+            // Run the operation on the A tmp register with the immediate and then store the output from it.
+            op_callback(tmp_a_reg_key),
         ]
+        .into_iter()
+        .chain(self.define_lir_var(out_id))
+        .chain([PreallocInstruction::StoreVar {
+            dest: out_var_key,
+            src: tmp_a_reg_key,
+        }])
+        .collect()
+    }
+
+    /// The value of the LIR variable if it's a constant that can be used as an immediate.
+    fn constant(&self, lir_id: LIRVarId) -> Option<Immediate> {
+        self.constant_lir_vars.get(&lir_id).copied()
+    }
+
+    /// The value of the LIR variable if it's a constant known before the code is laid out, unlike label addresses.
+    fn constant_value(&self, lir_id: LIRVarId) -> Option<Word> {
+        match self.constant(lir_id)? {
+            Immediate::Value(value) => Some(value),
+            Immediate::Label(..) => None,
+        }
+    }
+
+    /// The immediate a LIR value is loaded as, if there is one for its type.
+    fn immediate(value: LIRValue) -> Option<Immediate> {
+        match value {
+            LIRValue::Uint16(value) => Some(Immediate::Value(value)),
+            LIRValue::Label(label) => Some(Immediate::Label(label)),
+            LIRValue::Uint8(..) => None,
+        }
+    }
+
+    /// Define the LIR variable if this is its first assignment.
+    fn define_lir_var(&mut self, lir_id: LIRVarId) -> Option<PreallocInstruction> {
+        self.defined_lir_vars
+            .insert(lir_id)
+            .then(|| PreallocInstruction::DefineVar(Self::lir_var_key(lir_id)))
     }
 
     fn lir_var_key(lir_id: LIRVarId) -> VarKey {
@@ -22,6 +22,8 @@ use libisa::{instruction::Instruction as TargetInstruction, Register, Word};
 use serde::{Deserialize, Serialize};
 use varidspace::VarIdSpace;
 
+use crate::lir::LIRLabelId;
+
 pub mod codegen;
 pub mod varidspace;
 
@@ -50,6 +52,14 @@ pub enum VarKey {
     Memory(MemVarKey),
 }
 
+/// Immediate operand of an instruction, either a value or the address of a label, which is only known once the code
+/// is laid out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Immediate {
+    Value(Word),
+    Label(LIRLabelId),
+}
+
 /// A code representation based on target machine code that abstracts away some of the register/memory use
 /// and register allocation. This is essentially a middle ground between LIR and target machine code, with
 /// most of the actual code generation emitting to this and register and memory allocations happening after that.
@@ -72,7 +82,7 @@ pub enum PreallocInstruction {
     /// Load an immediate value to the destination register variable
     LoadImmediate {
         dest: RegVarKey,
-        value: Word,
+        value: Immediate,
     },
 
     /// Load a variable to the destination register variable
@@ -91,6 +101,11 @@ pub enum PreallocInstruction {
     JmpC(RegVarKey),
     JmpZ(RegVarKey),
 
+    /// Jumps to a statically known address.
+    JmpI(Immediate),
+    JmpCI(Immediate),
+    JmpZI(Immediate),
+
     Add(RegVarKey, RegVarKey),
     Sub(RegVarKey, RegVarKey),
     AddC(RegVarKey, RegVarKey),
@@ -98,6 +113,13 @@ pub enum PreallocInstruction {
 
     And(RegVarKey, RegVarKey),
 
+    AddI(RegVarKey, Word),
+    SubI(RegVarKey, Word),
+    AndI(RegVarKey, Word),
+
+    /// Mark the position of the instruction following it as the address of the LIR label.
+    Label(LIRLabelId),
+
     // TODO: Should high/low byte loads/stores be implemented here (types handled in LIR->prealloc transformation),
     //       or should types be brought into this representation (types handled in prealloc->target transformation)?
     TargetPassthrough {
@@ -127,13 +149,33 @@ impl PreallocInstruction {
             | Self::SubC(a, b)
             | Self::And(a, b) => vec![a.id(), b.id()],
 
+            Self::AddI(a, _) | Self::SubI(a, _) | Self::AndI(a, _) => vec![a.id()],
+
             // Not matching the all the rest with _ because I would forget to update this without the compile time error.
             Self::DefineVar(..)
             | Self::ExplicitMemory { .. }
             | Self::ExplicitRegister { .. }
+            | Self::JmpI(..)
+            | Self::JmpCI(..)
+            | Self::JmpZI(..)
+            | Self::Label(..)
             | Self::TargetPassthrough { .. } => vec![],
         }
     }
+
+    /// Returns the label whose address this instruction has as an immediate, if any.
+    pub fn referenced_label(&self) -> Option<LIRLabelId> {
+        match self {
+            Self::LoadImmediate { value, .. }
+            | Self::JmpI(value)
+            | Self::JmpCI(value)
+            | Self::JmpZI(value) => match value {
+                Immediate::Value(..) => None,
+                Immediate::Label(label) => Some(*label),
+            },
+            _ => None,
+        }
+    }
 }
 
 pub trait VarTrait {
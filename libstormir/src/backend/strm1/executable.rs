@@ -0,0 +1,62 @@
+use libisa::{
+    executable::{Executable, Permissions, Segment},
+    Word,
+};
+
+use crate::transformer::{
+    extra::{Extras, SUFFIX_MSGPACK},
+    Transformer,
+};
+
+use super::machinecode::EXTRAS_BYTE_TO_INSTRUCTION_INDEX_MAP_KEY;
+
+/// Symbol of the entry point in the executables written.
+pub const ENTRY_SYMBOL: &str = "_start";
+
+/// Wraps machine code in a STRM1 executable, the code being a single executable segment, by default at address 0
+/// which is also the entry point. The byte to instruction index mapping goes along as msgpack debug information.
+#[derive(Debug, Clone, Default)]
+pub struct ExecutableTransformer {
+    code_addr: Word,
+    entry: Word,
+}
+
+impl ExecutableTransformer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Place the code segment at `code_addr` and start execution from `entry`, e.g. a platform's reset PC.
+    pub fn with_placement(mut self, code_addr: Word, entry: Word) -> Self {
+        self.code_addr = code_addr;
+        self.entry = entry;
+        self
+    }
+}
+
+impl Transformer for ExecutableTransformer {
+    type Input = Vec<u8>;
+    type Output = Vec<u8>;
+
+    fn transform(&mut self, input: Extras<Self::Input>) -> anyhow::Result<Extras<Self::Output>> {
+        let debug = input
+            .extra_raw(&[EXTRAS_BYTE_TO_INSTRUCTION_INDEX_MAP_KEY, SUFFIX_MSGPACK].concat())
+            .cloned();
+
+        Ok(input.try_map_data(|machine_code| {
+            let executable = Executable::new(self.entry)
+                .with_segment(Segment::new(
+                    self.code_addr,
+                    Permissions::READ_EXECUTE,
+                    machine_code,
+                ))
+                .with_symbol(ENTRY_SYMBOL, self.entry);
+
+            match debug {
+                Some(debug) => executable.with_debug(debug),
+                None => executable,
+            }
+            .to_bytes()
+        })?)
+    }
+}
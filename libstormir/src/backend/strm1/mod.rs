@@ -1,4 +1,10 @@
 use codegen::CodegenTransformer;
+use executable::ExecutableTransformer;
+use libisa::{
+    memimage::{self, ImageFormat, ImageLayout},
+    Word,
+};
+use libplatform::Platform;
 use machinecode::MachinecodeTransformer;
 
 use crate::{
@@ -9,16 +15,67 @@ use crate::{
 };
 
 mod codegen;
+pub mod executable;
 mod machinecode;
 
 #[cfg(test)]
 mod tests;
 
-pub struct STRM1Transformer;
+/// Where the variables kept in memory are placed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MemoryLayout {
+    /// Code and data share a memory, the variables being placed right after the code.
+    #[default]
+    VonNeumann,
+
+    /// Code and data are in separate memories, the variables being placed from address 0 of data memory.
+    Harvard,
+}
+
+/// Container the machine code is output in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutputFormat {
+    /// Bare machine code, loaded at and run from address 0.
+    #[default]
+    Raw,
+
+    /// STRM1 executable with a header describing the entry point and the code segment.
+    Executable,
+
+    /// Intel HEX or `$readmemh` image of the raw machine code, for initialising FPGA block RAM.
+    MemoryImage(ImageFormat, ImageLayout),
+}
+
+pub struct STRM1Transformer {
+    layout: MemoryLayout,
+    output_format: OutputFormat,
+    platform: Option<Platform>,
+}
 
 impl STRM1Transformer {
     pub fn new() -> Self {
-        Self {}
+        Self {
+            layout: MemoryLayout::default(),
+            output_format: OutputFormat::default(),
+            platform: None,
+        }
+    }
+
+    pub fn with_layout(mut self, layout: MemoryLayout) -> Self {
+        self.layout = layout;
+        self
+    }
+
+    pub fn with_output_format(mut self, output_format: OutputFormat) -> Self {
+        self.output_format = output_format;
+        self
+    }
+
+    /// Place code and variables in the platform's regions, leave its reserved registers alone and start executables
+    /// from its reset PC. Without a platform, code starts at address 0 with the variables right after it.
+    pub fn with_platform(mut self, platform: Platform) -> Self {
+        self.platform = Some(platform);
+        self
     }
 }
 
@@ -27,9 +84,26 @@ impl Transformer for STRM1Transformer {
     type Output = Vec<u8>;
 
     fn transform(&mut self, input: Extras<Self::Input>) -> anyhow::Result<Extras<Self::Output>> {
-        CodegenTransformer::new()
+        let machine_code = CodegenTransformer::new()
+            .with_layout(self.layout)
+            .with_platform(self.platform.clone())
             .chain(MachinecodeTransformer)
             .runner()
-            .run_with_extras(input)
+            .run_with_extras(input)?;
+
+        match self.output_format {
+            OutputFormat::Raw => Ok(machine_code),
+            OutputFormat::Executable => {
+                let mut executable = match &self.platform {
+                    Some(platform) => ExecutableTransformer::new()
+                        .with_placement(platform.code_region().addr as Word, platform.reset_pc),
+                    None => ExecutableTransformer::new(),
+                };
+
+                executable.runner().run_with_extras(machine_code)
+            }
+            OutputFormat::MemoryImage(format, layout) => Ok(machine_code
+                .try_map_data(|machine_code| memimage::export(&machine_code, format, layout))?),
+        }
     }
 }
@@ -1,13 +1,27 @@
 /* Tests that verify behavior of compiled LIR by emulating the output and examining the emulator's state. */
 
 use anyhow::{anyhow, Context};
+use libdeassembler::Deassembler;
+use libemulator::Emulator;
+use libisa::{
+    executable::Executable,
+    instruction::{kind::InstructionKind, Instruction},
+    memimage::{self, ImageFormat, ImageLayout},
+};
+use libplatform::Platform;
 
 use crate::{
-    backend::strm1::codegen::alloc::tests::TestEmulateExt,
+    backend::strm1::{
+        codegen::alloc::tests::{EmulatorTest, TestEmulateExt},
+        executable::ENTRY_SYMBOL,
+        MemoryLayout, OutputFormat, STRM1Transformer,
+    },
+    frontend::strm1::{register_var_id, STRM1LiftTransformer},
     lir::{LIRInstruction, LIRValue},
+    transformer::runner::TransformerRunnerExt,
 };
 
-use super::{Test, LIR_HALT};
+use super::{Test, LIR_HALT, PLATFORM};
 
 #[test]
 fn simple_halt() {
@@ -47,3 +61,396 @@ fn variable_assignment_ignorant() {
         return Ok(());
     });
 }
+
+#[test]
+fn lifted_machinecode_round_trip() {
+    let code = libisa::instruction::assembler::assemble([
+        Instruction::new(InstructionKind::LoadI)
+            .with_reg_a(0)
+            .with_immediate(5),
+        Instruction::new(InstructionKind::LoadI)
+            .with_reg_a(1)
+            .with_immediate(7),
+        Instruction::new(InstructionKind::Add)
+            .with_reg_a(0)
+            .with_reg_b(1),
+        Instruction::new(InstructionKind::Halt),
+    ])
+    .unwrap()
+    .machine_code;
+
+    let lir = STRM1LiftTransformer::new()
+        .runner()
+        .run(code)
+        .expect("Error lifting machine code")
+        .data;
+
+    Test::new("lifted_machinecode_round_trip", lir).emulate_dump_panicking(|test| {
+        test.run_till_halt()?;
+
+        let sum = test
+            .get_var_ignorant(register_var_id(0))
+            .context("Variable wasn't found")?;
+
+        if sum != 12 {
+            return Err(anyhow!("Sum {} differs from expected 12", sum));
+        }
+
+        Ok(())
+    });
+}
+
+#[test]
+fn lifted_branches_round_trip() {
+    let code = libisa::instruction::assembler::assemble([
+        Instruction::new(InstructionKind::LoadI)
+            .with_reg_a(0)
+            .with_immediate(5),
+        Instruction::new(InstructionKind::LoadI)
+            .with_reg_a(1)
+            .with_immediate(0),
+        Instruction::new(InstructionKind::And)
+            .with_reg_a(1)
+            .with_reg_b(1),
+        // Skips the loadi of 0xBAD, the zero flag being set.
+        Instruction::new(InstructionKind::JmpZI).with_immediate(18),
+        Instruction::new(InstructionKind::LoadI)
+            .with_reg_a(0)
+            .with_immediate(0xBAD),
+        // Skips another loadi of 0xBAD, through a register this time.
+        Instruction::new(InstructionKind::LoadI)
+            .with_reg_a(2)
+            .with_immediate(28),
+        Instruction::new(InstructionKind::Jmp).with_reg_a(2),
+        Instruction::new(InstructionKind::LoadI)
+            .with_reg_a(0)
+            .with_immediate(0xBAD),
+        Instruction::new(InstructionKind::AddI)
+            .with_reg_a(0)
+            .with_immediate(7),
+        Instruction::new(InstructionKind::Halt),
+    ])
+    .unwrap()
+    .machine_code;
+
+    let lir = STRM1LiftTransformer::new()
+        .runner()
+        .run(code)
+        .expect("Error lifting machine code")
+        .data;
+
+    // The compiled code is laid out differently, so the original addresses would land elsewhere.
+    Test::new("lifted_branches_round_trip", lir).emulate_dump_panicking(|test| {
+        test.run_till_halt()?;
+
+        let sum = test
+            .get_var_ignorant(register_var_id(0))
+            .context("Variable wasn't found")?;
+
+        if sum != 12 {
+            return Err(anyhow!("Sum {} differs from expected 12", sum));
+        }
+
+        Ok(())
+    });
+}
+
+#[test]
+fn constant_operand_selects_immediate_form() {
+    let program = [
+        LIRInstruction::Const {
+            id: 1,
+            value: LIRValue::Uint16(30),
+        },
+        LIRInstruction::Const {
+            id: 2,
+            value: LIRValue::Uint16(12),
+        },
+        LIRInstruction::Sub { id: 3, a: 1, b: 2 },
+        LIR_HALT.clone(),
+    ];
+
+    let test = Test::new("constant_operand_selects_immediate_form", program);
+
+    let instructions = Deassembler::new(test.compilation_output.data.iter())
+        .deassemble()
+        .expect("Error deassembling compiled program");
+
+    assert!(instructions.iter().any(|instruction| {
+        instruction.kind == InstructionKind::SubI && instruction.immediate == Some(12)
+    }));
+
+    // The subtrahend is only ever used as an immediate, so it isn't loaded to a register of its own.
+    assert!(!instructions.iter().any(|instruction| {
+        instruction.kind == InstructionKind::LoadI && instruction.immediate == Some(12)
+    }));
+
+    test.emulate_dump_panicking(|test| {
+        test.run_till_halt()?;
+
+        let difference = test.get_var_ignorant(3).context("Variable wasn't found")?;
+
+        if difference != 18 {
+            return Err(anyhow!(
+                "Difference {} differs from expected 18",
+                difference
+            ));
+        }
+
+        Ok(())
+    });
+}
+
+#[test]
+fn constant_branch_jumps_directly() {
+    let program = |target| {
+        [
+            LIRInstruction::Const {
+                id: 1,
+                value: LIRValue::Uint16(target),
+            },
+            LIRInstruction::Const {
+                id: 2,
+                value: LIRValue::Uint16(0),
+            },
+            LIRInstruction::Branch { addr: 1 },
+            LIRInstruction::Const {
+                id: 2,
+                value: LIRValue::Uint16(0xBAD),
+            },
+            LIR_HALT.clone(),
+        ]
+    };
+
+    // The code length doesn't depend on the target, so the halt's address can be found with any target.
+    let halt_addr = Test::new("constant_branch_jumps_directly", program(0))
+        .compilation_output
+        .data
+        .len()
+        - libisa::BYTES_PER_WORD;
+
+    let test = Test::new("constant_branch_jumps_directly", program(halt_addr as u16));
+
+    let instructions = Deassembler::new(test.compilation_output.data.iter())
+        .deassemble()
+        .expect("Error deassembling compiled program");
+
+    assert!(instructions
+        .contains(&Instruction::new(InstructionKind::JmpI).with_immediate(halt_addr as u16)));
+
+    // The target is only ever jumped to directly, so it isn't loaded to a register of its own.
+    assert!(!instructions.iter().any(|instruction| {
+        instruction.kind == InstructionKind::LoadI
+            && instruction.immediate == Some(halt_addr as u16)
+    }));
+
+    test.emulate_dump_panicking(|test| {
+        test.run_till_halt()?;
+
+        let skipped = test.get_var_ignorant(2).context("Variable wasn't found")?;
+
+        if skipped != 0 {
+            return Err(anyhow!("Code after the branch was executed"));
+        }
+
+        Ok(())
+    });
+}
+
+const SPILLED_VAR_COUNT: u16 = 20;
+const SPILLED_SUM_ID: u64 = 100;
+
+/// Program with more variables alive at once than there are registers, summing them up into `SPILLED_SUM_ID`.
+fn spilled_variables_program() -> Vec<LIRInstruction> {
+    // Assigned twice so they aren't constants, and all alive at once so they don't fit in the registers.
+    let assignments = (1..=SPILLED_VAR_COUNT).flat_map(|id| {
+        [
+            LIRInstruction::Const {
+                id: id as u64,
+                value: LIRValue::Uint16(0),
+            },
+            LIRInstruction::Const {
+                id: id as u64,
+                value: LIRValue::Uint16(id),
+            },
+        ]
+    });
+
+    let sum = (2..=SPILLED_VAR_COUNT).map(|id| LIRInstruction::Add {
+        id: SPILLED_SUM_ID,
+        a: SPILLED_SUM_ID,
+        b: id as u64,
+    });
+
+    assignments
+        .chain([LIRInstruction::Copy {
+            id: SPILLED_SUM_ID,
+            src: 1,
+        }])
+        .chain(sum)
+        .chain([LIR_HALT.clone()])
+        .collect()
+}
+
+fn check_spilled_sum(test: &mut EmulatorTest) -> anyhow::Result<()> {
+    test.run_till_halt()?;
+
+    let sum = test
+        .get_var_ignorant(SPILLED_SUM_ID)
+        .context("Variable wasn't found")?;
+    let expected = SPILLED_VAR_COUNT * (SPILLED_VAR_COUNT + 1) / 2;
+
+    if sum != expected {
+        return Err(anyhow!("Sum {} differs from expected {}", sum, expected));
+    }
+
+    Ok(())
+}
+
+#[test]
+fn spilled_variables_use_base_offset_addressing() {
+    let test = Test::new(
+        "spilled_variables_use_base_offset_addressing",
+        spilled_variables_program(),
+    );
+
+    let instructions = Deassembler::new(test.compilation_output.data.iter())
+        .deassemble()
+        .expect("Error deassembling compiled program");
+
+    for kind in [InstructionKind::LoadO, InstructionKind::StoreO] {
+        assert!(instructions
+            .iter()
+            .any(|instruction| instruction.kind == kind));
+    }
+
+    test.emulate_dump_panicking(check_spilled_sum);
+}
+
+#[test]
+fn harvard_layout_places_variables_from_zero() {
+    let test = Test::new_with_layout(
+        "harvard_layout_places_variables_from_zero",
+        MemoryLayout::Harvard,
+        spilled_variables_program(),
+    );
+
+    let instructions = Deassembler::new(test.compilation_output.data.iter())
+        .deassemble()
+        .expect("Error deassembling compiled program");
+
+    // The prologue points the data base register at the start of data memory.
+    assert_eq!(instructions[0].kind, InstructionKind::LoadI);
+    assert_eq!(instructions[0].immediate, Some(0));
+
+    test.emulate_dump_panicking(check_spilled_sum);
+}
+
+#[test]
+fn executable_output_wraps_raw_output() {
+    let program = [
+        LIRInstruction::Const {
+            id: 1,
+            value: LIRValue::Uint16(0x1234),
+        },
+        LIR_HALT.clone(),
+    ];
+
+    let raw = Test::new("executable_output_wraps_raw_output", program.clone())
+        .compilation_output
+        .data;
+
+    let output = STRM1Transformer::new()
+        .with_output_format(OutputFormat::Executable)
+        .runner()
+        .run(program.into_iter().collect())
+        .expect("Error compiling LIR")
+        .data;
+
+    let executable = Executable::parse(&output).expect("Error parsing executable");
+
+    assert_eq!(executable.symbol(ENTRY_SYMBOL), Some(executable.entry));
+    assert_eq!(executable.segments.len(), 1);
+    assert_eq!(executable.segments[0].data, raw);
+    assert!(executable.debug.is_some());
+
+    let mut emulator = Emulator::new(output).expect("Error loading executable");
+    emulator
+        .execute_to_halt()
+        .expect("Error running executable");
+}
+
+#[test]
+fn memory_image_output_loads_like_raw_output() {
+    let program = [
+        LIRInstruction::Const {
+            id: 1,
+            value: LIRValue::Uint16(0x1234),
+        },
+        LIR_HALT.clone(),
+    ];
+
+    let raw = Test::new("memory_image_output_loads_like_raw_output", program.clone())
+        .compilation_output
+        .data;
+
+    for format in [ImageFormat::IntelHex, ImageFormat::Readmemh] {
+        let layout = ImageLayout::new(1, 0x400).expect("Error creating layout");
+
+        let output = STRM1Transformer::new()
+            .with_output_format(OutputFormat::MemoryImage(format, layout))
+            .runner()
+            .run(program.to_vec())
+            .expect("Error compiling LIR")
+            .data;
+
+        let image = memimage::import(&output, format, layout).expect("Error importing image");
+        assert_eq!(image, raw);
+
+        let mut emulator = Emulator::new(image).expect("Error loading image");
+        emulator.execute_to_halt().expect("Error running image");
+    }
+}
+
+#[test]
+fn platform_places_variables_and_reserves_registers() {
+    let mut platform: Platform = PLATFORM.parse().expect("Error parsing platform");
+    platform.reserved_registers = vec![14, 15];
+
+    let data_addr = platform.data_region().addr;
+
+    let test = Test::new_with_platform(
+        "platform_places_variables_and_reserves_registers",
+        platform,
+        spilled_variables_program(),
+    );
+
+    let instructions = Deassembler::new(test.compilation_output.data.iter())
+        .deassemble()
+        .expect("Error deassembling compiled program");
+
+    // Variables go in the data region rather than right after the code.
+    assert_eq!(instructions[0].kind, InstructionKind::LoadI);
+    assert_eq!(instructions[0].immediate, Some(data_addr as u16));
+
+    for instruction in &instructions {
+        for reg in [instruction.reg_a, instruction.reg_b].into_iter().flatten() {
+            assert!(reg < 14, "Reserved register used by {}", instruction);
+        }
+    }
+
+    test.emulate_dump_panicking(check_spilled_sum);
+}
+
+#[test]
+fn code_overflowing_platform_region_is_an_error() {
+    let mut platform: Platform = PLATFORM.parse().expect("Error parsing platform");
+    platform.regions[0].size = 8;
+
+    let result = STRM1Transformer::new()
+        .with_platform(platform)
+        .runner()
+        .run(spilled_variables_program());
+
+    assert!(result.is_err());
+}
@@ -1,15 +1,18 @@
 use lazy_static::lazy_static;
 use libisa::instruction::{kind::InstructionKind, Instruction};
+use libplatform::Platform;
 
 use crate::{
     lir::LIRInstruction,
     transformer::{extra::Extras, runner::TransformerRunnerExt},
 };
 
-use super::STRM1Transformer;
+use super::{MemoryLayout, STRM1Transformer};
 
 mod emulated;
 
+const PLATFORM: &str = include_str!("../../../../../customasm/platform.ron");
+
 lazy_static! {
     static ref LIR_HALT: LIRInstruction = LIRInstruction::NativeMachinecode {
         code: Instruction::new(InstructionKind::Halt).assemble().unwrap()
@@ -18,23 +21,50 @@ lazy_static! {
 
 pub struct Test {
     pub name: &'static str,
+    pub layout: MemoryLayout,
     pub compilation_output: Extras<Vec<u8>>,
 }
 
 impl Test {
     pub fn new<I>(name: &'static str, lir: I) -> Self
+    where
+        I: IntoIterator<Item = LIRInstruction>,
+    {
+        Self::new_with_layout(name, MemoryLayout::default(), lir)
+    }
+
+    pub fn new_with_layout<I>(name: &'static str, layout: MemoryLayout, lir: I) -> Self
+    where
+        I: IntoIterator<Item = LIRInstruction>,
+    {
+        let transformer = STRM1Transformer::new().with_layout(layout);
+        Self::new_with_transformer(name, layout, transformer, lir)
+    }
+
+    pub fn new_with_platform<I>(name: &'static str, platform: Platform, lir: I) -> Self
+    where
+        I: IntoIterator<Item = LIRInstruction>,
+    {
+        let transformer = STRM1Transformer::new().with_platform(platform);
+        Self::new_with_transformer(name, MemoryLayout::default(), transformer, lir)
+    }
+
+    fn new_with_transformer<I>(
+        name: &'static str,
+        layout: MemoryLayout,
+        mut transformer: STRM1Transformer,
+        lir: I,
+    ) -> Self
     where
         I: IntoIterator<Item = LIRInstruction>,
     {
         let lir = lir.into_iter().collect();
 
-        let compilation_output = STRM1Transformer::new()
-            .runner()
-            .run(lir)
-            .expect("Error compiling LIR");
+        let compilation_output = transformer.runner().run(lir).expect("Error compiling LIR");
 
         Self {
             name,
+            layout,
             compilation_output,
         }
     }
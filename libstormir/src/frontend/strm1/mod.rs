@@ -0,0 +1,397 @@
+use std::collections::{BTreeSet, HashMap};
+
+use anyhow::{anyhow, Context};
+use libdeassembler::traversal::Traversal;
+use libisa::{
+    instruction::{kind::InstructionKind, Instruction},
+    Register, Word,
+};
+
+use crate::{
+    lir::{LIRInstruction, LIRLabelId, LIRValue, LIRVarId},
+    transformer::{extra::Extras, Transformer},
+};
+
+#[cfg(test)]
+mod tests;
+
+/// LIR variable IDs of memory cells start from here, the IDs below it being the registers.
+pub const MEMORY_VAR_ID_OFFSET: LIRVarId = 0x1_0000;
+
+/// LIR variable IDs of instruction immediates and jump targets start from here, offset by the address of the
+/// instruction.
+pub const IMMEDIATE_VAR_ID_OFFSET: LIRVarId = 0x2_0000;
+
+pub fn register_var_id(reg: Register) -> LIRVarId {
+    reg as LIRVarId
+}
+
+pub fn memory_var_id(addr: Word) -> LIRVarId {
+    MEMORY_VAR_ID_OFFSET + addr as LIRVarId
+}
+
+pub fn immediate_var_id(instruction_addr: Word) -> LIRVarId {
+    IMMEDIATE_VAR_ID_OFFSET + instruction_addr as LIRVarId
+}
+
+pub fn label_id(addr: Word) -> LIRLabelId {
+    addr as LIRLabelId
+}
+
+/// Lifts STRM1 machine code back to LIR. Every register and every memory cell accessed through a constant address
+/// turns into a LIR variable, which gets reassigned just like the original register or memory cell.
+///
+/// Only the code reachable from the entry point is lifted. Jump targets turn into LIR labels, so branches keep going to
+/// the same code wherever it ends up once compiled again. Jumps through registers that aren't known constants can't be
+/// lifted, as their targets are machine code addresses.
+#[derive(Debug, Default)]
+pub struct STRM1LiftTransformer {
+    entry: Word,
+}
+
+/// What is known about the machine state at some point of the straight-line code being lifted.
+#[derive(Debug, Default)]
+struct LiftState {
+    /// Registers known to contain a constant, which is how memory addresses are resolved.
+    constants: HashMap<Register, Word>,
+
+    /// The variable whose value the zero flag currently reflects.
+    flags_var: Option<LIRVarId>,
+}
+
+/// Variables read by the lifted code before anything is assigned to them, which need their initial value defined.
+#[derive(Debug, Default)]
+struct Uninitialized {
+    assigned: BTreeSet<LIRVarId>,
+    registers: BTreeSet<Register>,
+    memory_cells: BTreeSet<Word>,
+}
+
+impl STRM1LiftTransformer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_entry(mut self, entry: Word) -> Self {
+        self.entry = entry;
+        self
+    }
+}
+
+impl Transformer for STRM1LiftTransformer {
+    type Input = Vec<u8>;
+    type Output = Vec<LIRInstruction>;
+
+    fn transform(&mut self, input: Extras<Self::Input>) -> anyhow::Result<Extras<Self::Output>> {
+        input.try_map_data(|code| self.lift(&code))
+    }
+}
+
+impl STRM1LiftTransformer {
+    fn lift(&self, code: &[u8]) -> anyhow::Result<Vec<LIRInstruction>> {
+        let traversal = Traversal::from_entry(code, self.entry);
+        let jump_targets: BTreeSet<_> = traversal.jump_targets.values().copied().collect();
+
+        if let Some((addr, target)) = traversal
+            .jump_targets
+            .iter()
+            .find(|(_, target)| !traversal.is_reachable(**target))
+        {
+            return Err(anyhow!(
+                "Jump at 0x{:04x} targets 0x{:04x}, which isn't an instruction",
+                addr,
+                target
+            ));
+        }
+
+        let mut body = Vec::new();
+        let mut state = LiftState::default();
+        let mut uninitialized = Uninitialized::default();
+        let mut fallthrough_addr = None;
+
+        for (&addr, instruction) in &traversal.instructions {
+            // Nothing is known about the machine state when arriving from elsewhere.
+            if jump_targets.contains(&addr) || fallthrough_addr != Some(addr) {
+                state = LiftState::default();
+            }
+
+            if jump_targets.contains(&addr) {
+                body.push(LIRInstruction::Label { id: label_id(addr) });
+            }
+
+            let jump_target = traversal.jump_targets.get(&addr).copied();
+
+            let lifted = Self::lift_instruction(
+                addr,
+                instruction,
+                jump_target,
+                &mut state,
+                &mut uninitialized,
+            )
+            .with_context(|| format!("Lifting '{}' at 0x{:04x}", instruction, addr))?;
+
+            body.extend(lifted);
+
+            let falls_through = instruction.kind != InstructionKind::Halt
+                && (!instruction.kind.is_jump() || instruction.kind.is_conditional_jump());
+
+            fallthrough_addr =
+                falls_through.then(|| addr.wrapping_add(instruction.kind.len_bytes() as Word));
+        }
+
+        // Registers start out zeroed and memory cells with their contents in the binary.
+        let register_inits = uninitialized
+            .registers
+            .iter()
+            .map(|reg| LIRInstruction::Const {
+                id: register_var_id(*reg),
+                value: LIRValue::Uint16(0),
+            });
+
+        let memory_inits = uninitialized.memory_cells.iter().map(|addr| {
+            let addr = *addr as usize;
+            let high = code.get(addr).copied().unwrap_or(0);
+            let low = code.get(addr + 1).copied().unwrap_or(0);
+
+            LIRInstruction::Const {
+                id: memory_var_id(addr as Word),
+                value: LIRValue::Uint16(libisa::bytes_to_word([high, low])),
+            }
+        });
+
+        Ok(register_inits.chain(memory_inits).chain(body).collect())
+    }
+
+    fn lift_instruction(
+        addr: Word,
+        instruction: &Instruction,
+        jump_target: Option<Word>,
+        state: &mut LiftState,
+        uninitialized: &mut Uninitialized,
+    ) -> anyhow::Result<Vec<LIRInstruction>> {
+        let reg_a = instruction.reg_a.unwrap_or(0);
+        let reg_b = instruction.reg_b.unwrap_or(0);
+
+        Ok(match instruction.kind {
+            InstructionKind::Nop => vec![],
+
+            InstructionKind::LoadI => {
+                // SAFETY: Deassembled instructions always have their immediate set if they have one.
+                let value = instruction.immediate.unwrap();
+
+                uninitialized.assign_register(reg_a);
+                state.write(reg_a, Some(value));
+
+                vec![LIRInstruction::Const {
+                    id: register_var_id(reg_a),
+                    value: LIRValue::Uint16(value),
+                }]
+            }
+
+            InstructionKind::Cpy => {
+                uninitialized.read_register(reg_b);
+                uninitialized.assign_register(reg_a);
+                state.write(reg_a, state.constants.get(&reg_b).copied());
+
+                vec![LIRInstruction::Copy {
+                    id: register_var_id(reg_a),
+                    src: register_var_id(reg_b),
+                }]
+            }
+
+            InstructionKind::Load | InstructionKind::LoadO => {
+                let src_addr = state
+                    .constant(reg_b)
+                    .context("src_addr")?
+                    .wrapping_add(instruction.immediate.unwrap_or(0));
+
+                uninitialized.read_memory_cell(src_addr);
+                uninitialized.assign_register(reg_a);
+                state.write(reg_a, None);
+
+                vec![LIRInstruction::Copy {
+                    id: register_var_id(reg_a),
+                    src: memory_var_id(src_addr),
+                }]
+            }
+
+            InstructionKind::Store | InstructionKind::StoreO => {
+                let dest_addr = state
+                    .constant(reg_a)
+                    .context("dest_addr")?
+                    .wrapping_add(instruction.immediate.unwrap_or(0));
+
+                uninitialized.read_register(reg_b);
+                uninitialized.assign_memory_cell(dest_addr);
+
+                vec![LIRInstruction::Copy {
+                    id: memory_var_id(dest_addr),
+                    src: register_var_id(reg_b),
+                }]
+            }
+
+            InstructionKind::Add | InstructionKind::Sub => {
+                let (id, a, b) = (
+                    register_var_id(reg_a),
+                    register_var_id(reg_a),
+                    register_var_id(reg_b),
+                );
+
+                uninitialized.read_register(reg_a);
+                uninitialized.read_register(reg_b);
+                state.write(reg_a, None);
+                state.flags_var = Some(id);
+
+                match instruction.kind {
+                    InstructionKind::Add => vec![LIRInstruction::Add { id, a, b }],
+                    _ => vec![LIRInstruction::Sub { id, a, b }],
+                }
+            }
+
+            InstructionKind::AddI | InstructionKind::SubI => {
+                // SAFETY: Deassembled instructions always have their immediate set if they have one.
+                let value = instruction.immediate.unwrap();
+
+                // Every immediate gets a variable of its own, assigned once so the backend can turn it back into one.
+                let (id, a, b) = (
+                    register_var_id(reg_a),
+                    register_var_id(reg_a),
+                    immediate_var_id(addr),
+                );
+
+                uninitialized.read_register(reg_a);
+                state.write(reg_a, None);
+                state.flags_var = Some(id);
+
+                let immediate = LIRInstruction::Const {
+                    id: b,
+                    value: LIRValue::Uint16(value),
+                };
+
+                match instruction.kind {
+                    InstructionKind::AddI => vec![immediate, LIRInstruction::Add { id, a, b }],
+                    _ => vec![immediate, LIRInstruction::Sub { id, a, b }],
+                }
+            }
+
+            InstructionKind::Jmp
+            | InstructionKind::JmpI
+            | InstructionKind::JmpR
+            | InstructionKind::JmpZ
+            | InstructionKind::JmpZI
+            | InstructionKind::JmpZR => {
+                // Jumps through registers jump to the address loaded to them, which is what gets labeled instead.
+                let target = jump_target.context("Jump target isn't a known constant")?;
+                let target_var = immediate_var_id(addr);
+
+                let immediate = LIRInstruction::Const {
+                    id: target_var,
+                    value: LIRValue::Label(label_id(target)),
+                };
+
+                let branch = match instruction.kind {
+                    InstructionKind::Jmp | InstructionKind::JmpI | InstructionKind::JmpR => {
+                        LIRInstruction::Branch { addr: target_var }
+                    }
+                    _ => LIRInstruction::BranchZero {
+                        addr: target_var,
+                        test: state
+                            .flags_var
+                            .ok_or_else(|| anyhow!("Unknown source of the zero flag"))?,
+                    },
+                };
+
+                vec![immediate, branch]
+            }
+
+            // Anding a register with itself only loads its flags, which is how zero tests are done.
+            InstructionKind::And if reg_a == reg_b => {
+                uninitialized.read_register(reg_a);
+                state.flags_var = Some(register_var_id(reg_a));
+
+                vec![]
+            }
+
+            InstructionKind::Halt => vec![LIRInstruction::NativeMachinecode {
+                code: instruction.assemble()?,
+            }],
+
+            InstructionKind::JmpC
+            | InstructionKind::JmpCI
+            | InstructionKind::JmpCR
+            | InstructionKind::AddC
+            | InstructionKind::SubC
+            | InstructionKind::And
+            | InstructionKind::AndI
+            | InstructionKind::LoadH
+            | InstructionKind::LoadL
+            | InstructionKind::StoreH
+            | InstructionKind::StoreL
+            | InstructionKind::LoadHO
+            | InstructionKind::LoadLO
+            | InstructionKind::StoreHO
+            | InstructionKind::StoreLO
+            | InstructionKind::JmpN
+            | InstructionKind::JmpO
+            | InstructionKind::JmpL
+            | InstructionKind::JmpNI
+            | InstructionKind::JmpOI
+            | InstructionKind::JmpLI
+            | InstructionKind::JmpNR
+            | InstructionKind::JmpOR
+            | InstructionKind::JmpLR
+            | InstructionKind::GetF
+            | InstructionKind::SetF
+            | InstructionKind::Cmp
+            | InstructionKind::CmpI
+            | InstructionKind::Sys
+            | InstructionKind::Sret
+            | InstructionKind::GetCr
+            | InstructionKind::SetCr => return Err(anyhow!("No LIR equivalent")),
+        })
+    }
+}
+
+impl LiftState {
+    /// Record a write to the register, along with its value if it's a known constant.
+    fn write(&mut self, reg: Register, constant: Option<Word>) {
+        match constant {
+            Some(constant) => self.constants.insert(reg, constant),
+            None => self.constants.remove(&reg),
+        };
+
+        // The flags no longer reflect the register once it's overwritten.
+        if self.flags_var == Some(register_var_id(reg)) {
+            self.flags_var = None;
+        }
+    }
+
+    fn constant(&self, reg: Register) -> anyhow::Result<Word> {
+        self.constants
+            .get(&reg)
+            .copied()
+            .ok_or_else(|| anyhow!("Address in register {} isn't a known constant", reg))
+    }
+}
+
+impl Uninitialized {
+    fn assign_register(&mut self, reg: Register) {
+        self.assigned.insert(register_var_id(reg));
+    }
+
+    fn assign_memory_cell(&mut self, addr: Word) {
+        self.assigned.insert(memory_var_id(addr));
+    }
+
+    fn read_register(&mut self, reg: Register) {
+        if !self.assigned.contains(&register_var_id(reg)) {
+            self.registers.insert(reg);
+        }
+    }
+
+    fn read_memory_cell(&mut self, addr: Word) {
+        if !self.assigned.contains(&memory_var_id(addr)) {
+            self.memory_cells.insert(addr);
+        }
+    }
+}
@@ -0,0 +1,173 @@
+use libisa::instruction::{kind::InstructionKind, Instruction};
+
+use crate::{
+    lir::{LIRInstruction, LIRValue},
+    transformer::runner::TransformerRunnerExt,
+};
+
+use super::{immediate_var_id, label_id, memory_var_id, register_var_id, STRM1LiftTransformer};
+
+fn lift(instructions: &[Instruction]) -> anyhow::Result<Vec<LIRInstruction>> {
+    let code = libisa::instruction::assembler::assemble(instructions.iter().copied())
+        .unwrap()
+        .machine_code;
+
+    Ok(STRM1LiftTransformer::new().runner().run(code)?.data)
+}
+
+#[test]
+fn registers_and_memory_cells_become_variables() {
+    let lir = lift(&[
+        Instruction::new(InstructionKind::LoadI)
+            .with_reg_a(0)
+            .with_immediate(0x100),
+        Instruction::new(InstructionKind::Store)
+            .with_reg_a(0)
+            .with_reg_b(1),
+        Instruction::new(InstructionKind::Load)
+            .with_reg_a(2)
+            .with_reg_b(0),
+        Instruction::new(InstructionKind::Add)
+            .with_reg_a(2)
+            .with_reg_b(2),
+        Instruction::new(InstructionKind::Halt),
+    ])
+    .unwrap();
+
+    let (reg_0, reg_1, reg_2) = (register_var_id(0), register_var_id(1), register_var_id(2));
+
+    assert_eq!(
+        lir[..lir.len() - 1],
+        [
+            // Register 1 is read before being assigned, so it gets its initial value.
+            LIRInstruction::Const {
+                id: reg_1,
+                value: LIRValue::Uint16(0)
+            },
+            LIRInstruction::Const {
+                id: reg_0,
+                value: LIRValue::Uint16(0x100)
+            },
+            LIRInstruction::Copy {
+                id: memory_var_id(0x100),
+                src: reg_1
+            },
+            LIRInstruction::Copy {
+                id: reg_2,
+                src: memory_var_id(0x100)
+            },
+            LIRInstruction::Add {
+                id: reg_2,
+                a: reg_2,
+                b: reg_2
+            },
+        ]
+    );
+
+    assert!(matches!(
+        lir.last(),
+        Some(LIRInstruction::NativeMachinecode { .. })
+    ));
+}
+
+#[test]
+fn loadi_and_jmpz_become_branch_zero() {
+    let lir = lift(&[
+        Instruction::new(InstructionKind::LoadI)
+            .with_reg_a(0)
+            .with_immediate(8),
+        Instruction::new(InstructionKind::And)
+            .with_reg_a(1)
+            .with_reg_b(1),
+        Instruction::new(InstructionKind::JmpZ).with_reg_a(0),
+        Instruction::new(InstructionKind::Halt),
+    ])
+    .unwrap();
+
+    // The jump at 6 goes to the label of the halt at 8 rather than to the address loaded.
+    let branch = [
+        LIRInstruction::Const {
+            id: immediate_var_id(6),
+            value: LIRValue::Label(label_id(8)),
+        },
+        LIRInstruction::BranchZero {
+            addr: immediate_var_id(6),
+            test: register_var_id(1),
+        },
+        LIRInstruction::Label { id: label_id(8) },
+    ];
+
+    assert!(lir.windows(branch.len()).any(|window| window == branch));
+}
+
+#[test]
+fn jumps_through_unknown_registers_are_errors() {
+    let lifted = lift(&[
+        Instruction::new(InstructionKind::Jmp).with_reg_a(0),
+        Instruction::new(InstructionKind::Halt),
+    ]);
+
+    assert!(lifted.is_err());
+}
+
+#[test]
+fn unknown_addresses_are_errors() {
+    let lifted = lift(&[
+        Instruction::new(InstructionKind::Load)
+            .with_reg_a(0)
+            .with_reg_b(1),
+        Instruction::new(InstructionKind::Halt),
+    ]);
+
+    assert!(lifted.is_err());
+}
+
+#[test]
+fn immediates_get_variables_of_their_own() {
+    let lir = lift(&[
+        Instruction::new(InstructionKind::AddI)
+            .with_reg_a(0)
+            .with_immediate(3),
+        Instruction::new(InstructionKind::Halt),
+    ])
+    .unwrap();
+
+    assert_eq!(
+        lir[1..3],
+        [
+            LIRInstruction::Const {
+                id: immediate_var_id(0),
+                value: LIRValue::Uint16(3)
+            },
+            LIRInstruction::Add {
+                id: register_var_id(0),
+                a: register_var_id(0),
+                b: immediate_var_id(0)
+            },
+        ]
+    );
+}
+
+#[test]
+fn relative_jumps_branch_to_absolute_targets() {
+    let lir = lift(&[
+        Instruction::new(InstructionKind::JmpR).with_immediate(2),
+        Instruction::new(InstructionKind::Nop),
+        Instruction::new(InstructionKind::Halt),
+    ])
+    .unwrap();
+
+    assert_eq!(
+        lir[..3],
+        [
+            LIRInstruction::Const {
+                id: immediate_var_id(0),
+                value: LIRValue::Label(label_id(6))
+            },
+            LIRInstruction::Branch {
+                addr: immediate_var_id(0)
+            },
+            LIRInstruction::Label { id: label_id(6) },
+        ]
+    );
+}
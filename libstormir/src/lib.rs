@@ -1,5 +1,6 @@
 #![feature(try_trait_v2, assert_matches)]
 
 pub mod backend;
+pub mod frontend;
 pub mod lir;
 pub mod transformer;
@@ -1,6 +1,7 @@
 pub mod shim;
 
 pub type LIRVarId = u64;
+pub type LIRLabelId = u64;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum LIRInstruction {
@@ -46,6 +47,11 @@ pub enum LIRInstruction {
         b: LIRVarId,
     },
 
+    /// Mark the position of the instruction following it, whose address `LIRValue::Label` values are.
+    Label {
+        id: LIRLabelId,
+    },
+
     /// Native machine code passthrough. May or may not be validated.
     NativeMachinecode {
         code: Vec<u8>,
@@ -56,6 +62,9 @@ pub enum LIRInstruction {
 pub enum LIRValue {
     Uint8(u8),
     Uint16(u16),
+
+    /// Address of the label, only known once the code is laid out.
+    Label(LIRLabelId),
 }
 
 // That use<'_> bound is some black magic that tells Rust that the iterator's lifetime depends on the lir reference.
@@ -0,0 +1,124 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::exit,
+};
+
+use clap::Parser;
+use libisa::{
+    memimage::{self, ImageFormat, ImageLayout},
+    object::ObjectFile,
+};
+use liblinker::{bank::BankDescription, Linker};
+
+#[derive(Parser, Debug)]
+#[command(version, about)]
+pub struct Args {
+    /// Object files to link, their sections being placed in the order given.
+    #[arg(required = true)]
+    object_paths: Vec<PathBuf>,
+
+    /// Customasm source whose #bankdef blocks describe where sections go, like banks.asm.
+    #[arg(short, long)]
+    banks: PathBuf,
+
+    /// Where to write the linked program.
+    #[arg(short, long)]
+    output: PathBuf,
+
+    /// Write where every section and symbol ended up to this file.
+    #[arg(short, long)]
+    map: Option<PathBuf>,
+
+    /// Output a STRM1 executable with a segment per bank instead of a raw image of the output banks.
+    #[arg(short, long, conflicts_with = "format")]
+    executable: bool,
+
+    /// Format of the image of the output banks: bin, ihex or mem for $readmemh. Goes by the output's extension if
+    /// not given, defaulting to bin.
+    #[arg(short, long)]
+    format: Option<ImageFormat>,
+
+    /// Bytes per entry of Intel HEX and $readmemh images, 1 for byte-addressed memories.
+    #[arg(long, default_value_t = ImageLayout::default().width)]
+    image_width: usize,
+
+    /// Address of the first entry of Intel HEX and $readmemh images, in entries.
+    #[arg(long, default_value_t = ImageLayout::default().base_addr)]
+    image_base: u32,
+}
+
+fn main() {
+    let args = Args::parse();
+
+    let banks: BankDescription = read(&args.banks, |bytes| {
+        String::from_utf8_lossy(&bytes)
+            .parse()
+            .map_err(|e| format!("{}", e))
+    });
+
+    let linker = args
+        .object_paths
+        .iter()
+        .fold(Linker::new(banks), |linker, path| {
+            let object = read(path, |bytes| {
+                ObjectFile::parse(&bytes).map_err(|e| format!("{}", e))
+            });
+            linker.with_object(path.to_string_lossy(), object)
+        });
+
+    let output = match linker.link() {
+        Ok(output) => output,
+        Err(errors) => {
+            for error in errors.0 {
+                eprintln!("Error: {}", error);
+            }
+
+            exit(1);
+        }
+    };
+
+    let image = if args.executable {
+        match output.executable().to_bytes() {
+            Ok(bytes) => bytes,
+            Err(e) => fail(&args.output, e),
+        }
+    } else {
+        let format = args.format.unwrap_or_else(|| {
+            args.output
+                .extension()
+                .and_then(|extension| ImageFormat::from_extension(&extension.to_string_lossy()))
+                .unwrap_or_default()
+        });
+
+        match ImageLayout::new(args.image_width, args.image_base)
+            .and_then(|layout| memimage::export(&output.image(), format, layout))
+        {
+            Ok(bytes) => bytes,
+            Err(e) => fail(&args.output, e),
+        }
+    };
+
+    if let Err(e) = fs::write(&args.output, image) {
+        fail(&args.output, e);
+    }
+
+    if let Some(map_path) = &args.map {
+        if let Err(e) = fs::write(map_path, output.symbol_map()) {
+            fail(map_path, e);
+        }
+    }
+}
+
+/// Read and parse the file, exiting on failure.
+fn read<T>(path: &Path, parse: impl FnOnce(Vec<u8>) -> Result<T, String>) -> T {
+    match fs::read(path).map_err(|e| e.to_string()).and_then(parse) {
+        Ok(parsed) => parsed,
+        Err(e) => fail(path, e),
+    }
+}
+
+fn fail(path: &Path, error: impl std::fmt::Display) -> ! {
+    eprintln!("Error with '{}': {}", path.to_string_lossy(), error);
+    exit(1);
+}
@@ -0,0 +1,109 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::exit,
+};
+
+use clap::{Parser, Subcommand};
+use libplatform::Platform;
+
+#[derive(Parser, Debug)]
+#[command(version, about)]
+pub struct Args {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Check the platform description and summarise its memory map.
+    Check { platform_path: PathBuf },
+
+    /// Generate the customasm bank definitions included by rules.asm.
+    Banks {
+        platform_path: PathBuf,
+
+        /// Where to write the banks, standard output if not given.
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+}
+
+fn main() {
+    let args = Args::parse();
+
+    match args.command {
+        Command::Check { platform_path } => {
+            let platform = read_platform(&platform_path);
+
+            for region in &platform.regions {
+                let range = region.range();
+                println!(
+                    "{:04x}-{:04x}  {:<12} {}",
+                    range.start,
+                    range.end - 1,
+                    region.name,
+                    [
+                        (region.name == platform.code, "code"),
+                        (region.name == platform.data, "data"),
+                        (region.output, "output"),
+                    ]
+                    .iter()
+                    .filter(|(is, _)| *is)
+                    .map(|(_, role)| *role)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+                );
+            }
+
+            for range in &platform.mmio {
+                println!(
+                    "{:04x}-{:04x}  {:<12} mmio",
+                    range.addr,
+                    range.range().end - 1,
+                    range.name
+                );
+            }
+
+            println!("Reset PC: 0x{:04x}", platform.reset_pc);
+            println!("Reserved registers: {:?}", platform.reserved_registers);
+        }
+        Command::Banks {
+            platform_path,
+            output,
+        } => {
+            let platform = read_platform(&platform_path);
+            let source_name = platform_path.file_name().map_or_else(
+                || platform_path.to_string_lossy(),
+                |name| name.to_string_lossy(),
+            );
+            let banks = platform.customasm_banks(&source_name);
+
+            match output {
+                Some(output) => {
+                    if let Err(e) = fs::write(&output, banks) {
+                        fail(&output, e);
+                    }
+                }
+                None => print!("{}", banks),
+            }
+        }
+    }
+}
+
+fn read_platform(path: &Path) -> Platform {
+    match fs::read_to_string(path)
+        .map_err(|e| e.to_string())
+        .and_then(|text| {
+            text.parse()
+                .map_err(|e: libplatform::PlatformError| e.to_string())
+        }) {
+        Ok(platform) => platform,
+        Err(e) => fail(path, e),
+    }
+}
+
+fn fail(path: &Path, error: impl std::fmt::Display) -> ! {
+    eprintln!("Error with '{}': {}", path.to_string_lossy(), error);
+    exit(1);
+}